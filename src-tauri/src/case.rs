@@ -0,0 +1,146 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The fallback if the case sensitivity of a directory cannot be detected.
+const CASE_SENSITIVE: bool = !(cfg!(target_os = "windows") || cfg!(target_os = "macos"));
+
+/// Detects the case sensitivity of the directories at runtime,
+/// so that case insensitive mounts on Linux and case sensitive volumes on macOS are handled properly.
+#[derive(Default)]
+pub struct CaseSensitivity {
+  directory_map: HashMap<PathBuf, bool>,
+}
+
+impl CaseSensitivity {
+  /// Detects the case sensitivity by looking up an existing name with the case swapped,
+  /// or by creating a temporary probe file if no name has a letter.
+  fn detect(directory: &Path) -> Option<bool> {
+    let names: HashSet<String> = directory
+      .read_dir()
+      .ok()?
+      .filter_map(|entry| entry.ok())
+      .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+      .collect();
+    for name in names.iter() {
+      let swapped_name = swap_case(name.as_str());
+      if swapped_name != *name && !names.contains(swapped_name.as_str()) {
+        return Some(!directory.join(swapped_name).exists());
+      }
+    }
+    let probe_path = directory.join(format!(".BatchFR-{}", uuid::Uuid::new_v4()));
+    fs::write(probe_path.as_path(), []).ok()?;
+    let swapped_probe_path = directory.join(swap_case(probe_path.file_name()?.to_str()?));
+    let case_sensitive = !swapped_probe_path.exists();
+    if let Err(err) = fs::remove_file(probe_path.as_path()) {
+      log::error!("Couldn't remove {} because {}", probe_path.display(), err);
+    }
+    Some(case_sensitive)
+  }
+
+  /// Gets the key of the path for comparison which is lower case in a case insensitive directory.
+  pub fn get_key(&mut self, path: &str) -> String {
+    let parent_path = Path::new(path).parent().unwrap_or(Path::new(""));
+    if self.is_case_sensitive(parent_path) {
+      path.to_string()
+    } else {
+      path.to_lowercase()
+    }
+  }
+
+  /// Checks the case sensitivity of the directory, or its nearest existing ancestor if it doesn't exist yet.
+  pub fn is_case_sensitive(&mut self, directory: &Path) -> bool {
+    let directory = match directory.ancestors().find(|path| path.is_dir()) {
+      Some(directory) => directory,
+      None => return CASE_SENSITIVE,
+    };
+    if let Some(case_sensitive) = self.directory_map.get(directory) {
+      return *case_sensitive;
+    }
+    let case_sensitive = Self::detect(directory).unwrap_or(CASE_SENSITIVE);
+    log::debug!(
+      "Directory {} is case sensitive: {}.",
+      directory.display(),
+      case_sensitive
+    );
+    self.directory_map.insert(directory.to_path_buf(), case_sensitive);
+    case_sensitive
+  }
+}
+
+fn swap_case(text: &str) -> String {
+  text
+    .chars()
+    .map(|c| {
+      if c.is_ascii_lowercase() {
+        c.to_ascii_uppercase()
+      } else {
+        c.to_ascii_lowercase()
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_temp_path() -> PathBuf {
+    let temp_path = std::env::temp_dir().join(format!("BatchFR-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(temp_path.as_path()).unwrap();
+    temp_path
+  }
+
+  #[test]
+  fn test_detect() {
+    let temp_path = new_temp_path();
+    // The empty directory is detected by the probe file which is removed afterwards.
+    let case_sensitive = CaseSensitivity::detect(temp_path.as_path());
+    assert!(case_sensitive.is_some());
+    assert_eq!(0, temp_path.read_dir().unwrap().count());
+    fs::write(temp_path.join("abc.txt"), []).unwrap();
+    assert_eq!(
+      Some(!temp_path.join("ABC.TXT").exists()),
+      CaseSensitivity::detect(temp_path.as_path())
+    );
+    assert_eq!(case_sensitive, CaseSensitivity::detect(temp_path.as_path()));
+    assert_eq!(None, CaseSensitivity::detect(temp_path.join("missing").as_path()));
+    fs::remove_dir_all(temp_path).unwrap();
+  }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn test_get_key_in_case_sensitive_directory() {
+    let temp_path = new_temp_path();
+    // The names with both cases fall back to the probe file.
+    fs::write(temp_path.join("a.txt"), []).unwrap();
+    fs::write(temp_path.join("A.TXT"), []).unwrap();
+    assert_eq!(Some(true), CaseSensitivity::detect(temp_path.as_path()));
+    let mut case_sensitivity = CaseSensitivity::default();
+    let path = temp_path.join("Abc.TXT").to_string_lossy().to_string();
+    assert_eq!(path, case_sensitivity.get_key(path.as_str()));
+    // The missing directory takes the case sensitivity of the nearest existing ancestor.
+    let path = temp_path.join("New").join("Abc.TXT").to_string_lossy().to_string();
+    assert_eq!(path, case_sensitivity.get_key(path.as_str()));
+    assert_eq!(Some(&true), case_sensitivity.directory_map.get(temp_path.as_path()));
+    assert_eq!(1, case_sensitivity.directory_map.len());
+    fs::remove_dir_all(temp_path).unwrap();
+  }
+}
@@ -0,0 +1,223 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use tauri_plugin_cli::Matches;
+
+use crate::config;
+use crate::controller;
+use crate::protocol;
+use crate::rules;
+
+fn get_strings(matches: &Matches, name: &str) -> Vec<String> {
+  match matches.args.get(name).map(|arg| &arg.value) {
+    Some(serde_json::Value::String(value)) => vec![value.clone()],
+    Some(serde_json::Value::Array(values)) => values
+      .iter()
+      .filter_map(|value| value.as_str().map(|value| value.to_string()))
+      .collect(),
+    _ => Vec::new(),
+  }
+}
+
+/// Creates the item of the file or directory to be scanned with the relative path resolved against the current directory,
+/// as the target paths must be absolute.
+fn new_root_item(path: &str) -> Result<protocol::Item> {
+  let path = std::path::absolute(path)
+    .map_err(anyhow::Error::msg)?
+    .to_string_lossy()
+    .to_string();
+  Ok(protocol::Item {
+    source_path: path.clone(),
+    target_path: path,
+    item_type: protocol::ItemType::Unknown,
+    metadata: BTreeMap::new(),
+    companions: Vec::new(),
+  })
+}
+
+fn parse_option(plugin: &config::ConfigPlugin, text: &str) -> Result<(String, serde_json::Value)> {
+  let (name, value) = text
+    .split_once('=')
+    .ok_or_else(|| anyhow::anyhow!("Option {} must be in the form of name=value.", text))?;
+  let option = plugin
+    .options
+    .iter()
+    .find(|option| option.get_name() == name)
+    .ok_or_else(|| anyhow::anyhow!("Option {} is not defined in plugin {}.", name, plugin.name))?;
+  let value = match option {
+    config::ConfigPluginOption::Boolean(_) => serde_json::Value::from(
+      value
+        .parse::<bool>()
+        .map_err(|_| anyhow::anyhow!("Option {} must be a boolean.", name))?,
+    ),
+    config::ConfigPluginOption::Double(_) => serde_json::Value::from(
+      value
+        .parse::<f64>()
+        .map_err(|_| anyhow::anyhow!("Option {} must be a number.", name))?,
+    ),
+    config::ConfigPluginOption::Integer(_) => serde_json::Value::from(
+      value
+        .parse::<i64>()
+        .map_err(|_| anyhow::anyhow!("Option {} must be an integer.", name))?,
+    ),
+    _ => serde_json::Value::from(value),
+  };
+  Ok((name.to_string(), value))
+}
+
+/// Runs a native plugin against the given file or directory without the GUI.
+/// A template alone runs the Template plugin.
+/// It returns false if the command line doesn't ask for a native plugin.
+pub fn run(matches: &Matches) -> Result<bool> {
+  let template = get_strings(matches, "template").into_iter().next();
+  let plugin_id_or_name = match (get_strings(matches, "plugin").into_iter().next(), &template) {
+    (Some(plugin_id_or_name), _) => plugin_id_or_name,
+    (None, Some(_)) => rules::RULE_TEMPLATE.to_string(),
+    (None, None) => return Ok(false),
+  };
+  let plugin = rules::NATIVE_PLUGINS
+    .iter()
+    .find(|plugin| plugin.id == plugin_id_or_name || plugin.name.eq_ignore_ascii_case(plugin_id_or_name.as_str()))
+    .ok_or_else(|| anyhow::anyhow!("Native plugin {} is not found.", plugin_id_or_name))?;
+  let path = get_strings(matches, "fileOrDirectory")
+    .into_iter()
+    .next()
+    .ok_or_else(|| anyhow::anyhow!("File or directory is not specified."))?;
+  let mut options: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+  for text in get_strings(matches, "option") {
+    let (name, value) = parse_option(plugin, text.as_str())?;
+    options.insert(name, value);
+  }
+  if let Some(template) = template {
+    options.insert("template".to_string(), serde_json::Value::from(template));
+  }
+  let dry_run = matches
+    .args
+    .get("dry-run")
+    .and_then(|arg| arg.value.as_bool())
+    .unwrap_or(false);
+  tauri::async_runtime::block_on(async move {
+    let config = controller::get_config().await?;
+    let extensions = if config.filter_by_extensions {
+      config.extensions.clone()
+    } else {
+      Vec::new()
+    };
+    let items = controller::scan_items(
+      vec![new_root_item(path.as_str())?],
+      protocol::ScanOptions {
+        depth: config.depth,
+        extensions,
+        group_companions: config.group_companions,
+        hashes: config.hashes.clone(),
+        include_directory: config.include_directories,
+        metadata: config.metadata.clone(),
+        normalization: config.normalization,
+      },
+      |_| {},
+    )
+    .await?;
+    let items = controller::run_native_plugin(plugin.id.clone(), options, items).await?;
+    if dry_run {
+      items
+        .iter()
+        .filter(|item| item.source_path != item.target_path)
+        .for_each(|item| println!("{} -> {}", item.source_path, item.target_path));
+    } else {
+      let result = controller::rename_items(
+        items,
+        protocol::RenameOptions {
+          normalization: config.normalization,
+          ..Default::default()
+        },
+      )
+      .await?;
+      // The trashed paths are printed so that they can be restored even if the rename failed partially.
+      result
+        .trashed_paths
+        .iter()
+        .for_each(|path| println!("Trashed {}", path));
+      if let Some(error) = result.error {
+        return Err(anyhow::anyhow!(
+          "Renamed {} item(s) and trashed {} item(s) before the failure because {}",
+          result.count,
+          result.trashed_paths.len(),
+          error
+        ));
+      }
+      println!("Renamed {} item(s) successfully.", result.count);
+    }
+    Ok::<(), anyhow::Error>(())
+  })?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::path::Path;
+
+  #[test]
+  fn test_new_root_item_resolves_relative_paths() {
+    let current_path = std::env::current_dir().unwrap();
+    let item = new_root_item("music").unwrap();
+    assert_eq!(current_path.join("music").to_string_lossy(), item.source_path);
+    assert_eq!(item.source_path, item.target_path);
+    let absolute_path = current_path.join("a b").to_string_lossy().to_string();
+    assert_eq!(
+      absolute_path,
+      new_root_item(absolute_path.as_str()).unwrap().source_path
+    );
+  }
+
+  #[test]
+  fn test_rename_items_scanned_from_a_relative_root() {
+    let root = format!("test-{}", uuid::Uuid::new_v4());
+    fs::create_dir(root.as_str()).unwrap();
+    fs::write(Path::new(root.as_str()).join("a.txt"), "a").unwrap();
+    let result = tauri::async_runtime::block_on(async {
+      let items = controller::scan_items(
+        vec![new_root_item(root.as_str())?],
+        protocol::ScanOptions {
+          depth: -1,
+          extensions: Vec::new(),
+          group_companions: false,
+          hashes: Vec::new(),
+          include_directory: false,
+          metadata: Vec::new(),
+          normalization: None,
+        },
+        |_| {},
+      )
+      .await?;
+      let mut options: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+      options.insert("prefix".to_string(), serde_json::Value::from("b_"));
+      options.insert("suffix".to_string(), serde_json::Value::from(""));
+      let items = controller::run_native_plugin(rules::RULE_ADD_PREFIX_AND_SUFFIX.to_string(), options, items).await?;
+      controller::rename_items(items, protocol::RenameOptions::default()).await
+    });
+    let renamed = Path::new(root.as_str()).join("b_a.txt").exists();
+    fs::remove_dir_all(root.as_str()).unwrap();
+    let result = result.unwrap();
+    assert_eq!(None, result.error);
+    assert_eq!(1, result.count);
+    assert!(renamed);
+  }
+}
@@ -0,0 +1,255 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::protocol;
+use crate::video;
+
+pub const ARTWORK_EXTENSIONS: [&str; 3] = ["jpeg", "jpg", "png"];
+pub const COMPANION_EXTENSIONS: [&str; 10] = ["ass", "idx", "nfo", "srt", "ssa", "sub", "sup", "tbn", "vtt", "xmp"];
+
+const ARTWORK_SUFFIXES: [&str; 3] = ["fanart", "poster", "thumb"];
+
+/// Expands the companions into items renamed along with the primary file, keeping the suffixes
+/// like the language tags.
+pub fn expand_companions(items: Vec<protocol::Item>) -> Vec<protocol::Item> {
+  let mut new_items: Vec<protocol::Item> = Vec::new();
+  for item in items {
+    let companion_items: Vec<protocol::Item> = if item.source_path == item.target_path {
+      Vec::new()
+    } else if item.target_path.is_empty() {
+      // The companions are sent to the trash along with the primary file.
+      item
+        .companions
+        .iter()
+        .map(|companion| protocol::Item {
+          source_path: companion.clone(),
+          target_path: String::new(),
+          item_type: protocol::ItemType::File,
+          metadata: BTreeMap::new(),
+          companions: Vec::new(),
+        })
+        .collect()
+    } else {
+      let target_path = Path::new(item.target_path.as_str());
+      let target_parent_path = target_path.parent().unwrap_or(Path::new(""));
+      let target_stem = target_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+      item
+        .companions
+        .iter()
+        .filter_map(|companion| {
+          get_companion_suffix(item.source_path.as_str(), companion.as_str()).map(|suffix| protocol::Item {
+            source_path: companion.clone(),
+            target_path: target_parent_path
+              .join(format!("{}{}", target_stem, suffix))
+              .to_string_lossy()
+              .to_string(),
+            item_type: protocol::ItemType::File,
+            metadata: BTreeMap::new(),
+            companions: Vec::new(),
+          })
+        })
+        .collect()
+    };
+    new_items.push(item);
+    new_items.extend(companion_items);
+  }
+  new_items
+}
+
+fn get_extension(path: &Path) -> String {
+  path
+    .extension()
+    .map(|extension| extension.to_string_lossy().to_lowercase())
+    .unwrap_or_default()
+}
+
+/// Checks whether the suffix is an artwork suffix like "-poster.jpg" or ".fanart.png".
+fn is_artwork_suffix(suffix: &str) -> bool {
+  let stem = Path::new(suffix)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  ARTWORK_SUFFIXES.iter().any(|artwork_suffix| {
+    stem.ends_with(&format!("-{}", artwork_suffix)) || stem.ends_with(&format!(".{}", artwork_suffix))
+  })
+}
+
+/// Gets the suffix of the companion after the stem of the primary file like ".en.srt" or "-poster.jpg".
+pub fn get_companion_suffix(primary_path: &str, companion_path: &str) -> Option<String> {
+  let stem = Path::new(primary_path).file_stem()?.to_str()?;
+  let file_name = Path::new(companion_path).file_name()?.to_str()?;
+  file_name
+    .strip_prefix(stem)
+    .filter(|suffix| suffix.starts_with(['.', '-']))
+    .map(|suffix| suffix.to_string())
+}
+
+/// Moves the companion files like subtitles, nfo files and posters sharing the base name of a primary file
+/// in the same directory into the companions of the primary item.
+/// An image is only a companion as the artwork of a video like "-poster.jpg", otherwise it is a primary file.
+/// The longest matching base name wins, and the companions without a primary file are kept as they are.
+pub fn group_companions(items: Vec<protocol::Item>) -> Vec<protocol::Item> {
+  let is_companion = |item: &protocol::Item| {
+    item.item_type == protocol::ItemType::File
+      && COMPANION_EXTENSIONS.contains(&get_extension(Path::new(item.source_path.as_str())).as_str())
+  };
+  let is_artwork = |item: &protocol::Item| {
+    item.item_type == protocol::ItemType::File
+      && ARTWORK_EXTENSIONS.contains(&get_extension(Path::new(item.source_path.as_str())).as_str())
+  };
+  // The primary files are indexed by the parent directory.
+  let mut primary_map: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+  for (index, item) in items.iter().enumerate() {
+    if item.item_type == protocol::ItemType::File && !is_companion(item) {
+      let parent = Path::new(item.source_path.as_str())
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default();
+      primary_map.entry(parent).or_default().push(index);
+    }
+  }
+  let mut companion_map: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+  let mut companion_set: HashSet<usize> = HashSet::new();
+  // The artwork is grouped first so that it is never the primary file of the other companions.
+  for artwork_pass in [true, false] {
+    for (index, item) in items.iter().enumerate() {
+      let artwork = is_artwork(item);
+      if artwork != artwork_pass || !(artwork || is_companion(item)) {
+        continue;
+      }
+      let parent = Path::new(item.source_path.as_str())
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default();
+      let primary_index = primary_map.get(&parent).and_then(|indexes| {
+        indexes
+          .iter()
+          .filter(|primary_index| **primary_index != index && !companion_set.contains(*primary_index))
+          .filter_map(|primary_index| {
+            let primary_path = items[*primary_index].source_path.as_str();
+            get_companion_suffix(primary_path, item.source_path.as_str())
+              .filter(|suffix| !artwork || (is_artwork_suffix(suffix) && video::is_video_file(Path::new(primary_path))))
+              .map(|suffix| (*primary_index, suffix.len()))
+          })
+          .min_by_key(|(_, length)| *length)
+          .map(|(primary_index, _)| primary_index)
+      });
+      if let Some(primary_index) = primary_index {
+        companion_map
+          .entry(primary_index)
+          .or_default()
+          .push(item.source_path.clone());
+        companion_set.insert(index);
+      }
+    }
+  }
+  items
+    .into_iter()
+    .enumerate()
+    .filter(|(index, _)| !companion_set.contains(index))
+    .map(|(index, mut item)| {
+      if let Some(companions) = companion_map.remove(&index) {
+        item.companions = companions;
+      }
+      item
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn group(paths: &[&str]) -> Vec<(String, Vec<String>)> {
+    group_companions(paths.iter().map(|path| protocol::Item::new_file(path)).collect())
+      .into_iter()
+      .map(|item| (item.source_path, item.companions))
+      .collect()
+  }
+
+  fn new_group(path: &str, companions: &[&str]) -> (String, Vec<String>) {
+    (
+      path.to_string(),
+      companions.iter().map(|companion| companion.to_string()).collect(),
+    )
+  }
+
+  #[test]
+  fn test_group_companions_groups_the_video_companions() {
+    assert_eq!(
+      vec![
+        new_group(
+          "/x/Movie.mkv",
+          &["/x/Movie-poster.jpg", "/x/Movie.en.srt", "/x/Movie.nfo"]
+        ),
+        new_group("/x/Movie 2.mkv", &["/x/Movie 2.srt"]),
+        new_group("/x/Other.srt", &[]),
+        new_group("/y/Movie.nfo", &[]),
+      ],
+      group(&[
+        "/x/Movie.mkv",
+        "/x/Movie.en.srt",
+        "/x/Movie 2.mkv",
+        "/x/Movie 2.srt",
+        "/x/Movie.nfo",
+        "/x/Movie-poster.jpg",
+        "/x/Other.srt",
+        "/y/Movie.nfo",
+      ])
+    );
+  }
+
+  #[test]
+  fn test_group_companions_keeps_the_photos_as_primary_files() {
+    assert_eq!(
+      vec![
+        new_group("/x/photo.jpg", &["/x/photo.xmp"]),
+        new_group("/x/Movie.mp4", &["/x/Movie-fanart.png", "/x/Movie-fanart.xmp"]),
+        new_group("/x/Movie.jpg", &[]),
+      ],
+      group(&[
+        "/x/photo.jpg",
+        "/x/photo.xmp",
+        "/x/Movie.mp4",
+        "/x/Movie.jpg",
+        "/x/Movie-fanart.png",
+        "/x/Movie-fanart.xmp",
+      ])
+    );
+  }
+
+  #[test]
+  fn test_expand_companions_keeps_the_suffixes() {
+    let mut item = protocol::Item::new_file("/x/Movie.mkv");
+    item.target_path = "/y/Film.mkv".to_string();
+    item.companions = vec!["/x/Movie.en.srt".to_string(), "/x/Movie-poster.jpg".to_string()];
+    let target_paths: Vec<String> = expand_companions(vec![item])
+      .into_iter()
+      .map(|item| item.target_path)
+      .collect();
+    assert_eq!(
+      vec!["/y/Film.mkv", "/y/Film.en.srt", "/y/Film-poster.jpg"],
+      target_paths
+    );
+  }
+}
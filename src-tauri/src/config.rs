@@ -18,11 +18,13 @@
 use anyhow::{Error, Result};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
 
 use crate::plugins;
+use crate::protocol;
 
 static mut CONFIG: Lazy<Config> = Lazy::new(|| Config::new());
 
@@ -32,8 +34,18 @@ pub struct Config {
   pub extensions: Vec<String>,
   #[serde(rename = "filterByExtensions")]
   pub filter_by_extensions: bool,
+  #[serde(default, rename = "groupCompanions")]
+  pub group_companions: bool,
+  #[serde(default)]
+  pub hashes: Vec<protocol::HashAlgorithm>,
   #[serde(rename = "includeDirectories")]
   pub include_directories: bool,
+  #[serde(default)]
+  pub metadata: Vec<protocol::MetadataType>,
+  #[serde(default)]
+  pub normalization: Option<protocol::UnicodeNormalization>,
+  #[serde(default)]
+  pub pipelines: Vec<ConfigPipeline>,
   pub plugins: Vec<ConfigPlugin>,
 }
 
@@ -47,7 +59,12 @@ impl Default for Config {
         .map(|s| s.trim().to_string())
         .collect(),
       filter_by_extensions: true,
+      group_companions: false,
+      hashes: Vec::new(),
       include_directories: false,
+      metadata: Vec::new(),
+      normalization: None,
+      pipelines: Vec::new(),
       plugins: plugins::BUILT_IN_PLUGINS.to_vec(),
     }
   }
@@ -74,6 +91,12 @@ impl Config {
     config_path_buf
   }
 
+  pub fn get_plugins_path_buf() -> PathBuf {
+    let mut plugins_path_buf = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    plugins_path_buf.push("plugins");
+    plugins_path_buf
+  }
+
   fn load(path: PathBuf) -> Self {
     let cloned_path = path.clone();
     let path_string = cloned_path.to_str().unwrap();
@@ -103,12 +126,32 @@ impl Config {
   }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPipeline {
+  pub description: String,
+  pub id: String,
+  pub name: String,
+  pub steps: Vec<ConfigPipelineStep>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPipelineStep {
+  #[serde(default)]
+  pub options: BTreeMap<String, serde_json::Value>,
+  #[serde(rename = "pluginId")]
+  pub plugin_id: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ConfigPlugin {
   pub code: String,
   pub description: String,
+  #[serde(default)]
+  pub external: bool,
   pub id: String,
   pub name: String,
+  #[serde(default)]
+  pub native: bool,
   pub options: Vec<ConfigPluginOption>,
 }
 
@@ -116,15 +159,155 @@ pub struct ConfigPlugin {
 #[serde(tag = "type")]
 pub enum ConfigPluginOption {
   Boolean(ConfigPluginOptionBoolean),
+  Date(ConfigPluginOptionDate),
   Double(ConfigPluginOptionDouble),
+  Enum(ConfigPluginOptionEnum),
   Integer(ConfigPluginOptionInteger),
+  Path(ConfigPluginOptionPath),
+  Regex(ConfigPluginOptionRegex),
   String(ConfigPluginOptionString),
+  Text(ConfigPluginOptionText),
+}
+
+impl ConfigPluginOption {
+  pub fn get_default_value(&self) -> serde_json::Value {
+    match self {
+      Self::Boolean(option) => serde_json::Value::from(option.default_value),
+      Self::Date(option) => serde_json::Value::from(option.default_value.clone()),
+      Self::Double(option) => serde_json::Value::from(option.default_value),
+      Self::Enum(option) => serde_json::Value::from(option.default_value.clone()),
+      Self::Integer(option) => serde_json::Value::from(option.default_value),
+      Self::Path(option) => serde_json::Value::from(option.default_value.clone()),
+      Self::Regex(option) => serde_json::Value::from(option.default_value.clone()),
+      Self::String(option) => serde_json::Value::from(option.default_value.clone()),
+      Self::Text(option) => serde_json::Value::from(option.default_value.clone()),
+    }
+  }
+
+  pub fn get_name(&self) -> &str {
+    match self {
+      Self::Boolean(option) => option.name.as_str(),
+      Self::Date(option) => option.name.as_str(),
+      Self::Double(option) => option.name.as_str(),
+      Self::Enum(option) => option.name.as_str(),
+      Self::Integer(option) => option.name.as_str(),
+      Self::Path(option) => option.name.as_str(),
+      Self::Regex(option) => option.name.as_str(),
+      Self::String(option) => option.name.as_str(),
+      Self::Text(option) => option.name.as_str(),
+    }
+  }
+
+  pub fn validate(&self) -> Result<()> {
+    if self.get_name().is_empty() {
+      return Err(anyhow::anyhow!("Option name is empty."));
+    }
+    match self {
+      Self::Double(option) => {
+        // NaN is neither greater nor less than anything, so it would pass the range checks.
+        if let Some(value) = [option.min, option.max, option.step]
+          .into_iter()
+          .flatten()
+          .find(|value| !value.is_finite())
+        {
+          return Err(anyhow::anyhow!(
+            "Option {} has a non-finite bound {}.",
+            option.name,
+            value
+          ));
+        }
+        validate_range(option.min, option.max, option.step)?
+      }
+      Self::Enum(option) if option.values.is_empty() => {
+        return Err(anyhow::anyhow!("Option {} doesn't have any values.", option.name));
+      }
+      Self::Integer(option) => validate_range(option.min, option.max, option.step)?,
+      _ => {}
+    }
+    self.validate_value(&self.get_default_value())
+  }
+
+  pub fn validate_value(&self, value: &serde_json::Value) -> Result<()> {
+    let name = self.get_name();
+    match self {
+      Self::Boolean(_) => {
+        if !value.is_boolean() {
+          return Err(anyhow::anyhow!("Option {} must be a boolean.", name));
+        }
+      }
+      Self::Date(_) => {
+        let value = value
+          .as_str()
+          .ok_or_else(|| anyhow::anyhow!("Option {} must be a string.", name))?;
+        if !value.is_empty() {
+          chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|err| anyhow::anyhow!("Option {} has an invalid date {} because {}.", name, value, err))?;
+        }
+      }
+      Self::Double(option) => {
+        let value = value
+          .as_f64()
+          .ok_or_else(|| anyhow::anyhow!("Option {} must be a number.", name))?;
+        validate_value_in_range(name, value, option.min, option.max, option.step)?;
+      }
+      Self::Enum(option) => {
+        let value = value
+          .as_str()
+          .ok_or_else(|| anyhow::anyhow!("Option {} must be a string.", name))?;
+        if !option.values.iter().any(|v| v == value) {
+          return Err(anyhow::anyhow!(
+            "Option {} must be one of {} instead of {}.",
+            name,
+            option.values.join(", "),
+            value
+          ));
+        }
+      }
+      Self::Integer(option) => {
+        let value = value
+          .as_i64()
+          .and_then(|value| i32::try_from(value).ok())
+          .ok_or_else(|| anyhow::anyhow!("Option {} must be an integer.", name))?;
+        validate_value_in_range(
+          name,
+          value as f64,
+          option.min.map(f64::from),
+          option.max.map(f64::from),
+          option.step.map(f64::from),
+        )?;
+      }
+      Self::Regex(_) => {
+        let value = value
+          .as_str()
+          .ok_or_else(|| anyhow::anyhow!("Option {} must be a string.", name))?;
+        regex::Regex::new(value)
+          .map_err(|err| anyhow::anyhow!("Option {} has an invalid regular expression because {}", name, err))?;
+      }
+      Self::Path(_) | Self::String(_) | Self::Text(_) => {
+        if !value.is_string() {
+          return Err(anyhow::anyhow!("Option {} must be a string.", name));
+        }
+      }
+    }
+    Ok(())
+  }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ConfigPluginOptionBoolean {
   #[serde(rename = "defaultValue")]
   pub default_value: bool,
+  #[serde(default)]
+  pub description: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPluginOptionDate {
+  #[serde(rename = "defaultValue")]
+  pub default_value: String,
+  #[serde(default)]
+  pub description: String,
   pub name: String,
 }
 
@@ -132,13 +315,57 @@ pub struct ConfigPluginOptionBoolean {
 pub struct ConfigPluginOptionDouble {
   #[serde(rename = "defaultValue")]
   pub default_value: f64,
+  #[serde(default)]
+  pub description: String,
+  #[serde(default)]
+  pub max: Option<f64>,
+  #[serde(default)]
+  pub min: Option<f64>,
+  pub name: String,
+  #[serde(default)]
+  pub step: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPluginOptionEnum {
+  #[serde(rename = "defaultValue")]
+  pub default_value: String,
+  #[serde(default)]
+  pub description: String,
   pub name: String,
+  pub values: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ConfigPluginOptionInteger {
   #[serde(rename = "defaultValue")]
   pub default_value: i32,
+  #[serde(default)]
+  pub description: String,
+  #[serde(default)]
+  pub max: Option<i32>,
+  #[serde(default)]
+  pub min: Option<i32>,
+  pub name: String,
+  #[serde(default)]
+  pub step: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPluginOptionPath {
+  #[serde(rename = "defaultValue")]
+  pub default_value: String,
+  #[serde(default)]
+  pub description: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPluginOptionRegex {
+  #[serde(rename = "defaultValue")]
+  pub default_value: String,
+  #[serde(default)]
+  pub description: String,
   pub name: String,
 }
 
@@ -146,9 +373,72 @@ pub struct ConfigPluginOptionInteger {
 pub struct ConfigPluginOptionString {
   #[serde(rename = "defaultValue")]
   pub default_value: String,
+  #[serde(default)]
+  pub description: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigPluginOptionText {
+  #[serde(rename = "defaultValue")]
+  pub default_value: String,
+  #[serde(default)]
+  pub description: String,
   pub name: String,
 }
 
+fn validate_range<T>(min: Option<T>, max: Option<T>, step: Option<T>) -> Result<()>
+where
+  T: Copy + Default + PartialOrd + std::fmt::Display,
+{
+  if let (Some(min), Some(max)) = (min, max) {
+    if min > max {
+      return Err(anyhow::anyhow!("Min {} is greater than max {}.", min, max));
+    }
+  }
+  if let Some(step) = step {
+    if step <= T::default() {
+      return Err(anyhow::anyhow!("Step {} must be positive.", step));
+    }
+  }
+  Ok(())
+}
+
+fn validate_value_in_range(
+  name: &str,
+  value: f64,
+  min: Option<f64>,
+  max: Option<f64>,
+  step: Option<f64>,
+) -> Result<()> {
+  if let Some(min) = min {
+    if value < min {
+      return Err(anyhow::anyhow!(
+        "Option {} must be greater than or equal to {}.",
+        name,
+        min
+      ));
+    }
+  }
+  if let Some(max) = max {
+    if value > max {
+      return Err(anyhow::anyhow!(
+        "Option {} must be less than or equal to {}.",
+        name,
+        max
+      ));
+    }
+  }
+  if let Some(step) = step {
+    // The steps are counted from min or 0.
+    let steps = (value - min.unwrap_or_default()) / step;
+    if (steps - steps.round()).abs() > 1e-9 {
+      return Err(anyhow::anyhow!("Option {} must be a multiple of step {}.", name, step));
+    }
+  }
+  Ok(())
+}
+
 pub fn get_config() -> Config {
   unsafe { CONFIG.clone() }
 }
@@ -159,3 +449,154 @@ pub fn set_config(config: Config) -> Result<()> {
   unsafe { CONFIG.clone_from(&config) };
   result
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_option_double(
+    default_value: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    step: Option<f64>,
+  ) -> ConfigPluginOption {
+    ConfigPluginOption::Double(ConfigPluginOptionDouble {
+      default_value,
+      description: String::new(),
+      max,
+      min,
+      name: "d".to_string(),
+      step,
+    })
+  }
+
+  fn new_option_enum(default_value: &str, values: &[&str]) -> ConfigPluginOption {
+    ConfigPluginOption::Enum(ConfigPluginOptionEnum {
+      default_value: default_value.to_string(),
+      description: String::new(),
+      name: "e".to_string(),
+      values: values.iter().map(|value| value.to_string()).collect(),
+    })
+  }
+
+  fn new_option_integer(
+    default_value: i32,
+    min: Option<i32>,
+    max: Option<i32>,
+    step: Option<i32>,
+  ) -> ConfigPluginOption {
+    ConfigPluginOption::Integer(ConfigPluginOptionInteger {
+      default_value,
+      description: String::new(),
+      max,
+      min,
+      name: "i".to_string(),
+      step,
+    })
+  }
+
+  fn get_error(result: Result<()>) -> String {
+    result.unwrap_err().to_string()
+  }
+
+  #[test]
+  fn test_validate_enum() {
+    assert!(new_option_enum("a", &["a", "b"]).validate().is_ok());
+    assert_eq!(
+      "Option e doesn't have any values.",
+      get_error(new_option_enum("", &[]).validate())
+    );
+    assert_eq!(
+      "Option e must be one of a, b instead of c.",
+      get_error(new_option_enum("c", &["a", "b"]).validate())
+    );
+    let option = new_option_enum("a", &["a", "b"]);
+    assert!(option.validate_value(&serde_json::Value::from("b")).is_ok());
+    assert_eq!(
+      "Option e must be a string.",
+      get_error(option.validate_value(&serde_json::Value::from(1)))
+    );
+  }
+
+  #[test]
+  fn test_validate_regex_and_date() {
+    let option = ConfigPluginOption::Regex(ConfigPluginOptionRegex {
+      default_value: "(a".to_string(),
+      description: String::new(),
+      name: "r".to_string(),
+    });
+    assert!(get_error(option.validate()).starts_with("Option r has an invalid regular expression"));
+    assert!(option.validate_value(&serde_json::Value::from("(a)")).is_ok());
+    let option = ConfigPluginOption::Date(ConfigPluginOptionDate {
+      default_value: String::new(),
+      description: String::new(),
+      name: "t".to_string(),
+    });
+    assert!(option.validate().is_ok());
+    assert!(option.validate_value(&serde_json::Value::from("2024-02-29")).is_ok());
+    assert!(get_error(option.validate_value(&serde_json::Value::from("2023-02-29")))
+      .starts_with("Option t has an invalid date"));
+    assert!(get_error(option.validate_value(&serde_json::Value::from("29.02.2024")))
+      .starts_with("Option t has an invalid date"));
+  }
+
+  #[test]
+  fn test_validate_ranges() {
+    assert!(new_option_integer(2, Some(0), Some(10), Some(2)).validate().is_ok());
+    assert_eq!(
+      "Min 10 is greater than max 0.",
+      get_error(new_option_integer(5, Some(10), Some(0), None).validate())
+    );
+    assert_eq!(
+      "Step 0 must be positive.",
+      get_error(new_option_integer(0, None, None, Some(0)).validate())
+    );
+    assert_eq!(
+      "Step -1.5 must be positive.",
+      get_error(new_option_double(0.0, None, None, Some(-1.5)).validate())
+    );
+    for (min, max, step) in [
+      (Some(f64::NAN), None, None),
+      (None, Some(f64::INFINITY), None),
+      (None, None, Some(f64::NAN)),
+    ] {
+      assert!(
+        get_error(new_option_double(0.0, min, max, step).validate()).starts_with("Option d has a non-finite bound")
+      );
+    }
+  }
+
+  #[test]
+  fn test_validate_values_in_range() {
+    let option = new_option_integer(1, Some(1), Some(9), Some(2));
+    assert!(option.validate().is_ok());
+    assert!(option.validate_value(&serde_json::Value::from(5)).is_ok());
+    assert_eq!(
+      "Option i must be greater than or equal to 1.",
+      get_error(option.validate_value(&serde_json::Value::from(-1)))
+    );
+    assert_eq!(
+      "Option i must be less than or equal to 9.",
+      get_error(option.validate_value(&serde_json::Value::from(11)))
+    );
+    assert_eq!(
+      "Option i must be a multiple of step 2.",
+      get_error(option.validate_value(&serde_json::Value::from(4)))
+    );
+    assert_eq!(
+      "Option i must be an integer.",
+      get_error(option.validate_value(&serde_json::Value::from(i64::MAX)))
+    );
+    let option = new_option_double(0.5, Some(0.0), Some(1.0), Some(0.1));
+    assert!(option.validate().is_ok());
+    assert!(option.validate_value(&serde_json::Value::from(0.3)).is_ok());
+    assert_eq!(
+      "Option d must be less than or equal to 1.",
+      get_error(option.validate_value(&serde_json::Value::from(1.5)))
+    );
+    assert_eq!(
+      "Option d must be a number.",
+      get_error(option.validate_value(&serde_json::Value::from("0.5")))
+    );
+  }
+}
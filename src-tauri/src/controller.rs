@@ -16,15 +16,59 @@
 */
 
 use anyhow::Result;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::Path;
 
+use crate::case;
+use crate::companions;
 use crate::config;
+use crate::hashes;
+use crate::metadata;
+use crate::organize;
 use crate::plugins;
 use crate::protocol;
+use crate::rules;
+use crate::sanitize;
+use crate::subtitles;
+use crate::trash;
+use crate::unicode;
 
-const CASE_INSENSITIVE: bool = cfg!(target_os = "windows") || cfg!(target_os = "macos");
+pub async fn export_plugin(id: String, path: String) -> Result<()> {
+  let config = get_config().await?;
+  let plugin = config
+    .plugins
+    .iter()
+    .find(|plugin| plugin.id == id)
+    .ok_or_else(|| anyhow::anyhow!("Plugin {} is not found.", id))?;
+  if plugin.native {
+    return Err(anyhow::anyhow!("Native plugin {} cannot be exported.", plugin.name));
+  }
+  let content = plugins::format_plugin_file(plugin)?;
+  fs::write(path, content).map_err(anyhow::Error::msg)
+}
+
+pub async fn find_duplicates(
+  items: Vec<protocol::Item>,
+  options: protocol::DuplicateOptions,
+) -> Result<Vec<protocol::DuplicateGroup>> {
+  hashes::find_duplicates(&items, &options)
+}
+
+pub async fn find_normalization_duplicates(
+  items: Vec<protocol::Item>,
+) -> Result<Vec<protocol::NormalizationDuplicateGroup>> {
+  Ok(unicode::find_normalization_duplicates(&items))
+}
+
+pub async fn flatten_items(
+  items: Vec<protocol::Item>,
+  options: protocol::FlattenOptions,
+) -> Result<Vec<protocol::Item>> {
+  let mut items = items;
+  organize::flatten_items(&mut items, &options)?;
+  Ok(items)
+}
 
 pub async fn get_built_in_plugins() -> Result<Vec<config::ConfigPlugin>> {
   Ok(plugins::BUILT_IN_PLUGINS.clone())
@@ -39,9 +83,88 @@ pub async fn get_config() -> Result<config::Config> {
     });
     config::set_config(config.clone())?;
   }
+  // Merge the native plugins by id as they are defined by the code like the ones added or changed in a newer version.
+  plugins::BUILT_IN_PLUGINS
+    .iter()
+    .filter(|plugin| plugin.native)
+    .for_each(|plugin| match config.plugins.iter_mut().find(|p| p.id == plugin.id) {
+      Some(p) => *p = plugin.clone(),
+      None => config.plugins.push(plugin.clone()),
+    });
+  // Merge the external plugins unless they are overridden by the plugins in the config.
+  plugins::get_external_plugins().into_iter().for_each(|plugin| {
+    if config.plugins.iter().any(|p| p.id == plugin.id) {
+      log::warn!("External plugin {} is overridden by the config.", plugin.id);
+    } else {
+      config.plugins.push(plugin);
+    }
+  });
   Ok(config)
 }
 
+pub async fn import_plugin(path: String, overwrite: bool) -> Result<config::ConfigPlugin> {
+  let content = fs::read_to_string(path.as_str()).map_err(anyhow::Error::msg)?;
+  let mut plugin = plugins::parse_plugin_file(content.as_str())
+    .map_err(|err| anyhow::anyhow!("Plugin file {} is invalid because {}", path, err))?;
+  let mut config = config::get_config();
+  // The external and the native plugins are not overwritten as they are loaded from the plugins directory and the code.
+  let is_reserved = plugins::get_external_plugins().iter().any(|p| p.id == plugin.id)
+    || plugins::BUILT_IN_PLUGINS.iter().any(|p| p.native && p.id == plugin.id);
+  match config.plugins.iter().position(|p| p.id == plugin.id) {
+    Some(index) if overwrite && !is_reserved => {
+      log::debug!("Overwriting plugin {} with {}.", plugin.id, path);
+      config.plugins[index] = plugin.clone();
+    }
+    None if !is_reserved => config.plugins.push(plugin.clone()),
+    _ => {
+      // Keep the existing plugin and import the new one with a new id.
+      plugin.id = uuid::Uuid::new_v4().to_string();
+      config.plugins.push(plugin.clone());
+    }
+  }
+  config::set_config(config)?;
+  Ok(plugin)
+}
+
+pub async fn match_subtitles(
+  items: Vec<protocol::Item>,
+  options: protocol::SubtitleMatchOptions,
+) -> Result<Vec<protocol::Item>> {
+  let mut items = items;
+  subtitles::match_subtitles(&mut items, &options)?;
+  Ok(items)
+}
+
+fn merge_options(
+  plugin: &config::ConfigPlugin,
+  options: BTreeMap<String, serde_json::Value>,
+) -> BTreeMap<String, serde_json::Value> {
+  // Start with the default values and override them with the given values.
+  let mut merged_options: BTreeMap<String, serde_json::Value> = plugin
+    .options
+    .iter()
+    .map(|option| (option.get_name().to_string(), option.get_default_value()))
+    .collect();
+  merged_options.extend(options);
+  merged_options
+}
+
+pub async fn organize_items(
+  items: Vec<protocol::Item>,
+  options: protocol::OrganizeOptions,
+) -> Result<Vec<protocol::Item>> {
+  let mut items = items;
+  organize::organize_items(&mut items, &options)?;
+  Ok(items)
+}
+
+pub async fn preview_regex_rename(
+  items: Vec<protocol::Item>,
+  options: protocol::RegexRenameOptions,
+) -> Result<Vec<protocol::RegexRenamePreview>> {
+  rules::preview_regex_rename(&items, &options)
+}
+
 fn recursive_scan_items(
   items: &mut Vec<protocol::Item>,
   path: &Path,
@@ -57,6 +180,8 @@ fn recursive_scan_items(
         source_path: path_str.to_string(),
         target_path: path_str.to_string(),
         item_type: protocol::ItemType::Directory,
+        metadata: BTreeMap::new(),
+        companions: Vec::new(),
       });
       item_set.insert(path_str.to_string());
     }
@@ -86,6 +211,8 @@ fn recursive_scan_items(
         source_path: path_str.to_string(),
         target_path: path_str.to_string(),
         item_type: protocol::ItemType::File,
+        metadata: BTreeMap::new(),
+        companions: Vec::new(),
       });
       item_set.insert(path_str.to_string());
     }
@@ -95,35 +222,120 @@ fn recursive_scan_items(
   Ok(())
 }
 
-pub async fn rename_items(items: Vec<protocol::Item>) -> Result<usize> {
+pub async fn regex_rename_items(
+  items: Vec<protocol::Item>,
+  options: protocol::RegexRenameOptions,
+) -> Result<Vec<protocol::Item>> {
+  let mut items = items;
+  rules::regex_rename_items(&mut items, &options)?;
+  Ok(items)
+}
+
+pub async fn remove_empty_directories(
+  directories: Vec<String>,
+  roots: Vec<String>,
+) -> Result<protocol::RemoveDirectoriesResult> {
+  Ok(organize::remove_empty_directories(&directories, &roots))
+}
+
+pub async fn rename_items(
+  items: Vec<protocol::Item>,
+  options: protocol::RenameOptions,
+) -> Result<protocol::RenameResult> {
   let mut count = 0;
+  let mut removed_directories: Vec<String> = Vec::new();
+  let mut trashed_paths: Vec<String> = Vec::new();
   if !items.is_empty() {
-    // Step 1: Normalize the paths.
-    let items: Vec<protocol::Item> = items
+    // Step 1: Normalize the paths with the companions renamed along with their primary files.
+    let items: Vec<protocol::Item> = companions::expand_companions(items)
       .into_iter()
       .map(|item| protocol::Item {
         source_path: Path::new(item.source_path.as_str()).to_str().unwrap().to_string(),
-        target_path: Path::new(item.target_path.as_str()).to_str().unwrap().to_string(),
+        target_path: match options.normalization {
+          Some(normalization) => {
+            unicode::normalize_path(item.source_path.as_str(), item.target_path.as_str(), normalization)
+          }
+          None => Path::new(item.target_path.as_str()).to_str().unwrap().to_string(),
+        },
         item_type: item.item_type,
+        metadata: item.metadata,
+        companions: Vec::new(),
       })
       .filter(|item| item.source_path != item.target_path)
       .collect();
-    // Step 2: Check duplicated source and target paths.
+    // The items with an empty target path are sent to the trash.
+    let (trash_items, items): (Vec<protocol::Item>, Vec<protocol::Item>) =
+      items.into_iter().partition(|item| item.target_path.is_empty());
+    if let Some(item) = items
+      .iter()
+      .find(|item| !Path::new(item.target_path.as_str()).is_absolute())
+    {
+      return Err(anyhow::anyhow!("Target path {} is not absolute.", item.target_path));
+    }
+    // Step 2: Check the lengths of the names on the file system with the optional truncation.
+    // The lengths of the paths are only checked with an explicit profile as long paths may be supported.
+    let profile = options.profile.unwrap_or_else(sanitize::get_current_profile);
+    let include_path = options.profile.is_some();
+    let items = items
+      .into_iter()
+      .map(|mut item| {
+        if options.truncate {
+          item.target_path = sanitize::truncate_path(item.target_path.as_str(), item.item_type, profile, include_path);
+        }
+        match sanitize::get_length_issues(item.target_path.as_str(), profile, include_path)
+          .into_iter()
+          .next()
+        {
+          Some(issue) => Err(anyhow::anyhow!(issue)),
+          None => Ok(item),
+        }
+      })
+      .collect::<Result<Vec<protocol::Item>>>()?;
+    // Step 3: Check duplicated source and target paths with the case sensitivity of the directories.
+    let mut case_sensitivity = case::CaseSensitivity::default();
     let mut source_path_set: HashSet<String> = HashSet::new();
-    for item in items.iter() {
-      if source_path_set.contains(item.source_path.as_str()) {
+    for item in trash_items.iter().chain(items.iter()) {
+      if !source_path_set.insert(case_sensitivity.get_key(item.source_path.as_str())) {
         return Err(anyhow::anyhow!("Source path {} is duplicated.", item.source_path));
       }
-      source_path_set.insert(item.source_path.clone());
     }
     let mut target_path_set: HashSet<String> = HashSet::new();
     for item in items.iter() {
-      if target_path_set.contains(item.target_path.as_str()) {
+      if !target_path_set.insert(case_sensitivity.get_key(item.target_path.as_str())) {
         return Err(anyhow::anyhow!("Target path {} is duplicated.", item.target_path));
       }
-      target_path_set.insert(item.target_path.clone());
     }
-    // Step 3: Check if source paths exist, and target paths do not exist.
+    // A directory sent to the trash must not contain any item to be renamed as the trash goes first.
+    for trash_item in trash_items.iter() {
+      let trash_path = Path::new(trash_item.source_path.as_str());
+      let case_sensitive = case_sensitivity.is_case_sensitive(trash_path);
+      let is_descendant = |path: &str| {
+        let (path, trash_path) = if case_sensitive {
+          (path.to_string(), trash_item.source_path.clone())
+        } else {
+          (path.to_lowercase(), trash_item.source_path.to_lowercase())
+        };
+        let path = Path::new(path.as_str());
+        path != Path::new(trash_path.as_str()) && path.starts_with(trash_path.as_str())
+      };
+      if let Some(item) = items
+        .iter()
+        .find(|item| is_descendant(item.source_path.as_str()) || is_descendant(item.target_path.as_str()))
+      {
+        return Err(anyhow::anyhow!(
+          "Path {} cannot be sent to the trash as it contains {}.",
+          trash_path.display(),
+          item.source_path
+        ));
+      }
+    }
+    // Step 4: Check if source paths exist, and target paths do not exist.
+    for item in trash_items.iter() {
+      if !Path::new(item.source_path.as_str()).exists() {
+        return Err(anyhow::anyhow!("Source path {} does not exist.", item.source_path));
+      }
+      trash::check_trash(item.source_path.as_str())?;
+    }
     for item in items.iter() {
       let source_path = Path::new(item.source_path.as_str());
       if !source_path.exists() {
@@ -131,20 +343,18 @@ pub async fn rename_items(items: Vec<protocol::Item>) -> Result<usize> {
       }
       let target_path = Path::new(item.target_path.as_str());
       if target_path.exists()
-        && !source_path_set.contains(item.target_path.as_str())
-        && (!CASE_INSENSITIVE
-          || (CASE_INSENSITIVE
-            && source_path.to_str().unwrap().to_lowercase() != target_path.to_str().unwrap().to_lowercase()))
+        && !source_path_set.contains(case_sensitivity.get_key(item.target_path.as_str()).as_str())
+        && !unicode::is_same_file(item.source_path.as_str(), item.target_path.as_str())
       {
         return Err(anyhow::anyhow!("Target path {} exists.", target_path.display()));
       }
     }
-    // Step 4: Resolve the renaming sequence.
+    // Step 5: Resolve the renaming sequence.
     let mut pass_1_items: Vec<protocol::Item> = Vec::new();
     let mut pass_2_items: Vec<protocol::Item> = Vec::new();
     for item in items.iter() {
-      // Resolve the conflict.
-      if source_path_set.contains(item.target_path.as_str()) {
+      // Resolve the conflict which includes the case only rename like "a.TXT" to "a.txt" in a case insensitive directory.
+      if source_path_set.contains(case_sensitivity.get_key(item.target_path.as_str()).as_str()) {
         if let Some(parent_path) = Path::new(item.target_path.as_str()).parent() {
           let name = uuid::Uuid::new_v4();
           let temp_path = parent_path.join(name.to_string()).to_str().unwrap().to_string();
@@ -152,11 +362,15 @@ pub async fn rename_items(items: Vec<protocol::Item>) -> Result<usize> {
             source_path: item.source_path.clone(),
             target_path: temp_path.clone(),
             item_type: item.item_type,
+            metadata: BTreeMap::new(),
+            companions: Vec::new(),
           });
           pass_2_items.push(protocol::Item {
             source_path: temp_path.clone(),
             target_path: item.target_path.clone(),
             item_type: item.item_type,
+            metadata: BTreeMap::new(),
+            companions: Vec::new(),
           });
         } else {
           return Err(anyhow::anyhow!("Target path {} cannot be resolved.", item.target_path));
@@ -165,33 +379,131 @@ pub async fn rename_items(items: Vec<protocol::Item>) -> Result<usize> {
         pass_1_items.push(item.clone());
       }
     }
-    // Step 5: Rename the items.
-    for items in [pass_1_items, pass_2_items].iter() {
-      for item in items.iter() {
-        let source_path = Path::new(item.source_path.as_str());
-        let target_path = Path::new(item.target_path.as_str());
-        let target_parent_path = target_path.parent().unwrap();
-        if !target_parent_path.exists() {
-          fs::create_dir_all(target_parent_path).map_err(anyhow::Error::msg)?;
+    // Step 6: Send the items to the trash first to release their paths, and rename the items.
+    // The partial result is returned along with the error so that the trashed items can be restored.
+    let result = (|| -> Result<()> {
+      for item in trash_items.iter() {
+        trashed_paths.push(trash::send_to_trash(item.source_path.as_str())?);
+      }
+      for items in [pass_1_items, pass_2_items].iter() {
+        for item in items.iter() {
+          let source_path = Path::new(item.source_path.as_str());
+          let target_path = Path::new(item.target_path.as_str());
+          let target_parent_path = target_path.parent().unwrap();
+          if !target_parent_path.exists() {
+            fs::create_dir_all(target_parent_path).map_err(anyhow::Error::msg)?;
+          }
+          fs::rename(source_path, target_path).map_err(anyhow::Error::msg)?;
+          count += 1;
         }
-        fs::rename(source_path, target_path).map_err(anyhow::Error::msg)?;
-        count += 1;
       }
+      Ok(())
+    })();
+    if let Err(err) = result {
+      return Ok(protocol::RenameResult {
+        count,
+        error: Some(err.to_string()),
+        removed_directories,
+        trashed_paths,
+      });
+    }
+    // Step 7: Prune the source directories which become empty.
+    if options.prune_directories {
+      let source_paths: Vec<String> = items
+        .iter()
+        .chain(trash_items.iter())
+        .map(|item| item.source_path.clone())
+        .collect();
+      // Pruning is a best effort cleanup which doesn't fail the rename already done.
+      let result = organize::prune_empty_directories(&source_paths, &options.roots);
+      result.errors.iter().for_each(|error| log::warn!("{}", error));
+      removed_directories = result.removed_directories;
     }
   }
-  Ok(count)
+  Ok(protocol::RenameResult {
+    count,
+    error: None,
+    removed_directories,
+    trashed_paths,
+  })
+}
+
+/// Resolves the plugins and the options of the pipeline steps which run in order in the target editor.
+pub async fn resolve_pipeline(id: String) -> Result<Vec<protocol::PipelineStep>> {
+  let config = get_config().await?;
+  let pipeline = config
+    .pipelines
+    .iter()
+    .find(|pipeline| pipeline.id == id)
+    .ok_or_else(|| anyhow::anyhow!("Pipeline {} is not found.", id))?;
+  let mut steps: Vec<protocol::PipelineStep> = Vec::new();
+  for step in pipeline.steps.iter() {
+    let plugin = config
+      .plugins
+      .iter()
+      .find(|plugin| plugin.id == step.plugin_id)
+      .ok_or_else(|| anyhow::anyhow!("Plugin {} in pipeline {} is not found.", step.plugin_id, pipeline.name))?;
+    let options = merge_options(plugin, step.options.clone());
+    validate_options(plugin, &options)?;
+    steps.push(protocol::PipelineStep {
+      options,
+      plugin: plugin.clone(),
+    });
+  }
+  Ok(steps)
+}
+
+pub async fn restore_from_trash(paths: Vec<String>) -> Result<protocol::RestoreResult> {
+  trash::restore_from_trash(&paths)
 }
 
-pub async fn scan_items(
+pub async fn run_native_plugin(
+  id: String,
+  options: BTreeMap<String, serde_json::Value>,
   items: Vec<protocol::Item>,
-  depth: i32,
-  include_directory: bool,
-  extensions: Vec<String>,
 ) -> Result<Vec<protocol::Item>> {
+  let plugin = rules::NATIVE_PLUGINS
+    .iter()
+    .find(|plugin| plugin.id == id)
+    .ok_or_else(|| anyhow::anyhow!("Native plugin {} is not found.", id))?;
+  let options = merge_options(plugin, options);
+  validate_options(plugin, &options)?;
+  let mut items = items;
+  rules::run(id.as_str(), &options, &mut items)?;
+  Ok(items)
+}
+
+pub async fn sanitize_items(
+  items: Vec<protocol::Item>,
+  options: protocol::SanitizeOptions,
+) -> Result<Vec<protocol::SanitizeResult>> {
+  sanitize::sanitize_items(&items, &options)
+}
+
+pub async fn scan_items<F>(
+  items: Vec<protocol::Item>,
+  options: protocol::ScanOptions,
+  on_hash_progress: F,
+) -> Result<Vec<protocol::Item>>
+where
+  F: Fn(protocol::HashProgress) + Sync,
+{
   let mut new_items = if items.is_empty() {
     Vec::new()
   } else {
-    let extensions: HashSet<String> = extensions.into_iter().collect();
+    let mut extensions: HashSet<String> = options.extensions.iter().cloned().collect();
+    // The companions are scanned regardless of the extensions and dropped later if they are not grouped.
+    let companion_extensions: HashSet<String> = if options.group_companions && !extensions.is_empty() {
+      companions::COMPANION_EXTENSIONS
+        .iter()
+        .chain(companions::ARTWORK_EXTENSIONS.iter())
+        .map(|extension| extension.to_string())
+        .filter(|extension| !extensions.contains(extension))
+        .collect()
+    } else {
+      HashSet::new()
+    };
+    extensions.extend(companion_extensions.iter().cloned());
     let mut item_set = HashSet::<String>::new();
     let mut new_items: Vec<protocol::Item> = Vec::new();
     for item in items.iter() {
@@ -203,18 +515,127 @@ pub async fn scan_items(
         &mut new_items,
         path,
         &mut item_set,
-        depth,
-        include_directory,
+        options.depth,
+        options.include_directory,
         &extensions,
       )?
     }
+    if let Some(normalization) = options.normalization {
+      new_items = unicode::normalize_items(new_items, normalization);
+    }
+    if options.group_companions {
+      new_items = companions::group_companions(new_items);
+      new_items.retain(|item| {
+        Path::new(item.source_path.as_str())
+          .extension()
+          .and_then(|extension| extension.to_str())
+          .is_none_or(|extension| !companion_extensions.contains(extension))
+      });
+    }
     new_items
   };
   new_items.sort_by(|a, b| a.source_path.cmp(&b.source_path));
+  metadata::read_metadata(&mut new_items, &options.metadata);
+  hashes::hash_items(&mut new_items, &options.hashes, on_hash_progress);
   Ok(new_items)
 }
 
-pub async fn set_config(config: config::Config) -> Result<config::Config> {
+pub async fn set_config(mut config: config::Config) -> Result<config::Config> {
+  // External plugins are loaded from the plugins directory and are not persisted in the config.
+  config.plugins.retain(|plugin| !plugin.external);
+  for plugin in config.plugins.iter() {
+    for option in plugin.options.iter() {
+      option
+        .validate()
+        .map_err(|err| anyhow::anyhow!("Plugin {} is invalid because {}", plugin.name, err))?;
+    }
+  }
   config::set_config(config)?;
-  Ok(config::get_config())
+  get_config().await
+}
+
+fn validate_options(plugin: &config::ConfigPlugin, options: &BTreeMap<String, serde_json::Value>) -> Result<()> {
+  for (name, value) in options.iter() {
+    let option = plugin
+      .options
+      .iter()
+      .find(|option| option.get_name() == name)
+      .ok_or_else(|| anyhow::anyhow!("Option {} is not defined in plugin {}.", name, plugin.name))?;
+    option
+      .validate_value(value)
+      .map_err(|err| anyhow::anyhow!("Plugin {} is invalid because {}", plugin.name, err))?;
+  }
+  for option in plugin.options.iter() {
+    if !options.contains_key(option.get_name()) {
+      return Err(anyhow::anyhow!(
+        "Option {} in plugin {} is missing.",
+        option.get_name(),
+        plugin.name
+      ));
+    }
+  }
+  Ok(())
+}
+
+pub async fn unflatten_items(
+  items: Vec<protocol::Item>,
+  options: protocol::UnflattenOptions,
+) -> Result<Vec<protocol::Item>> {
+  let mut items = items;
+  organize::unflatten_items(&mut items, &options)?;
+  Ok(items)
+}
+
+pub async fn validate_plugin_options(id: String, options: BTreeMap<String, serde_json::Value>) -> Result<()> {
+  let config = get_config().await?;
+  let plugin = config
+    .plugins
+    .iter()
+    .find(|plugin| plugin.id == id)
+    .ok_or_else(|| anyhow::anyhow!("Plugin {} is not found.", id))?;
+  validate_options(plugin, &options)
+}
+
+pub async fn verify_items(
+  items: Vec<protocol::Item>,
+  options: protocol::VerifyOptions,
+) -> Result<Vec<protocol::VerifyResult>> {
+  Ok(hashes::verify_items(&items, &options))
+}
+
+pub async fn write_tags(
+  items: Vec<protocol::Item>,
+  options: protocol::WriteTagsOptions,
+) -> Result<Vec<protocol::TagWriteResult>> {
+  metadata::write_audio_tags(&items, &options)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rename_items_rejects_trashing_an_ancestor_of_a_renamed_item() {
+    let mut trash_item = protocol::Item::new_file("/x/dir");
+    trash_item.target_path = String::new();
+    let mut source_item = protocol::Item::new_file("/x/dir/a.txt");
+    source_item.target_path = "/x/b.txt".to_string();
+    let mut target_item = protocol::Item::new_file("/x/c.txt");
+    target_item.target_path = "/x/dir/c.txt".to_string();
+    for item in [source_item, target_item] {
+      let error = tauri::async_runtime::block_on(rename_items(
+        vec![trash_item.clone(), item],
+        protocol::RenameOptions::default(),
+      ))
+      .unwrap_err();
+      assert!(error.to_string().starts_with("Path /x/dir cannot be sent to the trash"));
+    }
+    // A sibling sharing the prefix of the name is not a descendant.
+    let mut item = protocol::Item::new_file("/x/directory/a.txt");
+    item.target_path = "/x/dir2/a.txt".to_string();
+    let error =
+      tauri::async_runtime::block_on(rename_items(vec![trash_item, item], protocol::RenameOptions::default()))
+        .unwrap_err();
+    assert_eq!("Source path /x/dir does not exist.", error.to_string());
+  }
 }
@@ -0,0 +1,694 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::tags;
+
+pub const IMAGE_EXTENSIONS: [&str; 17] = [
+  "arw", "cr2", "dng", "heic", "heif", "jpeg", "jpg", "nef", "nrw", "orf", "pef", "raf", "rw2", "sr2", "srw", "tif",
+  "tiff",
+];
+
+pub const FIELD_DATE: &str = "date";
+pub const FIELD_DATE_SOURCE: &str = "dateSource";
+pub const FIELD_DAY: &str = "day";
+pub const FIELD_HOUR: &str = "hour";
+pub const FIELD_LATITUDE: &str = "latitude";
+pub const FIELD_LENS: &str = "lens";
+pub const FIELD_LONGITUDE: &str = "longitude";
+pub const FIELD_MAKE: &str = "make";
+pub const FIELD_MINUTE: &str = "minute";
+pub const FIELD_MODEL: &str = "model";
+pub const FIELD_MONTH: &str = "month";
+pub const FIELD_SECOND: &str = "second";
+pub const FIELD_TIME: &str = "time";
+pub const FIELD_YEAR: &str = "year";
+
+const DATE_SOURCE_EXIF: &str = "exif";
+const DATE_SOURCE_MTIME: &str = "mtime";
+const DATE_SOURCE_XMP: &str = "xmp";
+
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const MAX_BLOCK_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_IFD_ENTRIES: u16 = 1024;
+
+static XMP_PROPERTY_REGEX: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r#"(?s)(?:^|[\s<])([A-Za-z]+:[A-Za-z]+)(?:\s*=\s*"([^"]*)"|>([^<]*)</)"#).unwrap());
+
+/// The date and camera properties of a photo. The sources are merged in the order of priority.
+#[derive(Default)]
+struct ImageInfo {
+  date_time: Option<(NaiveDateTime, &'static str)>,
+  latitude: Option<f64>,
+  lens: Option<String>,
+  longitude: Option<f64>,
+  make: Option<String>,
+  model: Option<String>,
+  modify_date_time: Option<NaiveDateTime>,
+}
+
+impl ImageInfo {
+  fn into_fields(self, path: &Path) -> BTreeMap<String, String> {
+    let mut fields: BTreeMap<String, String> = BTreeMap::new();
+    let date_time = self
+      .date_time
+      .or(self.modify_date_time.map(|date_time| (date_time, DATE_SOURCE_EXIF)))
+      .or_else(|| {
+        fs::metadata(path)
+          .and_then(|metadata| metadata.modified())
+          .ok()
+          .map(|modified| (DateTime::<Local>::from(modified).naive_local(), DATE_SOURCE_MTIME))
+      });
+    if let Some((date_time, date_source)) = date_time {
+      fields.insert(FIELD_DATE.to_string(), date_time.format("%Y-%m-%d").to_string());
+      fields.insert(FIELD_DATE_SOURCE.to_string(), date_source.to_string());
+      fields.insert(FIELD_DAY.to_string(), date_time.format("%d").to_string());
+      fields.insert(FIELD_HOUR.to_string(), date_time.format("%H").to_string());
+      fields.insert(FIELD_MINUTE.to_string(), date_time.format("%M").to_string());
+      fields.insert(FIELD_MONTH.to_string(), date_time.format("%m").to_string());
+      fields.insert(FIELD_SECOND.to_string(), date_time.format("%S").to_string());
+      fields.insert(FIELD_TIME.to_string(), date_time.format("%H%M%S").to_string());
+      fields.insert(FIELD_YEAR.to_string(), date_time.format("%Y").to_string());
+    }
+    if let (Some(latitude), Some(longitude)) = (self.latitude, self.longitude) {
+      fields.insert(FIELD_LATITUDE.to_string(), format!("{:.6}", latitude));
+      fields.insert(FIELD_LONGITUDE.to_string(), format!("{:.6}", longitude));
+    }
+    for (name, value) in [
+      (FIELD_LENS, self.lens),
+      (FIELD_MAKE, self.make),
+      (FIELD_MODEL, self.model),
+    ] {
+      if let Some(value) = value {
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if !value.is_empty() {
+          fields.insert(name.to_string(), value.to_string());
+        }
+      }
+    }
+    fields
+  }
+}
+
+struct HeicItemLocation {
+  construction_method: u8,
+  /// The offsets and the lengths.
+  extents: Vec<(u64, u64)>,
+  item_id: u32,
+}
+
+struct IfdEntry {
+  tag: u16,
+  value: Vec<u8>,
+}
+
+/// A TIFF structure read from the given base offset of the reader.
+struct Tiff<'a, R: Read + Seek> {
+  base: u64,
+  little_endian: bool,
+  reader: &'a mut R,
+}
+
+impl<'a, R: Read + Seek> Tiff<'a, R> {
+  fn new(reader: &'a mut R, base: u64) -> Result<Self> {
+    reader.seek(SeekFrom::Start(base))?;
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header)?;
+    let little_endian = match &header {
+      b"II" => true,
+      b"MM" => false,
+      _ => return Err(anyhow::anyhow!("TIFF byte order is invalid.")),
+    };
+    Ok(Self {
+      base,
+      little_endian,
+      reader,
+    })
+  }
+
+  fn read_bytes(&mut self, offset: u64, size: usize) -> Result<Vec<u8>> {
+    let offset = self
+      .base
+      .checked_add(offset)
+      .ok_or_else(|| anyhow::anyhow!("TIFF offset {} is invalid.", offset))?;
+    self.reader.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; size];
+    self.reader.read_exact(&mut data)?;
+    Ok(data)
+  }
+
+  fn to_u16(&self, bytes: &[u8]) -> u16 {
+    if self.little_endian {
+      u16::from_le_bytes([bytes[0], bytes[1]])
+    } else {
+      u16::from_be_bytes([bytes[0], bytes[1]])
+    }
+  }
+
+  fn to_u32(&self, bytes: &[u8]) -> u32 {
+    if self.little_endian {
+      u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    } else {
+      u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+  }
+
+  fn read_first_ifd_offset(&mut self) -> Result<u64> {
+    let bytes = self.read_bytes(4, 4)?;
+    Ok(self.to_u32(&bytes) as u64)
+  }
+
+  /// Reads the entries of an IFD with the raw values.
+  fn read_ifd(&mut self, offset: u64) -> Result<Vec<IfdEntry>> {
+    let count_bytes = self.read_bytes(offset, 2)?;
+    let count = self.to_u16(&count_bytes).min(MAX_IFD_ENTRIES) as usize;
+    let data = self.read_bytes(offset + 2, count * 12)?;
+    let mut entries = Vec::with_capacity(count);
+    for entry in data.chunks_exact(12) {
+      let tag = self.to_u16(&entry[0..2]);
+      let value_type = self.to_u16(&entry[2..4]);
+      let value_count = self.to_u32(&entry[4..8]);
+      let type_size = match value_type {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => continue,
+      };
+      let size = type_size * value_count as u64;
+      if size > MAX_BLOCK_SIZE {
+        continue;
+      }
+      // A value of up to 4 bytes is stored in the entry itself.
+      let value = if size <= 4 {
+        entry[8..(8 + size as usize)].to_vec()
+      } else {
+        let value_offset = self.to_u32(&entry[8..12]) as u64;
+        match self.read_bytes(value_offset, size as usize) {
+          Ok(value) => value,
+          Err(_) => continue,
+        }
+      };
+      entries.push(IfdEntry { tag, value });
+    }
+    Ok(entries)
+  }
+
+  fn read_ascii(value: &[u8]) -> String {
+    String::from_utf8_lossy(value.split(|&b| b == 0).next().unwrap_or_default()).to_string()
+  }
+
+  fn read_rationals(&self, value: &[u8]) -> Vec<f64> {
+    value
+      .chunks_exact(8)
+      .map(|chunk| {
+        let denominator = self.to_u32(&chunk[4..8]);
+        if denominator == 0 {
+          0.0
+        } else {
+          self.to_u32(&chunk[0..4]) as f64 / denominator as f64
+        }
+      })
+      .collect()
+  }
+
+  fn read_info(&mut self, info: &mut ImageInfo) -> Result<()> {
+    let ifd_offset = self.read_first_ifd_offset()?;
+    let mut exif_offset: Option<u64> = None;
+    let mut gps_offset: Option<u64> = None;
+    for IfdEntry { tag, value } in self.read_ifd(ifd_offset)? {
+      match tag {
+        TAG_DATE_TIME => info.modify_date_time = parse_exif_date_time(Self::read_ascii(&value).as_str()),
+        TAG_EXIF_IFD if value.len() == 4 => exif_offset = Some(self.to_u32(&value) as u64),
+        TAG_GPS_IFD if value.len() == 4 => gps_offset = Some(self.to_u32(&value) as u64),
+        TAG_MAKE => info.make = Some(Self::read_ascii(&value)),
+        TAG_MODEL => info.model = Some(Self::read_ascii(&value)),
+        _ => {}
+      }
+    }
+    if let Some(exif_offset) = exif_offset {
+      for IfdEntry { tag, value } in self.read_ifd(exif_offset)? {
+        match tag {
+          TAG_DATE_TIME_ORIGINAL => {
+            if let Some(date_time) = parse_exif_date_time(Self::read_ascii(&value).as_str()) {
+              info.date_time = Some((date_time, DATE_SOURCE_EXIF));
+            }
+          }
+          TAG_LENS_MODEL => info.lens = Some(Self::read_ascii(&value)),
+          _ => {}
+        }
+      }
+    }
+    if let Some(gps_offset) = gps_offset {
+      let mut latitude: Option<f64> = None;
+      let mut latitude_sign = 1.0;
+      let mut longitude: Option<f64> = None;
+      let mut longitude_sign = 1.0;
+      for IfdEntry { tag, value } in self.read_ifd(gps_offset)? {
+        match tag {
+          TAG_GPS_LATITUDE => latitude = to_degrees(&self.read_rationals(&value)),
+          TAG_GPS_LATITUDE_REF if value.first() == Some(&b'S') => latitude_sign = -1.0,
+          TAG_GPS_LONGITUDE => longitude = to_degrees(&self.read_rationals(&value)),
+          TAG_GPS_LONGITUDE_REF if value.first() == Some(&b'W') => longitude_sign = -1.0,
+          _ => {}
+        }
+      }
+      info.latitude = latitude.map(|latitude| latitude * latitude_sign);
+      info.longitude = longitude.map(|longitude| longitude * longitude_sign);
+    }
+    Ok(())
+  }
+}
+
+fn get_xmp_sidecar_paths(path: &Path) -> Vec<PathBuf> {
+  let mut paths: Vec<PathBuf> = vec![path.with_extension("xmp"), path.with_extension("XMP")];
+  if let Some(file_name) = path.file_name() {
+    let mut file_name = file_name.to_os_string();
+    file_name.push(".xmp");
+    paths.push(path.with_file_name(file_name));
+  }
+  paths.into_iter().filter(|path| path.is_file()).collect()
+}
+
+pub fn is_image_file(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn parse_exif_date_time(text: &str) -> Option<NaiveDateTime> {
+  NaiveDateTime::parse_from_str(text.trim(), "%Y:%m:%d %H:%M:%S").ok()
+}
+
+/// Parses "2024-06-01T15:30:12.00+02:00", "2024-06-01T15:30" or "2024-06-01" in the local time of the photo.
+fn parse_xmp_date_time(text: &str) -> Option<NaiveDateTime> {
+  let text = text.trim();
+  text
+    .get(0..19)
+    .and_then(|text| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S").ok())
+    .or_else(|| {
+      text
+        .get(0..16)
+        .and_then(|text| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M").ok())
+    })
+    .or_else(|| {
+      text
+        .get(0..10)
+        .and_then(|text| chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+}
+
+/// Parses a XMP GPS coordinate like "37,46.5N" or "37,46,30W".
+fn parse_xmp_gps(text: &str) -> Option<f64> {
+  let text = text.trim();
+  let direction = text.chars().last()?;
+  let values: Vec<f64> = text[..(text.len() - direction.len_utf8())]
+    .split(',')
+    .map(|value| value.trim().parse::<f64>())
+    .collect::<std::result::Result<Vec<f64>, _>>()
+    .ok()?;
+  let degrees = to_degrees(&values)?;
+  match direction {
+    'N' | 'E' => Some(degrees),
+    'S' | 'W' => Some(-degrees),
+    _ => None,
+  }
+}
+
+fn read_heic<R: Read + Seek>(reader: &mut R, info: &mut ImageInfo) -> Result<Option<String>> {
+  let file_size = reader.seek(SeekFrom::End(0))?;
+  let (start, end) =
+    tags::read_mp4_atom(reader, (0, file_size), b"meta")?.ok_or_else(|| anyhow::anyhow!("Box meta is not found."))?;
+  if end - start > MAX_BLOCK_SIZE || end - start < 4 {
+    return Err(anyhow::anyhow!("Box meta is invalid."));
+  }
+  reader.seek(SeekFrom::Start(start))?;
+  let mut meta = vec![0u8; (end - start) as usize];
+  reader.read_exact(&mut meta)?;
+  // The meta box is a full box with 4 bytes of version and flags.
+  let meta_start = start + 4;
+  let meta = &meta[4..];
+  let boxes = tags::parse_mp4_atoms(meta)?;
+  let find = |box_type: &[u8; 4]| boxes.iter().find(|atom| &atom.atom_type == box_type);
+  let mut exif_item_id: Option<u32> = None;
+  let mut xmp_item_id: Option<u32> = None;
+  if let Some(iinf) = find(b"iinf") {
+    let iinf = &meta[iinf.body_start..iinf.end];
+    let entries_start = if iinf.first() == Some(&0) { 6 } else { 8 };
+    for infe in tags::parse_mp4_atoms(iinf.get(entries_start..).unwrap_or_default())? {
+      let infe = &iinf[entries_start..][infe.body_start..infe.end];
+      let (item_id, rest) = match infe.first() {
+        Some(2) if infe.len() >= 12 => (u16::from_be_bytes([infe[4], infe[5]]) as u32, &infe[6..]),
+        Some(3) if infe.len() >= 14 => (u32::from_be_bytes(infe[4..8].try_into()?), &infe[8..]),
+        _ => continue,
+      };
+      // The item type follows 2 bytes of protection index.
+      match &rest[2..6] {
+        b"Exif" => exif_item_id = Some(item_id),
+        b"mime" => {
+          let content_type = rest[6..].split(|&b| b == 0).nth(1).unwrap_or_default();
+          if content_type == b"application/rdf+xml" {
+            xmp_item_id = Some(item_id);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+  let iloc = match find(b"iloc") {
+    Some(iloc) => &meta[iloc.body_start..iloc.end],
+    None => return Ok(None),
+  };
+  let idat_start = find(b"idat").map(|idat| meta_start + idat.body_start as u64);
+  let mut read_item = |item_id: u32| -> Result<Option<Vec<u8>>> {
+    for location in read_heic_iloc(iloc)? {
+      if location.item_id != item_id {
+        continue;
+      }
+      let base = if location.construction_method == 1 {
+        idat_start.ok_or_else(|| anyhow::anyhow!("Box idat is not found."))?
+      } else {
+        0
+      };
+      let mut data: Vec<u8> = Vec::new();
+      for (offset, length) in location.extents {
+        let size = (data.len() as u64)
+          .checked_add(length)
+          .filter(|size| *size <= MAX_BLOCK_SIZE)
+          .ok_or_else(|| anyhow::anyhow!("Item {} is too large.", item_id))?;
+        let offset = base
+          .checked_add(offset)
+          .ok_or_else(|| anyhow::anyhow!("Item {} has an invalid offset.", item_id))?;
+        reader.seek(SeekFrom::Start(offset))?;
+        let position = data.len();
+        data.resize(size as usize, 0);
+        reader.read_exact(&mut data[position..])?;
+      }
+      return Ok(Some(data));
+    }
+    Ok(None)
+  };
+  if let Some(data) = exif_item_id.map(&mut read_item).transpose()?.flatten() {
+    // The Exif item starts with the offset to the TIFF header.
+    if data.len() >= 4 {
+      let offset = 4 + u32::from_be_bytes(data[0..4].try_into()?) as u64;
+      let mut cursor = Cursor::new(data);
+      Tiff::new(&mut cursor, offset)?.read_info(info)?;
+    }
+  }
+  let xmp = xmp_item_id
+    .map(&mut read_item)
+    .transpose()?
+    .flatten()
+    .map(|data| String::from_utf8_lossy(&data).to_string());
+  Ok(xmp)
+}
+
+fn read_heic_iloc(iloc: &[u8]) -> Result<Vec<HeicItemLocation>> {
+  let version = *iloc.first().ok_or_else(|| anyhow::anyhow!("Box iloc is truncated."))?;
+  let mut cursor = Cursor::new(iloc);
+  cursor.set_position(4);
+  let mut read_uint = |size: u8| -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    cursor.read_exact(&mut bytes[(8 - size as usize)..])?;
+    Ok(u64::from_be_bytes(bytes))
+  };
+  let sizes = read_uint(1)? as u8;
+  let (offset_size, length_size) = (sizes >> 4, sizes & 0x0F);
+  let sizes = read_uint(1)? as u8;
+  let (base_offset_size, index_size) = (sizes >> 4, if version > 0 { sizes & 0x0F } else { 0 });
+  // The sizes other than 0, 4 and 8 are invalid per ISO/IEC 14496-12.
+  if let Some(size) = [offset_size, length_size, base_offset_size, index_size]
+    .into_iter()
+    .find(|size| !matches!(size, 0 | 4 | 8))
+  {
+    return Err(anyhow::anyhow!("Box iloc has an invalid field size {}.", size));
+  }
+  let item_count = read_uint(if version < 2 { 2 } else { 4 })?;
+  let mut items = Vec::new();
+  for _ in 0..item_count {
+    let item_id = read_uint(if version < 2 { 2 } else { 4 })? as u32;
+    let construction_method = if version > 0 { (read_uint(2)? & 0x0F) as u8 } else { 0 };
+    // The data reference index is ignored.
+    read_uint(2)?;
+    let base_offset = read_uint(base_offset_size)?;
+    let extent_count = read_uint(2)?;
+    let mut extents = Vec::new();
+    for _ in 0..extent_count {
+      read_uint(index_size)?;
+      let offset = read_uint(offset_size)?;
+      let length = read_uint(length_size)?;
+      let offset = base_offset
+        .checked_add(offset)
+        .ok_or_else(|| anyhow::anyhow!("Box iloc has an invalid offset."))?;
+      extents.push((offset, length));
+    }
+    items.push(HeicItemLocation {
+      construction_method,
+      extents,
+      item_id,
+    });
+  }
+  Ok(items)
+}
+
+/// Reads the APP1 segments of a JPEG and returns the embedded XMP.
+fn read_jpeg<R: Read + Seek>(reader: &mut R, start: u64, info: &mut ImageInfo) -> Result<Option<String>> {
+  reader.seek(SeekFrom::Start(start))?;
+  let mut marker = [0u8; 2];
+  reader.read_exact(&mut marker)?;
+  if marker != [0xFF, 0xD8] {
+    return Err(anyhow::anyhow!("JPEG signature is not found."));
+  }
+  let mut position = start + 2;
+  let mut xmp: Option<String> = None;
+  loop {
+    reader.seek(SeekFrom::Start(position))?;
+    if reader.read_exact(&mut marker).is_err() || marker[0] != 0xFF {
+      break;
+    }
+    // The metadata segments are placed before the image data.
+    if matches!(marker[1], 0xD9 | 0xDA) {
+      break;
+    }
+    let mut size = [0u8; 2];
+    reader.read_exact(&mut size)?;
+    let size = u16::from_be_bytes(size) as u64;
+    if size < 2 {
+      break;
+    }
+    if marker[1] == 0xE1 {
+      let mut segment = vec![0u8; (size - 2) as usize];
+      reader.read_exact(&mut segment)?;
+      if segment.starts_with(b"Exif\0\0") {
+        let mut cursor = Cursor::new(&segment[6..]);
+        if let Ok(mut tiff) = Tiff::new(&mut cursor, 0) {
+          tiff.read_info(info)?;
+        }
+      } else if let Some(data) = segment.strip_prefix(b"http://ns.adobe.com/xap/1.0/\0") {
+        xmp = Some(String::from_utf8_lossy(data).to_string());
+      }
+    }
+    position += 2 + size;
+  }
+  Ok(xmp)
+}
+
+/// Reads the date, the camera, the lens and the GPS position of a photo.
+/// The embedded EXIF is preferred over the XMP sidecar and the embedded XMP,
+/// and the file modification time is the last fallback of the date.
+pub fn read_image_metadata(path: &Path) -> Result<BTreeMap<String, String>> {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension.to_lowercase())
+    .unwrap_or_default();
+  let mut reader = BufReader::new(File::open(path).map_err(anyhow::Error::msg)?);
+  let mut info = ImageInfo::default();
+  let xmp = match extension.as_str() {
+    "heic" | "heif" => read_heic(&mut reader, &mut info)?,
+    "jpeg" | "jpg" => read_jpeg(&mut reader, 0, &mut info)?,
+    "raf" => {
+      // The Fujifilm RAW holds a JPEG preview with the EXIF.
+      let mut header = [0u8; 92];
+      reader.read_exact(&mut header)?;
+      if !header.starts_with(b"FUJIFILMCCD-RAW") {
+        return Err(anyhow::anyhow!("RAF signature is not found."));
+      }
+      let offset = u32::from_be_bytes(header[84..88].try_into()?) as u64;
+      read_jpeg(&mut reader, offset, &mut info)?
+    }
+    _ => {
+      Tiff::new(&mut reader, 0)?.read_info(&mut info)?;
+      None
+    }
+  };
+  for xmp in get_xmp_sidecar_paths(path)
+    .into_iter()
+    .filter_map(|sidecar_path| fs::read_to_string(sidecar_path).ok())
+    .chain(xmp)
+  {
+    read_xmp(xmp.as_str(), &mut info);
+  }
+  Ok(info.into_fields(path))
+}
+
+/// Reads the XMP properties in both the attribute form and the element form.
+/// A property is only taken if it is missing from the preferred sources.
+fn read_xmp(xmp: &str, info: &mut ImageInfo) {
+  let mut latitude: Option<f64> = None;
+  let mut longitude: Option<f64> = None;
+  for captures in XMP_PROPERTY_REGEX.captures_iter(xmp) {
+    let value = captures
+      .get(2)
+      .or(captures.get(3))
+      .map_or("", |value| value.as_str())
+      .trim();
+    if value.is_empty() {
+      continue;
+    }
+    match &captures[1] {
+      "exif:DateTimeOriginal" | "photoshop:DateCreated" | "xmp:CreateDate" if info.date_time.is_none() => {
+        info.date_time = parse_xmp_date_time(value).map(|date_time| (date_time, DATE_SOURCE_XMP));
+      }
+      "aux:Lens" | "exifEX:LensModel" => {
+        info.lens.get_or_insert_with(|| value.to_string());
+      }
+      "exif:GPSLatitude" => latitude = parse_xmp_gps(value),
+      "exif:GPSLongitude" => longitude = parse_xmp_gps(value),
+      "tiff:Make" => {
+        info.make.get_or_insert_with(|| value.to_string());
+      }
+      "tiff:Model" => {
+        info.model.get_or_insert_with(|| value.to_string());
+      }
+      _ => {}
+    }
+  }
+  if info.latitude.is_none() || info.longitude.is_none() {
+    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+      info.latitude = Some(latitude);
+      info.longitude = Some(longitude);
+    }
+  }
+}
+
+/// Converts degrees, minutes and seconds to degrees.
+fn to_degrees(values: &[f64]) -> Option<f64> {
+  if values.is_empty() || values.len() > 3 {
+    return None;
+  }
+  Some(
+    values
+      .iter()
+      .zip([1.0, 60.0, 3600.0])
+      .map(|(value, divisor)| value / divisor)
+      .sum(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(box_type);
+    data.extend_from_slice(body);
+    data
+  }
+
+  /// Creates a HEIC with an Exif item 1 located by a version 1 iloc box with 8 byte offsets and lengths.
+  fn new_heic(construction_method: u8, extents: &[(u64, u64)]) -> Vec<u8> {
+    let mut infe = vec![2, 0, 0, 0, 0, 1, 0, 0];
+    infe.extend_from_slice(b"Exif");
+    let mut iinf = vec![0, 0, 0, 0, 0, 1];
+    iinf.extend(new_box(b"infe", &infe));
+    let mut iloc = vec![1, 0, 0, 0, 0x88, 0x00, 0, 1, 0, 1, 0, construction_method, 0, 0];
+    iloc.extend_from_slice(&(extents.len() as u16).to_be_bytes());
+    for (offset, length) in extents {
+      iloc.extend_from_slice(&offset.to_be_bytes());
+      iloc.extend_from_slice(&length.to_be_bytes());
+    }
+    let mut meta = vec![0, 0, 0, 0];
+    meta.extend(new_box(b"iinf", &iinf));
+    meta.extend(new_box(b"iloc", &iloc));
+    meta.extend(new_box(b"idat", &[0u8; 8]));
+    new_box(b"meta", &meta)
+  }
+
+  fn read_heic_error(data: Vec<u8>) -> String {
+    let mut info = ImageInfo::default();
+    read_heic(&mut Cursor::new(data), &mut info).unwrap_err().to_string()
+  }
+
+  #[test]
+  fn test_read_heic_reads_the_exif_item() {
+    // The Exif item has 4 bytes of the TIFF header offset and a big endian TIFF with the make.
+    let mut exif = vec![0, 0, 0, 0];
+    exif.extend_from_slice(b"MM\0\x2A\0\0\0\x08\0\x01\x01\x0F\0\x02\0\0\0\x04Abc\0\0\0\0\0");
+    let offset = new_heic(0, &[(0, 0)]).len() as u64;
+    let mut data = new_heic(0, &[(offset, exif.len() as u64)]);
+    data.extend_from_slice(&exif);
+    let mut info = ImageInfo::default();
+    assert_eq!(None, read_heic(&mut Cursor::new(data), &mut info).unwrap());
+    assert_eq!(Some("Abc".to_string()), info.make);
+  }
+
+  #[test]
+  fn test_read_heic_rejects_overflowing_extents() {
+    assert_eq!(
+      "Item 1 is too large.",
+      read_heic_error(new_heic(0, &[(0, 1), (0, u64::MAX)]))
+    );
+    assert_eq!(
+      "Item 1 has an invalid offset.",
+      read_heic_error(new_heic(1, &[(u64::MAX, 1)]))
+    );
+  }
+
+  #[test]
+  fn test_tiff_rejects_overflowing_offsets() {
+    let mut cursor = Cursor::new(b"II*\0\x08\0\0\0".to_vec());
+    let mut tiff = Tiff::new(&mut cursor, 0).ok().unwrap();
+    tiff.base = u64::MAX;
+    assert_eq!(
+      "TIFF offset 4 is invalid.",
+      tiff.read_bytes(4, 4).err().unwrap().to_string()
+    );
+  }
+}
@@ -0,0 +1,388 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use sha2::Digest;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::protocol;
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+static CRC_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([0-9A-Fa-f]{8})\]").unwrap());
+
+pub const FIELD_BLAKE3: &str = "blake3";
+pub const FIELD_CRC32: &str = "crc32";
+pub const FIELD_MD5: &str = "md5";
+pub const FIELD_SHA1: &str = "sha1";
+pub const FIELD_SHA256: &str = "sha256";
+
+enum Hasher {
+  Blake3(Box<blake3::Hasher>),
+  Crc32(crc32fast::Hasher),
+  Md5(md5::Md5),
+  Sha1(sha1::Sha1),
+  Sha256(sha2::Sha256),
+}
+
+impl Hasher {
+  fn new(algorithm: protocol::HashAlgorithm) -> Self {
+    match algorithm {
+      protocol::HashAlgorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+      protocol::HashAlgorithm::Crc32 => Self::Crc32(crc32fast::Hasher::new()),
+      protocol::HashAlgorithm::Md5 => Self::Md5(md5::Md5::new()),
+      protocol::HashAlgorithm::Sha1 => Self::Sha1(sha1::Sha1::new()),
+      protocol::HashAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+    }
+  }
+
+  /// Finalizes the hash as the field name and the value.
+  /// CRC32 is in upper case like the tags in file names, and the others are in lower case.
+  fn finalize(self) -> (String, String) {
+    match self {
+      Self::Blake3(hasher) => (FIELD_BLAKE3.to_string(), hasher.finalize().to_hex().to_string()),
+      Self::Crc32(hasher) => (FIELD_CRC32.to_string(), format!("{:08X}", hasher.finalize())),
+      Self::Md5(hasher) => (FIELD_MD5.to_string(), to_hex(&hasher.finalize())),
+      Self::Sha1(hasher) => (FIELD_SHA1.to_string(), to_hex(&hasher.finalize())),
+      Self::Sha256(hasher) => (FIELD_SHA256.to_string(), to_hex(&hasher.finalize())),
+    }
+  }
+
+  fn update(&mut self, buffer: &[u8]) {
+    match self {
+      Self::Blake3(hasher) => {
+        hasher.update(buffer);
+      }
+      Self::Crc32(hasher) => hasher.update(buffer),
+      Self::Md5(hasher) => hasher.update(buffer),
+      Self::Sha1(hasher) => hasher.update(buffer),
+      Self::Sha256(hasher) => hasher.update(buffer),
+    }
+  }
+}
+
+/// Groups the files by the size and then by the content hash, and proposes the target paths of the duplicates.
+/// The first file by the source path in every group is the original one and keeps its target path.
+/// The duplicates get the suffix or are moved into the folder next to them according to the action.
+pub fn find_duplicates(
+  items: &[protocol::Item],
+  options: &protocol::DuplicateOptions,
+) -> Result<Vec<protocol::DuplicateGroup>> {
+  let folder = options.folder.trim().trim_matches(std::path::is_separator);
+  if options.action == protocol::DuplicateAction::Move && folder.is_empty() {
+    return Err(anyhow::anyhow!("Folder of the duplicates is not specified."));
+  }
+  let mut size_map: BTreeMap<u64, Vec<&protocol::Item>> = BTreeMap::new();
+  for item in items.iter().filter(|item| item.item_type == protocol::ItemType::File) {
+    match std::fs::metadata(item.source_path.as_str()) {
+      Ok(metadata) => size_map.entry(metadata.len()).or_default().push(item),
+      Err(err) => log::warn!("Couldn't read the size of {} because {}", item.source_path, err),
+    }
+  }
+  // Only the files sharing the size with others are hashed, and the hashes in the metadata are reused.
+  let field = get_field(options.algorithm);
+  let candidates: Vec<(u64, &protocol::Item)> = size_map
+    .into_iter()
+    .filter(|(_, items)| items.len() > 1)
+    .flat_map(|(size, items)| items.into_iter().map(move |item| (size, item)))
+    .collect();
+  let hashed_items: Vec<(u64, String, &protocol::Item)> = candidates
+    .into_par_iter()
+    .filter_map(|(size, item)| {
+      let hash = match item.metadata.get(field) {
+        Some(hash) => Ok(hash.clone()),
+        None => hash_file(Path::new(item.source_path.as_str()), &[options.algorithm])
+          .map(|mut hashes| hashes.remove(field).unwrap_or_default()),
+      };
+      match hash {
+        Ok(hash) => Some((size, hash, item)),
+        Err(err) => {
+          log::warn!("Couldn't hash {} because {}", item.source_path, err);
+          None
+        }
+      }
+    })
+    .collect();
+  let mut hash_map: HashMap<(u64, String), Vec<&protocol::Item>> = HashMap::new();
+  for (size, hash, item) in hashed_items {
+    hash_map.entry((size, hash)).or_default().push(item);
+  }
+  let mut groups: Vec<protocol::DuplicateGroup> = hash_map
+    .into_iter()
+    .filter(|(_, items)| items.len() > 1)
+    .map(|((size, hash), mut items)| {
+      items.sort_by(|a, b| a.source_path.cmp(&b.source_path));
+      let items = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+          let mut item = item.clone();
+          if index > 0 {
+            item.target_path = get_duplicate_path(item.target_path.as_str(), options, folder);
+          }
+          item
+        })
+        .collect();
+      protocol::DuplicateGroup { hash, items, size }
+    })
+    .collect();
+  groups.sort_by(|a, b| a.items[0].source_path.cmp(&b.items[0].source_path));
+  Ok(groups)
+}
+
+/// Gets the range and the value in upper case of the last bracketed CRC32 tag like "[ABCD1234]" in the stem.
+fn get_crc_tag(stem: &str) -> Option<(Range<usize>, String)> {
+  CRC_TAG_REGEX
+    .captures_iter(stem)
+    .last()
+    .map(|captures| (captures.get(0).unwrap().range(), captures[1].to_uppercase()))
+}
+
+fn get_duplicate_path(path: &str, options: &protocol::DuplicateOptions, folder: &str) -> String {
+  let path = Path::new(path);
+  match options.action {
+    protocol::DuplicateAction::Keep => path.to_string_lossy().to_string(),
+    protocol::DuplicateAction::Move => {
+      let file_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+      path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(folder)
+        .join(file_name)
+        .to_string_lossy()
+        .to_string()
+    }
+    protocol::DuplicateAction::Suffix => {
+      let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+      let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+      path
+        .with_file_name(format!("{}{}{}", stem, options.suffix, extension))
+        .to_string_lossy()
+        .to_string()
+    }
+  }
+}
+
+fn get_field(algorithm: protocol::HashAlgorithm) -> &'static str {
+  match algorithm {
+    protocol::HashAlgorithm::Blake3 => FIELD_BLAKE3,
+    protocol::HashAlgorithm::Crc32 => FIELD_CRC32,
+    protocol::HashAlgorithm::Md5 => FIELD_MD5,
+    protocol::HashAlgorithm::Sha1 => FIELD_SHA1,
+    protocol::HashAlgorithm::Sha256 => FIELD_SHA256,
+  }
+}
+
+/// Gets the target path with the CRC32 tag added or stripped.
+fn get_tagged_path(path: &str, action: protocol::CrcTagAction, crc: &str) -> String {
+  let path = Path::new(path);
+  let stem = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let extension = path
+    .extension()
+    .map(|extension| format!(".{}", extension.to_string_lossy()))
+    .unwrap_or_default();
+  let stem = match (action, get_crc_tag(stem.as_str())) {
+    (protocol::CrcTagAction::Add, None) => format!("{} [{}]", stem, crc),
+    (protocol::CrcTagAction::Strip, Some((range, _))) => {
+      // The separator before the tag is kept between the remaining parts like "Show_04_v2".
+      let separator = match stem[..range.start].chars().last() {
+        Some('_') => "_",
+        _ => " ",
+      };
+      let prefix = stem[..range.start].trim_end_matches([' ', '_', '.', '-']);
+      let suffix = stem[range.end..].trim_start_matches([' ', '_']);
+      if prefix.is_empty() || suffix.is_empty() || suffix.starts_with(['.', '-']) {
+        format!("{}{}", prefix, suffix)
+      } else {
+        format!("{}{}{}", prefix, separator, suffix)
+      }
+    }
+    _ => return path.to_string_lossy().to_string(),
+  };
+  path
+    .with_file_name(format!("{}{}", stem, extension))
+    .to_string_lossy()
+    .to_string()
+}
+
+/// Streams the file once through all the given hash algorithms.
+pub fn hash_file(path: &Path, algorithms: &[protocol::HashAlgorithm]) -> Result<BTreeMap<String, String>> {
+  let mut unique_algorithms: Vec<protocol::HashAlgorithm> = Vec::new();
+  for algorithm in algorithms {
+    if !unique_algorithms.contains(algorithm) {
+      unique_algorithms.push(*algorithm);
+    }
+  }
+  let mut hashers: Vec<Hasher> = unique_algorithms.into_iter().map(Hasher::new).collect();
+  let mut file = File::open(path).map_err(anyhow::Error::msg)?;
+  let mut buffer = vec![0u8; BUFFER_SIZE];
+  loop {
+    let length = file.read(&mut buffer).map_err(anyhow::Error::msg)?;
+    if length == 0 {
+      break;
+    }
+    hashers.iter_mut().for_each(|hasher| hasher.update(&buffer[..length]));
+  }
+  Ok(hashers.into_iter().map(|hasher| hasher.finalize()).collect())
+}
+
+/// Hashes the files in parallel into the metadata and reports the progress after each file.
+/// A file that cannot be read is logged and keeps its metadata untouched.
+pub fn hash_items<F>(items: &mut [protocol::Item], algorithms: &[protocol::HashAlgorithm], on_progress: F)
+where
+  F: Fn(protocol::HashProgress) + Sync,
+{
+  if algorithms.is_empty() {
+    return;
+  }
+  let total = items
+    .iter()
+    .filter(|item| item.item_type == protocol::ItemType::File)
+    .count();
+  let completed = AtomicUsize::new(0);
+  items
+    .par_iter_mut()
+    .filter(|item| item.item_type == protocol::ItemType::File)
+    .for_each(|item| {
+      match hash_file(Path::new(item.source_path.as_str()), algorithms) {
+        Ok(hashes) => item.metadata.extend(hashes),
+        Err(err) => log::warn!("Couldn't hash {} because {}", item.source_path, err),
+      }
+      on_progress(protocol::HashProgress {
+        completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+        source_path: item.source_path.clone(),
+        total,
+      });
+    });
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+  bytes
+    .iter()
+    .fold(String::with_capacity(bytes.len() * 2), |mut text, byte| {
+      let _ = write!(text, "{:02x}", byte);
+      text
+    })
+}
+
+/// Verifies the bracketed CRC32 tags in the source names against the content in parallel.
+/// The CRC32 already in the metadata is reused, and the target paths get the tags added to the files
+/// without tags or stripped from the verified files according to the action.
+pub fn verify_items(items: &[protocol::Item], options: &protocol::VerifyOptions) -> Vec<protocol::VerifyResult> {
+  items
+    .par_iter()
+    .filter(|item| item.item_type == protocol::ItemType::File)
+    .map(|item| {
+      let source_path = Path::new(item.source_path.as_str());
+      let expected = source_path
+        .file_stem()
+        .and_then(|stem| get_crc_tag(stem.to_string_lossy().as_ref()))
+        .map(|(_, crc)| crc);
+      let actual = match item.metadata.get(FIELD_CRC32) {
+        Some(crc) => Ok(crc.clone()),
+        None => hash_file(source_path, &[protocol::HashAlgorithm::Crc32]).and_then(|hashes| {
+          hashes
+            .get(FIELD_CRC32)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("CRC32 is missing."))
+        }),
+      };
+      match actual {
+        Ok(actual) => {
+          let status = match &expected {
+            Some(expected) if *expected == actual => protocol::VerifyStatus::Match,
+            Some(_) => protocol::VerifyStatus::Mismatch,
+            None => protocol::VerifyStatus::Missing,
+          };
+          // A mismatched tag is kept so that the broken file stays visible.
+          let target_path = match (status, options.action) {
+            (protocol::VerifyStatus::Missing, protocol::CrcTagAction::Add)
+            | (protocol::VerifyStatus::Match, protocol::CrcTagAction::Strip) => {
+              get_tagged_path(item.target_path.as_str(), options.action, actual.as_str())
+            }
+            _ => item.target_path.clone(),
+          };
+          protocol::VerifyResult {
+            actual: Some(actual),
+            error: None,
+            expected,
+            source_path: item.source_path.clone(),
+            status,
+            target_path,
+          }
+        }
+        Err(err) => protocol::VerifyResult {
+          actual: None,
+          error: Some(err.to_string()),
+          expected,
+          source_path: item.source_path.clone(),
+          status: protocol::VerifyStatus::Error,
+          target_path: item.target_path.clone(),
+        },
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_get_crc_tag_accepts_digits_only() {
+    assert_eq!(Some((5..15, "12345678".to_string())), get_crc_tag("Show [12345678]"));
+  }
+
+  #[test]
+  fn test_get_crc_tag_takes_the_last_tag_in_upper_case() {
+    assert_eq!(
+      Some((19..29, "ABCD1234".to_string())),
+      get_crc_tag("[Group] [00000000] [abcd1234]")
+    );
+  }
+
+  #[test]
+  fn test_get_crc_tag_rejects_parentheses_and_other_lengths() {
+    assert_eq!(None, get_crc_tag("Show (ABCD1234)"));
+    assert_eq!(None, get_crc_tag("Show [ABCD1234)"));
+    assert_eq!(None, get_crc_tag("Show [ABCD12345]"));
+    assert_eq!(None, get_crc_tag("Show [ABCD123]"));
+  }
+
+  #[test]
+  fn test_to_hex() {
+    assert_eq!("00ff10", to_hex(&[0x00, 0xFF, 0x10]));
+  }
+}
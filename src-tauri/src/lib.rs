@@ -1,93 +1,312 @@
-/*
-* Copyright (c) 2024-2025. caoccao.com Sam Cao
-* All rights reserved.
-
-* Licensed under the Apache License, Version 2.0 (the "License");
-* you may not use this file except in compliance with the License.
-* You may obtain a copy of the License at
-
-* http://www.apache.org/licenses/LICENSE-2.0
-
-* Unless required by applicable law or agreed to in writing, software
-* distributed under the License is distributed on an "AS IS" BASIS,
-* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
-* See the License for the specific language governing permissions and
-* limitations under the License.
-*/
-
-use tauri::Manager;
-
-mod config;
-mod controller;
-mod plugins;
-mod protocol;
-
-fn convert_error(error: anyhow::Error) -> String {
-  error.to_string()
-}
-
-#[tauri::command]
-async fn get_built_in_plugins() -> Result<Vec<config::ConfigPlugin>, String> {
-  log::debug!("get_built_in_plugins");
-  controller::get_built_in_plugins().await.map_err(convert_error)
-}
-
-#[tauri::command]
-async fn get_config() -> Result<config::Config, String> {
-  log::debug!("get_config");
-  controller::get_config().await.map_err(convert_error)
-}
-
-#[tauri::command]
-async fn rename_items(items: Vec<protocol::Item>) -> Result<usize, String> {
-  log::debug!("rename_items: {:?}", items);
-  controller::rename_items(items).await.map_err(convert_error)
-}
-
-#[tauri::command]
-async fn scan_items(
-  items: Vec<protocol::Item>,
-  depth: i32,
-  include_directory: bool,
-  extensions: Vec<String>,
-) -> Result<Vec<protocol::Item>, String> {
-  log::debug!(
-    "scan_items: {:?}, depth: {}, include_directory: {}, extensions: {:?}",
-    items,
-    depth,
-    include_directory,
-    extensions
-  );
-  controller::scan_items(items, depth, include_directory, extensions)
-    .await
-    .map_err(convert_error)
-}
-
-#[tauri::command]
-async fn set_config(config: config::Config) -> Result<config::Config, String> {
-  log::debug!("set_config({:?})", config);
-  controller::set_config(config).await.map_err(convert_error)
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-  env_logger::init();
-  tauri::Builder::default()
-    .plugin(tauri_plugin_dialog::init())
-    .plugin(tauri_plugin_clipboard_manager::init())
-    .plugin(tauri_plugin_cli::init())
-    .setup(|app| {
-      let window = app.get_webview_window("main").unwrap();
-      let _ = window.set_title("Batch File Rename v0.2.0");
-      Ok(())
-    })
-    .invoke_handler(tauri::generate_handler![
-      get_built_in_plugins,
-      get_config,
-      rename_items,
-      scan_items,
-      set_config
-    ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
-}
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use tauri::{Emitter, Manager};
+use tauri_plugin_cli::CliExt;
+
+mod case;
+mod cli;
+mod companions;
+mod config;
+mod controller;
+mod exif;
+mod hashes;
+mod media;
+mod metadata;
+mod organize;
+mod plugins;
+mod protocol;
+mod rules;
+mod sanitize;
+mod subtitles;
+mod tags;
+mod template;
+mod trash;
+mod unicode;
+mod video;
+
+fn convert_error(error: anyhow::Error) -> String {
+  error.to_string()
+}
+
+#[tauri::command]
+async fn export_plugin(id: String, path: String) -> Result<(), String> {
+  log::debug!("export_plugin: {}, path: {}", id, path);
+  controller::export_plugin(id, path).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn find_duplicates(
+  items: Vec<protocol::Item>,
+  options: protocol::DuplicateOptions,
+) -> Result<Vec<protocol::DuplicateGroup>, String> {
+  log::debug!("find_duplicates: {}, options: {:?}", items.len(), options);
+  controller::find_duplicates(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn find_normalization_duplicates(
+  items: Vec<protocol::Item>,
+) -> Result<Vec<protocol::NormalizationDuplicateGroup>, String> {
+  log::debug!("find_normalization_duplicates: {}", items.len());
+  controller::find_normalization_duplicates(items)
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn flatten_items(
+  items: Vec<protocol::Item>,
+  options: protocol::FlattenOptions,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!("flatten_items: {}, options: {:?}", items.len(), options);
+  controller::flatten_items(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn get_built_in_plugins() -> Result<Vec<config::ConfigPlugin>, String> {
+  log::debug!("get_built_in_plugins");
+  controller::get_built_in_plugins().await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn get_config() -> Result<config::Config, String> {
+  log::debug!("get_config");
+  controller::get_config().await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn import_plugin(path: String, overwrite: bool) -> Result<config::ConfigPlugin, String> {
+  log::debug!("import_plugin: {}, overwrite: {}", path, overwrite);
+  controller::import_plugin(path, overwrite).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn match_subtitles(
+  items: Vec<protocol::Item>,
+  options: protocol::SubtitleMatchOptions,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!("match_subtitles: {}, options: {:?}", items.len(), options);
+  controller::match_subtitles(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn organize_items(
+  items: Vec<protocol::Item>,
+  options: protocol::OrganizeOptions,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!("organize_items: {}, options: {:?}", items.len(), options);
+  controller::organize_items(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn preview_regex_rename(
+  items: Vec<protocol::Item>,
+  options: protocol::RegexRenameOptions,
+) -> Result<Vec<protocol::RegexRenamePreview>, String> {
+  log::debug!("preview_regex_rename: {}, options: {:?}", items.len(), options);
+  controller::preview_regex_rename(items, options)
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn regex_rename_items(
+  items: Vec<protocol::Item>,
+  options: protocol::RegexRenameOptions,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!("regex_rename_items: {}, options: {:?}", items.len(), options);
+  controller::regex_rename_items(items, options)
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn remove_empty_directories(
+  directories: Vec<String>,
+  roots: Vec<String>,
+) -> Result<protocol::RemoveDirectoriesResult, String> {
+  log::debug!("remove_empty_directories: {:?}, roots: {:?}", directories, roots);
+  controller::remove_empty_directories(directories, roots)
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn rename_items(
+  items: Vec<protocol::Item>,
+  options: Option<protocol::RenameOptions>,
+) -> Result<protocol::RenameResult, String> {
+  log::debug!("rename_items: {:?}, options: {:?}", items, options);
+  controller::rename_items(items, options.unwrap_or_default())
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn resolve_pipeline(id: String) -> Result<Vec<protocol::PipelineStep>, String> {
+  log::debug!("resolve_pipeline: {}", id);
+  controller::resolve_pipeline(id).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn restore_from_trash(paths: Vec<String>) -> Result<protocol::RestoreResult, String> {
+  log::debug!("restore_from_trash: {:?}", paths);
+  controller::restore_from_trash(paths).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn run_native_plugin(
+  id: String,
+  options: std::collections::BTreeMap<String, serde_json::Value>,
+  items: Vec<protocol::Item>,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!(
+    "run_native_plugin: {}, options: {:?}, items: {}",
+    id,
+    options,
+    items.len()
+  );
+  controller::run_native_plugin(id, options, items)
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn sanitize_items(
+  items: Vec<protocol::Item>,
+  options: protocol::SanitizeOptions,
+) -> Result<Vec<protocol::SanitizeResult>, String> {
+  log::debug!("sanitize_items: {}, options: {:?}", items.len(), options);
+  controller::sanitize_items(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn scan_items(
+  app_handle: tauri::AppHandle,
+  items: Vec<protocol::Item>,
+  options: protocol::ScanOptions,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!("scan_items: {:?}, options: {:?}", items, options);
+  controller::scan_items(items, options, |progress| {
+    let _ = app_handle.emit("hash-progress", progress);
+  })
+  .await
+  .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn set_config(config: config::Config) -> Result<config::Config, String> {
+  log::debug!("set_config({:?})", config);
+  controller::set_config(config).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn unflatten_items(
+  items: Vec<protocol::Item>,
+  options: protocol::UnflattenOptions,
+) -> Result<Vec<protocol::Item>, String> {
+  log::debug!("unflatten_items: {}, options: {:?}", items.len(), options);
+  controller::unflatten_items(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn validate_plugin_options(
+  id: String,
+  options: std::collections::BTreeMap<String, serde_json::Value>,
+) -> Result<(), String> {
+  log::debug!("validate_plugin_options: {}, options: {:?}", id, options);
+  controller::validate_plugin_options(id, options)
+    .await
+    .map_err(convert_error)
+}
+
+#[tauri::command]
+async fn verify_items(
+  items: Vec<protocol::Item>,
+  options: protocol::VerifyOptions,
+) -> Result<Vec<protocol::VerifyResult>, String> {
+  log::debug!("verify_items: {}, options: {:?}", items.len(), options);
+  controller::verify_items(items, options).await.map_err(convert_error)
+}
+
+#[tauri::command]
+async fn write_tags(
+  items: Vec<protocol::Item>,
+  options: protocol::WriteTagsOptions,
+) -> Result<Vec<protocol::TagWriteResult>, String> {
+  log::debug!("write_tags: {}, options: {:?}", items.len(), options);
+  controller::write_tags(items, options).await.map_err(convert_error)
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+  env_logger::init();
+  tauri::Builder::default()
+    .plugin(tauri_plugin_dialog::init())
+    .plugin(tauri_plugin_clipboard_manager::init())
+    .plugin(tauri_plugin_cli::init())
+    .setup(|app| {
+      match app.cli().matches() {
+        Ok(matches) => match cli::run(&matches) {
+          Ok(true) => std::process::exit(0),
+          Ok(false) => {}
+          Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+          }
+        },
+        Err(err) => log::error!("Couldn't parse the command line because {}", err),
+      }
+      let window = app.get_webview_window("main").unwrap();
+      let _ = window.set_title("Batch File Rename v0.2.0");
+      let app_handle = app.handle().clone();
+      if let Err(err) = plugins::watch_external_plugins(move || {
+        let _ = app_handle.emit("external-plugins-changed", ());
+      }) {
+        log::error!("Couldn't watch external plugins because {}", err);
+      }
+      Ok(())
+    })
+    .invoke_handler(tauri::generate_handler![
+      export_plugin,
+      find_duplicates,
+      find_normalization_duplicates,
+      flatten_items,
+      get_built_in_plugins,
+      get_config,
+      import_plugin,
+      match_subtitles,
+      organize_items,
+      preview_regex_rename,
+      regex_rename_items,
+      remove_empty_directories,
+      rename_items,
+      resolve_pipeline,
+      restore_from_trash,
+      run_native_plugin,
+      sanitize_items,
+      scan_items,
+      set_config,
+      unflatten_items,
+      validate_plugin_options,
+      verify_items,
+      write_tags
+    ])
+    .run(tauri::generate_context!())
+    .expect("error while running tauri application");
+}
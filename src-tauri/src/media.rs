@@ -0,0 +1,277 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+pub const FIELD_CODEC: &str = "mediaCodec";
+pub const FIELD_EPISODE: &str = "mediaEpisode";
+pub const FIELD_EPISODES: &str = "mediaEpisodes";
+pub const FIELD_GROUP: &str = "mediaGroup";
+pub const FIELD_MEDIA_TYPE: &str = "mediaType";
+pub const FIELD_RESOLUTION: &str = "mediaResolution";
+pub const FIELD_SEASON: &str = "mediaSeason";
+pub const FIELD_SOURCE: &str = "mediaSource";
+pub const FIELD_TITLE: &str = "mediaTitle";
+pub const FIELD_YEAR: &str = "mediaYear";
+
+pub const MEDIA_TYPE_EPISODE: &str = "Episode";
+pub const MEDIA_TYPE_MOVIE: &str = "Movie";
+
+static CODEC_REGEX: Lazy<Regex> = Lazy::new(|| {
+  new_regex(r"(?:^|[ ._\[(-])(x\.?264|x\.?265|h\.?264|h\.?265|hevc|avc|xvid|divx|av1|vp9)(?:$|[ ._\])-])")
+});
+static EPISODE_REGEX: Lazy<Regex> = Lazy::new(|| {
+  new_regex(r"(?:^|[ ._\[(-])s(\d{1,3})[ ._-]?((?:e\d{1,4})(?:[ ._-]*-?[ ._-]*e\d{1,4})*)(?:$|[^a-z0-9])")
+});
+static EPISODE_NUMBER_REGEX: Lazy<Regex> = Lazy::new(|| new_regex(r"\d+"));
+static EPISODE_X_REGEX: Lazy<Regex> =
+  Lazy::new(|| new_regex(r"(?:^|[ ._\[(-])(\d{1,2})x(\d{2,3})(?:-(\d{2,3}))?(?:$|[^a-z0-9])"));
+static GROUP_PREFIX_REGEX: Lazy<Regex> = Lazy::new(|| new_regex(r"^\[([^\]]+)\]"));
+static GROUP_SUFFIX_REGEX: Lazy<Regex> = Lazy::new(|| new_regex(r"-([a-z0-9]+)(?:\[[^\]]*\])?$"));
+static RESOLUTION_REGEX: Lazy<Regex> =
+  Lazy::new(|| new_regex(r"(?:^|[ ._\[(-])(2160p|1440p|1080p|1080i|720p|576p|480p|4k|uhd)(?:$|[ ._\])-])"));
+static SOURCE_REGEX: Lazy<Regex> = Lazy::new(|| {
+  new_regex(r"(?:^|[ ._\[(-])(blu-?ray|bdrip|brrip|web-?dl|webrip|web|hdtv|dvdrip|dvd|hdrip|remux)(?:$|[ ._\])-])")
+});
+static YEAR_REGEX: Lazy<Regex> = Lazy::new(|| new_regex(r"(?:^|[ ._\[(-])((?:19|20)\d{2})(?:$|[ ._\])-])"));
+
+fn new_regex(pattern: &str) -> Regex {
+  RegexBuilder::new(pattern).case_insensitive(true).build().unwrap()
+}
+
+fn get_codec(codec: &str) -> String {
+  match codec.to_lowercase().replace('.', "").as_str() {
+    "avc" | "h264" => "h264".to_string(),
+    "h265" | "hevc" => "hevc".to_string(),
+    codec => codec.to_string(),
+  }
+}
+
+fn get_source(source: &str) -> String {
+  match source.to_lowercase().replace('-', "").as_str() {
+    "bdrip" => "BDRip".to_string(),
+    "bluray" => "BluRay".to_string(),
+    "brrip" => "BRRip".to_string(),
+    "dvd" => "DVD".to_string(),
+    "dvdrip" => "DVDRip".to_string(),
+    "hdrip" => "HDRip".to_string(),
+    "hdtv" => "HDTV".to_string(),
+    "remux" => "REMUX".to_string(),
+    "web" => "WEB".to_string(),
+    "webdl" => "WEB-DL".to_string(),
+    _ => "WEBRip".to_string(),
+  }
+}
+
+/// Cleans up the title by replacing the dots and the underscores with spaces.
+fn get_title(text: &str) -> String {
+  let text = text.replace(['.', '_'], " ");
+  let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+  text
+    .trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '(' | '[' | '{'))
+    .to_string()
+}
+
+/// Parses a release style name like "Show.Name.S01E02E03.1080p.WEB-DL.x264-GRP" or "Movie.Name.2019.1080p.BluRay".
+/// The title is the text before the episode, the year or the resolution,
+/// and the source, the codec and the resolution are only recognized after them.
+/// It returns an empty map if neither an episode nor a movie is recognized.
+pub fn parse_media_name(name: &str) -> BTreeMap<String, String> {
+  let mut fields: BTreeMap<String, String> = BTreeMap::new();
+  let mut text = name;
+  if let Some(captures) = GROUP_PREFIX_REGEX.captures(text) {
+    fields.insert(FIELD_GROUP.to_string(), captures[1].trim().to_string());
+    text = &text[captures.get(0).unwrap().end()..];
+  }
+  let mut title_end = text.len();
+  let mut anchor_end: Option<usize> = None;
+  let mut update_anchor = |range: Range<usize>| {
+    title_end = title_end.min(range.start);
+    anchor_end = Some(anchor_end.map_or(range.end, |anchor_end| anchor_end.max(range.end)));
+  };
+  if let Some(captures) = EPISODE_REGEX.captures(text) {
+    let episodes: Vec<u32> = EPISODE_NUMBER_REGEX
+      .find_iter(&captures[2])
+      .filter_map(|number| number.as_str().parse::<u32>().ok())
+      .collect();
+    insert_episode(&mut fields, &captures[1], &episodes);
+    update_anchor(captures.get(0).unwrap().range());
+  } else if let Some(captures) = EPISODE_X_REGEX.captures(text) {
+    let episodes: Vec<u32> = [captures.get(2), captures.get(3)]
+      .into_iter()
+      .flatten()
+      .filter_map(|number| number.as_str().parse::<u32>().ok())
+      .collect();
+    insert_episode(&mut fields, &captures[1], &episodes);
+    update_anchor(captures.get(0).unwrap().range());
+  }
+  // A year at the very beginning is part of the title like "2001 A Space Odyssey 1968".
+  if let Some(captures) = YEAR_REGEX
+    .captures_iter(text)
+    .filter(|captures| captures.get(1).unwrap().start() > 0)
+    .last()
+  {
+    fields.insert(FIELD_YEAR.to_string(), captures[1].to_string());
+    update_anchor(captures.get(0).unwrap().range());
+  }
+  let mut is_movie = fields.contains_key(FIELD_YEAR);
+  // The tokens are only searched after the episode or the year so that a title like "The Web" is kept.
+  // Without them, the text before the resolution is the title.
+  let anchor = match anchor_end {
+    Some(anchor_end) => anchor_end,
+    None => RESOLUTION_REGEX
+      .find(text)
+      .map_or(text.len(), |resolution| resolution.start()),
+  };
+  let tokens = &text[anchor..];
+  if let Some(captures) = RESOLUTION_REGEX.captures(tokens) {
+    let resolution = captures[1].to_lowercase();
+    let resolution = if resolution == "uhd" {
+      "4k".to_string()
+    } else {
+      resolution
+    };
+    fields.insert(
+      FIELD_RESOLUTION.to_string(),
+      resolution.to_uppercase().replace('P', "p").replace('I', "i"),
+    );
+    title_end = title_end.min(anchor + captures.get(0).unwrap().start());
+    is_movie = true;
+  }
+  if let Some(captures) = SOURCE_REGEX.captures(tokens) {
+    fields.insert(FIELD_SOURCE.to_string(), get_source(&captures[1]));
+    is_movie = true;
+  }
+  if let Some(captures) = CODEC_REGEX.captures(tokens) {
+    fields.insert(FIELD_CODEC.to_string(), get_codec(&captures[1]));
+    is_movie = true;
+  }
+  // A group suffix is only taken after a recognized token so that "Spider-Man" is not a group.
+  if title_end < text.len() && !fields.contains_key(FIELD_GROUP) {
+    if let Some(captures) = GROUP_SUFFIX_REGEX.captures(text) {
+      if captures.get(0).unwrap().start() >= title_end {
+        fields.insert(FIELD_GROUP.to_string(), captures[1].to_string());
+      }
+    }
+  }
+  let media_type = if fields.contains_key(FIELD_EPISODE) {
+    MEDIA_TYPE_EPISODE
+  } else if is_movie {
+    MEDIA_TYPE_MOVIE
+  } else {
+    return BTreeMap::new();
+  };
+  fields.insert(FIELD_MEDIA_TYPE.to_string(), media_type.to_string());
+  let title = get_title(&text[..title_end]);
+  if !title.is_empty() {
+    fields.insert(FIELD_TITLE.to_string(), title);
+  }
+  fields
+}
+
+/// Inserts the season, the first episode and the episodes like "E02" or "E02-E03".
+fn insert_episode(fields: &mut BTreeMap<String, String>, season: &str, episodes: &[u32]) {
+  let (first, last) = match (episodes.first(), episodes.last()) {
+    (Some(first), Some(last)) => (*first, *last),
+    _ => return,
+  };
+  let season = season.parse::<u32>().unwrap_or_default();
+  fields.insert(FIELD_SEASON.to_string(), season.to_string());
+  fields.insert(FIELD_EPISODE.to_string(), first.to_string());
+  let episodes = if last > first {
+    format!("E{:02}-E{:02}", first, last)
+  } else {
+    format!("E{:02}", first)
+  };
+  fields.insert(FIELD_EPISODES.to_string(), episodes);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn get_fields(name: &str) -> Vec<(String, String)> {
+    parse_media_name(name).into_iter().collect()
+  }
+
+  fn new_fields(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+    let fields: BTreeMap<String, String> = fields
+      .iter()
+      .map(|(field, value)| (field.to_string(), value.to_string()))
+      .collect();
+    fields.into_iter().collect()
+  }
+
+  #[test]
+  fn test_parse_media_name_parses_an_episode() {
+    assert_eq!(
+      new_fields(&[
+        (FIELD_CODEC, "x264"),
+        (FIELD_EPISODE, "2"),
+        (FIELD_EPISODES, "E02-E03"),
+        (FIELD_GROUP, "GRP"),
+        (FIELD_MEDIA_TYPE, MEDIA_TYPE_EPISODE),
+        (FIELD_RESOLUTION, "1080p"),
+        (FIELD_SEASON, "1"),
+        (FIELD_SOURCE, "WEB-DL"),
+        (FIELD_TITLE, "Show Name"),
+      ]),
+      get_fields("Show.Name.S01E02E03.1080p.WEB-DL.x264-GRP")
+    );
+  }
+
+  #[test]
+  fn test_parse_media_name_parses_a_movie() {
+    assert_eq!(
+      new_fields(&[
+        (FIELD_MEDIA_TYPE, MEDIA_TYPE_MOVIE),
+        (FIELD_RESOLUTION, "2160p"),
+        (FIELD_SOURCE, "BluRay"),
+        (FIELD_TITLE, "2001 A Space Odyssey"),
+        (FIELD_YEAR, "1968"),
+      ]),
+      get_fields("2001.A.Space.Odyssey.1968.2160p.BluRay")
+    );
+    assert_eq!(
+      new_fields(&[
+        (FIELD_MEDIA_TYPE, MEDIA_TYPE_MOVIE),
+        (FIELD_RESOLUTION, "720p"),
+        (FIELD_TITLE, "Movie"),
+      ]),
+      get_fields("Movie.720p")
+    );
+    assert!(get_fields("Holiday Photos").is_empty());
+  }
+
+  #[test]
+  fn test_parse_media_name_keeps_the_tokens_in_the_title() {
+    let fields = parse_media_name("The.Web.S01E01.720p.HDTV.x265");
+    assert_eq!(Some(&"The Web".to_string()), fields.get(FIELD_TITLE));
+    assert_eq!(Some(&"HDTV".to_string()), fields.get(FIELD_SOURCE));
+    assert_eq!(Some(&"x265".to_string()), fields.get(FIELD_CODEC));
+    let fields = parse_media_name("DVD.Rental.Story.2019.1080p.WEBRip");
+    assert_eq!(Some(&"DVD Rental Story".to_string()), fields.get(FIELD_TITLE));
+    assert_eq!(Some(&"WEBRip".to_string()), fields.get(FIELD_SOURCE));
+    let fields = parse_media_name("Spider-Man.HEVC.Story.2019");
+    assert_eq!(Some(&"Spider-Man HEVC Story".to_string()), fields.get(FIELD_TITLE));
+    assert_eq!(None, fields.get(FIELD_CODEC));
+    assert_eq!(None, fields.get(FIELD_GROUP));
+  }
+}
@@ -0,0 +1,222 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::exif;
+use crate::media;
+use crate::protocol;
+use crate::tags;
+use crate::template::Template;
+use crate::video;
+
+/// Reads the metadata of the given types into the files in parallel.
+/// A file that cannot be parsed is logged and keeps its metadata untouched.
+pub fn read_metadata(items: &mut [protocol::Item], metadata_types: &[protocol::MetadataType]) {
+  if metadata_types.is_empty() {
+    return;
+  }
+  items
+    .par_iter_mut()
+    .filter(|item| item.item_type == protocol::ItemType::File)
+    .for_each(|item| {
+      let path = Path::new(item.source_path.as_str());
+      for metadata_type in metadata_types {
+        let result = match metadata_type {
+          protocol::MetadataType::Audio => {
+            if tags::is_audio_file(path) {
+              tags::read_audio_tags(path)
+            } else {
+              continue;
+            }
+          }
+          protocol::MetadataType::Image => {
+            if exif::is_image_file(path) {
+              exif::read_image_metadata(path)
+            } else {
+              continue;
+            }
+          }
+          protocol::MetadataType::Media => {
+            // The fields parsed from the file name are prefixed with media to be apart from the embedded metadata.
+            let stem = path
+              .file_stem()
+              .map(|stem| stem.to_string_lossy().to_string())
+              .unwrap_or_default();
+            item.metadata.extend(media::parse_media_name(stem.as_str()));
+            continue;
+          }
+          protocol::MetadataType::Video => {
+            if video::is_video_file(path) {
+              video::read_video_metadata(path)
+            } else {
+              continue;
+            }
+          }
+        };
+        match result {
+          Ok(metadata) => item.metadata.extend(metadata),
+          Err(err) => log::warn!(
+            "Couldn't read {:?} metadata of {} because {}",
+            metadata_type,
+            path.display(),
+            err
+          ),
+        }
+      }
+    });
+}
+
+/// Parses the file names with the pattern and writes the fields into the audio tags.
+/// A pattern with "/" also matches the parent directories.
+/// The files are not touched in dry run, and every file gets its own result.
+pub fn write_audio_tags(
+  items: &[protocol::Item],
+  options: &protocol::WriteTagsOptions,
+) -> Result<Vec<protocol::TagWriteResult>> {
+  let template = Template::parse(options.pattern.as_str())?;
+  let fields = template.get_fields();
+  if fields.is_empty() {
+    return Err(anyhow::anyhow!("Pattern {} doesn't have any fields.", options.pattern));
+  }
+  if let Some(field) = fields.iter().find(|field| !tags::TAGS.contains(field)) {
+    return Err(anyhow::anyhow!(
+      "Tag {} is not supported. The supported tags are {}.",
+      field,
+      tags::TAGS.join(", ")
+    ));
+  }
+  let matcher = template.get_matcher()?;
+  let component_count = template.get_separator_count() + 1;
+  let results = items
+    .par_iter()
+    .filter(|item| item.item_type != protocol::ItemType::Directory)
+    .map(|item| {
+      let path = Path::new(item.source_path.as_str());
+      let mut result = protocol::TagWriteResult {
+        error: None,
+        source_path: item.source_path.clone(),
+        tags: BTreeMap::new(),
+      };
+      let text = path
+        .with_extension("")
+        .iter()
+        .rev()
+        .take(component_count)
+        .map(|component| component.to_string_lossy())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>()
+        .join("/");
+      let values = match matcher.captures(text.as_str()) {
+        Some(values) => values,
+        None => {
+          result.error = Some(format!("{} doesn't match pattern {}.", text, options.pattern));
+          return result;
+        }
+      };
+      for (field, value) in values {
+        match tags::normalize_tag(field.as_str(), value.as_str()) {
+          Some(value) => {
+            result.tags.insert(field, value);
+          }
+          None => {
+            result.error = Some(format!("Tag {} has an invalid value {}.", field, value));
+            return result;
+          }
+        }
+      }
+      if !tags::is_audio_file(path) {
+        result.error = Some(format!("{} is not an audio file.", path.display()));
+      } else if !options.dry_run {
+        if let Err(err) = tags::write_audio_tags(path, &result.tags) {
+          result.error = Some(err.to_string());
+        }
+      }
+      result
+    })
+    .collect();
+  Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_options(pattern: &str) -> protocol::WriteTagsOptions {
+    protocol::WriteTagsOptions {
+      dry_run: true,
+      pattern: pattern.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_write_audio_tags_matches_the_parent_directories() {
+    let items = vec![protocol::Item::new_file("/music/Band/Album/03 - Song.mp3")];
+    let results = write_audio_tags(&items, &new_options("{artist}/{album}/{track} - {title}")).unwrap();
+    let tags: Vec<(&str, &str)> = results[0]
+      .tags
+      .iter()
+      .map(|(tag, value)| (tag.as_str(), value.as_str()))
+      .collect();
+    assert_eq!(
+      vec![
+        ("album", "Album"),
+        ("artist", "Band"),
+        ("title", "Song"),
+        ("track", "3")
+      ],
+      tags
+    );
+    assert_eq!(None, results[0].error);
+  }
+
+  #[test]
+  fn test_write_audio_tags_accepts_the_repeated_fields() {
+    let items = vec![
+      protocol::Item::new_file("/music/Band - Song (Band).mp3"),
+      protocol::Item::new_file("/music/Band - Song (Other).mp3"),
+    ];
+    let results = write_audio_tags(&items, &new_options("{artist} - {title} ({artist})")).unwrap();
+    assert_eq!(Some(&"Band".to_string()), results[0].tags.get("artist"));
+    assert_eq!(None, results[0].error);
+    assert_eq!(
+      Some("Band - Song (Other) doesn't match pattern {artist} - {title} ({artist}).".to_string()),
+      results[1].error
+    );
+  }
+
+  #[test]
+  fn test_write_audio_tags_counts_only_the_literal_separators() {
+    // The "/" in the date format doesn't span a parent directory.
+    let items = vec![protocol::Item::new_file("/music/2004 Song.mp3")];
+    let results = write_audio_tags(&items, &new_options("{year:%Y/%m} {title}")).unwrap();
+    assert_eq!(Some(&"2004".to_string()), results[0].tags.get("year"));
+    assert_eq!(None, results[0].error);
+  }
+
+  #[test]
+  fn test_write_audio_tags_rejects_unsupported_tags() {
+    let error = write_audio_tags(&[], &new_options("{artist} - {name}")).unwrap_err();
+    assert!(error.to_string().starts_with("Tag name is not supported."));
+    assert!(write_audio_tags(&[], &new_options("Song")).is_err());
+  }
+}
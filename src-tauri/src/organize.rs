@@ -0,0 +1,505 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::exif;
+use crate::protocol;
+use crate::sanitize;
+use crate::subtitles;
+use crate::tags;
+use crate::template;
+use crate::video;
+
+const ARCHIVE_EXTENSIONS: [&str; 9] = ["7z", "bz2", "gz", "iso", "rar", "tar", "xz", "zip", "zst"];
+const BITMAP_EXTENSIONS: [&str; 7] = ["avif", "bmp", "gif", "ico", "png", "svg", "webp"];
+const DOCUMENT_EXTENSIONS: [&str; 14] = [
+  "csv", "doc", "docx", "epub", "md", "odp", "ods", "odt", "pdf", "ppt", "pptx", "rtf", "txt", "xlsx",
+];
+
+pub const CATEGORY_ARCHIVES: &str = "Archives";
+pub const CATEGORY_AUDIO: &str = "Audio";
+pub const CATEGORY_DOCUMENTS: &str = "Documents";
+pub const CATEGORY_IMAGES: &str = "Images";
+pub const CATEGORY_OTHERS: &str = "Others";
+pub const CATEGORY_SUBTITLES: &str = "Subtitles";
+pub const CATEGORY_VIDEOS: &str = "Videos";
+
+/// Moves the files under the root into the root with the relative paths encoded in the names
+/// like "a_b_c.txt" for "a/b/c.txt". The root defaults to the common directory of the files.
+/// A colliding name gets a counter like "a_b (2).txt".
+pub fn flatten_items(items: &mut [protocol::Item], options: &protocol::FlattenOptions) -> Result<()> {
+  let root = options.root.trim();
+  let root_path = if root.is_empty() {
+    match get_common_directory(items) {
+      Some(root_path) => root_path,
+      None => return Ok(()),
+    }
+  } else {
+    PathBuf::from(root)
+  };
+  let source_path_set: HashSet<&str> = items.iter().map(|item| item.source_path.as_str()).collect();
+  let mut target_path_set: HashSet<PathBuf> = HashSet::new();
+  let mut new_target_paths: Vec<Option<PathBuf>> = Vec::new();
+  for item in items.iter() {
+    let target_path = Path::new(item.target_path.as_str());
+    let relative_path = match target_path.strip_prefix(&root_path) {
+      Ok(relative_path) if item.item_type == protocol::ItemType::File && !item.target_path.is_empty() => relative_path,
+      _ => {
+        target_path_set.insert(target_path.to_path_buf());
+        new_target_paths.push(None);
+        continue;
+      }
+    };
+    let file_name = relative_path
+      .components()
+      .map(|component| component.as_os_str().to_string_lossy().to_string())
+      .collect::<Vec<String>>()
+      .join(options.separator.as_str());
+    // The existing files which are not renamed are treated as collisions.
+    let mut new_target_path = root_path.join(file_name.as_str());
+    let mut counter = 1;
+    while target_path_set.contains(&new_target_path)
+      || (new_target_path != target_path
+        && new_target_path.exists()
+        && !source_path_set.contains(new_target_path.to_string_lossy().as_ref()))
+    {
+      counter += 1;
+      new_target_path = root_path.join(get_numbered_name(file_name.as_str(), counter));
+    }
+    target_path_set.insert(new_target_path.clone());
+    new_target_paths.push(Some(new_target_path));
+  }
+  for (item, new_target_path) in items.iter_mut().zip(new_target_paths) {
+    if let Some(new_target_path) = new_target_path {
+      item.target_path = new_target_path.to_string_lossy().to_string();
+    }
+  }
+  Ok(())
+}
+
+fn get_category(path: &Path) -> &'static str {
+  let extension = path
+    .extension()
+    .map(|extension| extension.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  let extension = extension.as_str();
+  if tags::AUDIO_EXTENSIONS.contains(&extension) {
+    CATEGORY_AUDIO
+  } else if exif::IMAGE_EXTENSIONS.contains(&extension) || BITMAP_EXTENSIONS.contains(&extension) {
+    CATEGORY_IMAGES
+  } else if video::VIDEO_EXTENSIONS.contains(&extension) {
+    CATEGORY_VIDEOS
+  } else if subtitles::SUBTITLE_EXTENSIONS.contains(&extension) {
+    CATEGORY_SUBTITLES
+  } else if DOCUMENT_EXTENSIONS.contains(&extension) {
+    CATEGORY_DOCUMENTS
+  } else if ARCHIVE_EXTENSIONS.contains(&extension) {
+    CATEGORY_ARCHIVES
+  } else {
+    CATEGORY_OTHERS
+  }
+}
+
+fn get_common_directory(items: &[protocol::Item]) -> Option<PathBuf> {
+  let mut common_path: Option<PathBuf> = None;
+  for item in items
+    .iter()
+    .filter(|item| item.item_type == protocol::ItemType::File && !item.target_path.is_empty())
+  {
+    let parent_path = Path::new(item.target_path.as_str()).parent()?;
+    common_path = Some(match common_path {
+      None => parent_path.to_path_buf(),
+      Some(common_path) => common_path
+        .components()
+        .zip(parent_path.components())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a)
+        .collect(),
+    });
+  }
+  common_path
+}
+
+/// Gets the EXIF date from the metadata or the file itself with the modification time as the fallback.
+fn get_exif_date(item: &protocol::Item) -> Option<NaiveDateTime> {
+  let path = Path::new(item.source_path.as_str());
+  let metadata = if item.metadata.contains_key(exif::FIELD_DATE) {
+    item.metadata.clone()
+  } else {
+    match exif::is_image_file(path)
+      .then(|| exif::read_image_metadata(path).ok())
+      .flatten()
+    {
+      Some(metadata) => metadata,
+      None => return get_modified_date(path),
+    }
+  };
+  let date = NaiveDate::parse_from_str(metadata.get(exif::FIELD_DATE)?.as_str(), "%Y-%m-%d").ok()?;
+  let time = metadata
+    .get(exif::FIELD_TIME)
+    .and_then(|time| NaiveTime::parse_from_str(time.as_str(), "%H%M%S").ok())
+    .unwrap_or_default();
+  Some(date.and_time(time))
+}
+
+/// Gets the upper case first letter of the name with "0-9" for digits and "#" for the others.
+fn get_first_letter(path: &Path) -> String {
+  match path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .and_then(|name| name.chars().next())
+  {
+    Some(c) if c.is_ascii_digit() => "0-9".to_string(),
+    Some(c) if c.is_alphabetic() => c.to_uppercase().to_string(),
+    _ => "#".to_string(),
+  }
+}
+
+fn get_modified_date(path: &Path) -> Option<NaiveDateTime> {
+  let modified = fs::metadata(path).ok()?.modified().ok()?;
+  Some(DateTime::<Local>::from(modified).naive_local())
+}
+
+/// Gets the name with the counter before the extension like "a (2).txt".
+fn get_numbered_name(file_name: &str, counter: usize) -> String {
+  match file_name.rfind('.') {
+    Some(index) if index > 0 => format!("{} ({}){}", &file_name[..index], counter, &file_name[index..]),
+    _ => format!("{} ({})", file_name, counter),
+  }
+}
+
+/// Gets the relative folder of the item by the rule. "/" in the date format creates nested folders.
+/// The folders "." and ".." become "_" so that the files never leave the base path.
+fn get_folder(item: &protocol::Item, options: &protocol::OrganizeOptions) -> Option<PathBuf> {
+  let path = Path::new(item.source_path.as_str());
+  let folder = match options.rule {
+    protocol::OrganizeRule::Category => get_category(path).to_string(),
+    protocol::OrganizeRule::ExifDate => get_exif_date(item)?.format(options.date_format.as_str()).to_string(),
+    protocol::OrganizeRule::FirstLetter => get_first_letter(path),
+    protocol::OrganizeRule::Metadata => item
+      .metadata
+      .get(options.field.as_str())
+      .map(|value| value.trim().replace(std::path::is_separator, "_"))
+      .filter(|value| !value.is_empty())?,
+    protocol::OrganizeRule::Modified => get_modified_date(path)?
+      .format(options.date_format.as_str())
+      .to_string(),
+  };
+  let folder: PathBuf = folder
+    .split('/')
+    .map(|component| component.trim())
+    .filter(|component| !component.is_empty())
+    .map(sanitize::escape_dot_component)
+    .collect();
+  if folder.as_os_str().is_empty() {
+    None
+  } else {
+    Some(folder)
+  }
+}
+
+/// Moves the files into the folders computed by the rule under the root or the current parent directories.
+/// The files already in their folders, the files without a folder and the files sent to the trash keep their target paths.
+pub fn organize_items(items: &mut [protocol::Item], options: &protocol::OrganizeOptions) -> Result<()> {
+  if matches!(
+    options.rule,
+    protocol::OrganizeRule::ExifDate | protocol::OrganizeRule::Modified
+  ) {
+    if options.date_format.is_empty() {
+      return Err(anyhow::anyhow!("Date format {} is invalid.", options.date_format));
+    }
+    template::validate_date_format(options.date_format.as_str())?;
+  }
+  if options.rule == protocol::OrganizeRule::Metadata && options.field.is_empty() {
+    return Err(anyhow::anyhow!("Metadata field is not specified."));
+  }
+  let root = options.root.trim();
+  items
+    .par_iter_mut()
+    .filter(|item| item.item_type == protocol::ItemType::File && !item.target_path.is_empty())
+    .for_each(|item| {
+      if let Some(folder) = get_folder(item, options) {
+        let target_path = Path::new(item.target_path.as_str());
+        let parent_path = target_path.parent().unwrap_or(Path::new(""));
+        // The files organized before are not nested again.
+        if root.is_empty() && parent_path.ends_with(&folder) {
+          return;
+        }
+        let base_path = if root.is_empty() { parent_path } else { Path::new(root) };
+        if let Some(file_name) = target_path.file_name() {
+          item.target_path = base_path.join(folder).join(file_name).to_string_lossy().to_string();
+        }
+      }
+    });
+  Ok(())
+}
+
+/// Removes the directories which become empty after the files are moved away from the given paths.
+/// The directories are pruned upwards inside the roots, and the roots and their ancestors are never removed.
+/// Without a root containing the directory, only the direct parent is pruned.
+pub fn prune_empty_directories(paths: &[String], roots: &[String]) -> protocol::RemoveDirectoriesResult {
+  let root_paths: Vec<&Path> = roots.iter().map(|root| Path::new(root.as_str())).collect();
+  let mut directories: HashSet<String> = HashSet::new();
+  for path in paths {
+    let mut parent_path = Path::new(path.as_str()).parent();
+    while let Some(directory_path) = parent_path {
+      if directory_path.as_os_str().is_empty() || root_paths.iter().any(|root| root.starts_with(directory_path)) {
+        break;
+      }
+      directories.insert(directory_path.to_string_lossy().to_string());
+      if !root_paths.iter().any(|root| directory_path.starts_with(root)) {
+        break;
+      }
+      parent_path = directory_path.parent();
+    }
+  }
+  remove_empty_directories(&directories.into_iter().collect::<Vec<String>>(), roots)
+}
+
+/// Removes the given directories which are empty from the deepest one and returns the removed ones.
+/// The roots and their parent directories are never removed, and the failures are reported per directory.
+pub fn remove_empty_directories(directories: &[String], roots: &[String]) -> protocol::RemoveDirectoriesResult {
+  let roots: Vec<&Path> = roots.iter().map(|root| Path::new(root.as_str())).collect();
+  let mut directories: Vec<&String> = directories.iter().collect();
+  directories.sort_by(|a, b| {
+    let depth = |directory: &str| Path::new(directory).components().count();
+    depth(b.as_str()).cmp(&depth(a.as_str())).then_with(|| a.cmp(b))
+  });
+  directories.dedup();
+  let mut errors: Vec<String> = Vec::new();
+  let mut removed_directories: Vec<String> = Vec::new();
+  for directory in directories {
+    let path = Path::new(directory.as_str());
+    if path.as_os_str().is_empty() || !path.is_dir() || roots.iter().any(|root| root.starts_with(path)) {
+      continue;
+    }
+    let result = path.read_dir().and_then(|mut entries| match entries.next() {
+      Some(_) => Ok(false),
+      None => fs::remove_dir(path).map(|_| true),
+    });
+    match result {
+      Ok(true) => removed_directories.push(directory.clone()),
+      Ok(false) => {}
+      Err(err) => errors.push(format!("Couldn't remove directory {} because {}", directory, err)),
+    }
+  }
+  protocol::RemoveDirectoriesResult {
+    errors,
+    removed_directories,
+  }
+}
+
+/// Splits the files of every directory into subfolders of N files like "01", "02" in the order of the names,
+/// or into subfolders named by the prefix before the separator with the prefix optionally stripped.
+pub fn unflatten_items(items: &mut [protocol::Item], options: &protocol::UnflattenOptions) -> Result<()> {
+  match options.mode {
+    protocol::UnflattenMode::Count => {
+      if options.count == 0 {
+        return Err(anyhow::anyhow!("Count of files per folder must be greater than 0."));
+      }
+      let mut directory_map: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+      for (index, item) in items.iter().enumerate() {
+        if item.item_type == protocol::ItemType::File && !item.target_path.is_empty() {
+          let parent_path = Path::new(item.target_path.as_str()).parent().unwrap_or(Path::new(""));
+          directory_map.entry(parent_path.to_path_buf()).or_default().push(index);
+        }
+      }
+      for (parent_path, mut indexes) in directory_map {
+        indexes.sort_by(|a, b| items[*a].target_path.cmp(&items[*b].target_path));
+        let chunk_count = indexes.len().div_ceil(options.count);
+        let width = chunk_count.to_string().len().max(2);
+        for (chunk_index, chunk) in indexes.chunks(options.count).enumerate() {
+          let folder = format!("{:0width$}", chunk_index + 1, width = width);
+          for index in chunk {
+            let target_path = Path::new(items[*index].target_path.as_str());
+            if let Some(file_name) = target_path.file_name() {
+              items[*index].target_path = parent_path.join(&folder).join(file_name).to_string_lossy().to_string();
+            }
+          }
+        }
+      }
+    }
+    protocol::UnflattenMode::Prefix => {
+      if options.separator.is_empty() {
+        return Err(anyhow::anyhow!("Separator of the prefix is not specified."));
+      }
+      items
+        .par_iter_mut()
+        .filter(|item| item.item_type == protocol::ItemType::File && !item.target_path.is_empty())
+        .for_each(|item| {
+          let target_path = Path::new(item.target_path.as_str());
+          let file_name = target_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default();
+          if let Some((prefix, rest)) = file_name.split_once(options.separator.as_str()) {
+            let prefix = prefix.trim();
+            if !prefix.is_empty() && !rest.is_empty() {
+              // The folder and the stripped name "." or ".." become "_" so that the file never leaves its directory.
+              let prefix = sanitize::escape_dot_component(prefix);
+              let file_name = if options.strip_prefix {
+                sanitize::escape_dot_component(rest)
+              } else {
+                file_name.as_str()
+              };
+              item.target_path = target_path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(prefix)
+                .join(file_name)
+                .to_string_lossy()
+                .to_string();
+            }
+          }
+        });
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_options(rule: protocol::OrganizeRule, date_format: &str, field: &str) -> protocol::OrganizeOptions {
+    protocol::OrganizeOptions {
+      date_format: date_format.to_string(),
+      field: field.to_string(),
+      root: String::new(),
+      rule,
+    }
+  }
+
+  fn get_target_paths(items: &[protocol::Item]) -> Vec<&str> {
+    items.iter().map(|item| item.target_path.as_str()).collect()
+  }
+
+  #[test]
+  fn test_organize_items_by_category() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/a.mp3"),
+      protocol::Item::new_file("/x/b.pdf"),
+      protocol::Item::new_file("/x/c.unknown"),
+      protocol::Item::new_file("/x/Audio/d.mp3"),
+    ];
+    let options = new_options(protocol::OrganizeRule::Category, "", "");
+    organize_items(&mut items, &options).unwrap();
+    assert_eq!(
+      vec![
+        "/x/Audio/a.mp3",
+        "/x/Documents/b.pdf",
+        "/x/Others/c.unknown",
+        "/x/Audio/d.mp3"
+      ],
+      get_target_paths(&items)
+    );
+  }
+
+  #[test]
+  fn test_organize_items_keeps_the_metadata_folders_inside_the_base_path() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/a.mp3"),
+      protocol::Item::new_file("/x/b.mp3"),
+      protocol::Item::new_file("/x/c.mp3"),
+      protocol::Item::new_file("/x/d.mp3"),
+    ];
+    for (item, artist) in items.iter_mut().zip(["..", ".", "a/../..", "Band"]) {
+      item.metadata.insert("artist".to_string(), artist.to_string());
+    }
+    let options = new_options(protocol::OrganizeRule::Metadata, "", "artist");
+    organize_items(&mut items, &options).unwrap();
+    assert_eq!(
+      vec!["/x/_/a.mp3", "/x/_/b.mp3", "/x/a_.._../c.mp3", "/x/Band/d.mp3"],
+      get_target_paths(&items)
+    );
+  }
+
+  #[test]
+  fn test_organize_items_rejects_invalid_date_formats() {
+    let mut items = vec![protocol::Item::new_file("/x/a.jpg")];
+    for date_format in ["", "%Y/%z", "%Z", "%Q"] {
+      let options = new_options(protocol::OrganizeRule::Modified, date_format, "");
+      let error = organize_items(&mut items, &options).unwrap_err();
+      assert_eq!(format!("Date format {} is invalid.", date_format), error.to_string());
+    }
+    assert_eq!(vec!["/x/a.jpg"], get_target_paths(&items));
+  }
+
+  #[test]
+  fn test_flatten_items_numbers_the_colliding_names() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/a/b_c.txt"),
+      protocol::Item::new_file("/x/a_b/c.txt"),
+      protocol::Item::new_file("/x/d/e/f"),
+    ];
+    let options = protocol::FlattenOptions {
+      root: "/x".to_string(),
+      separator: "_".to_string(),
+    };
+    flatten_items(&mut items, &options).unwrap();
+    assert_eq!(
+      vec!["/x/a_b_c.txt", "/x/a_b_c (2).txt", "/x/d_e_f"],
+      get_target_paths(&items)
+    );
+  }
+
+  #[test]
+  fn test_unflatten_items_by_count_and_prefix() {
+    let mut items: Vec<protocol::Item> = ["c", "a", "b"]
+      .iter()
+      .map(|name| protocol::Item::new_file(format!("/x/{}.txt", name).as_str()))
+      .collect();
+    let mut options = protocol::UnflattenOptions {
+      count: 2,
+      mode: protocol::UnflattenMode::Count,
+      separator: String::new(),
+      strip_prefix: false,
+    };
+    unflatten_items(&mut items, &options).unwrap();
+    assert_eq!(
+      vec!["/x/02/c.txt", "/x/01/a.txt", "/x/01/b.txt"],
+      get_target_paths(&items)
+    );
+    let mut items = vec![
+      protocol::Item::new_file("/x/Show - 01.mkv"),
+      protocol::Item::new_file("/x/Other.mkv"),
+    ];
+    options.mode = protocol::UnflattenMode::Prefix;
+    options.separator = " - ".to_string();
+    options.strip_prefix = true;
+    unflatten_items(&mut items, &options).unwrap();
+    assert_eq!(vec!["/x/Show/01.mkv", "/x/Other.mkv"], get_target_paths(&items));
+    let mut items = vec![
+      protocol::Item::new_file("/x/..-x.txt"),
+      protocol::Item::new_file("/x/.-y.txt"),
+      protocol::Item::new_file("/x/a-.."),
+    ];
+    options.separator = "-".to_string();
+    unflatten_items(&mut items, &options).unwrap();
+    assert_eq!(vec!["/x/_/x.txt", "/x/_/y.txt", "/x/a/_"], get_target_paths(&items));
+    options.strip_prefix = false;
+    let mut items = vec![protocol::Item::new_file("/x/..-x.txt")];
+    unflatten_items(&mut items, &options).unwrap();
+    assert_eq!(vec!["/x/_/..-x.txt"], get_target_paths(&items));
+  }
+}
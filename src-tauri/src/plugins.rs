@@ -15,28 +15,43 @@
 * limitations under the License.
 */
 
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
+use std::fs;
 use std::include_str;
+use std::sync::{Mutex, RwLock};
 
-use crate::config::{ConfigPlugin, ConfigPluginOption, ConfigPluginOptionBoolean, ConfigPluginOptionInteger, ConfigPluginOptionString};
+use crate::config::{
+  Config, ConfigPlugin, ConfigPluginOption, ConfigPluginOptionBoolean, ConfigPluginOptionInteger,
+  ConfigPluginOptionString,
+};
+use crate::rules;
 
 const PLUGIN_START: &str = "\n  // Plugin Start\n";
 const PLUGIN_END: &str = "\n  // Plugin End\n";
+/// The version of the plugin file format which is bumped only when the format changes.
+const PLUGIN_FILE_VERSION: &str = "1";
 
 pub static BUILT_IN_PLUGINS: Lazy<Vec<ConfigPlugin>> = Lazy::new(|| get_built_in_plugins());
+static EXTERNAL_PLUGINS: Lazy<RwLock<Vec<ConfigPlugin>>> = Lazy::new(|| RwLock::new(load_external_plugins()));
+static EXTERNAL_PLUGINS_WATCHER: Lazy<Mutex<Option<RecommendedWatcher>>> = Lazy::new(|| Mutex::new(None));
 
 fn get_built_in_plugins() -> Vec<ConfigPlugin> {
-  vec![
+  let mut plugins = vec![
     ConfigPlugin {
       code: normalize_code(include_str!("../../scripts/js/plugin-dot-case.js")),
       description:
         "Capitalize every word of the file name and replace special characters with a given separator. The default separator is dot."
           .to_string(),
+      external: false,
       id: "4eec0c65-8267-4824-a8c0-1851b9858a81".to_string(),
       name: "Dot.Case".to_string(),
+      native: false,
       options: vec![
         ConfigPluginOption::String(ConfigPluginOptionString {
           default_value: ".".to_string(),
+          description: "The separator between words.".to_string(),
           name: "separator".to_string(),
         }),
       ],
@@ -44,15 +59,19 @@ fn get_built_in_plugins() -> Vec<ConfigPlugin> {
     ConfigPlugin {
       code: normalize_code(include_str!("../../scripts/js/plugin-to-lower-case.js")),
       description: "Convert all characters of the file name to lowercase.".to_string(),
+      external: false,
       id: "7c857ca3-d26e-45bb-adf7-a1800f3691b1".to_string(),
       name: "To lower case".to_string(),
+      native: false,
       options: vec![
         ConfigPluginOption::Boolean(ConfigPluginOptionBoolean  {
           default_value: true,
+          description: "Whether the name is included.".to_string(),
           name: "includeName".to_string(),
         }),
         ConfigPluginOption::Boolean(ConfigPluginOptionBoolean {
           default_value: false,
+          description: "Whether the extension is included.".to_string(),
           name: "includeExtension".to_string(),
         }),
       ],
@@ -60,31 +79,48 @@ fn get_built_in_plugins() -> Vec<ConfigPlugin> {
     ConfigPlugin {
       code: normalize_code(include_str!("../../scripts/js/plugin-to-sequence.js")),
       description: "Convert the file name to a sequence with prefix and suffix.".to_string(),
+      external: false,
       id: "2dd17cfc-ecb3-4aad-bcbb-c8f59cf3dfe3".to_string(),
       name: "To Sequence".to_string(),
+      native: false,
       options: vec![
         ConfigPluginOption::String(ConfigPluginOptionString  {
           default_value: "".to_string(),
+          description: "The prefix before the sequence.".to_string(),
           name: "prefix".to_string(),
         }),
         ConfigPluginOption::String(ConfigPluginOptionString  {
           default_value: "".to_string(),
+          description: "The suffix after the sequence.".to_string(),
           name: "suffix".to_string(),
         }),
         ConfigPluginOption::Integer(ConfigPluginOptionInteger  {
           default_value: 1,
+          description: "The first number of the sequence.".to_string(),
+          max: None,
+          min: None,
           name: "startAt".to_string(),
+          step: None,
         }),
         ConfigPluginOption::Integer(ConfigPluginOptionInteger  {
           default_value: 1,
+          description: "The increment between numbers.".to_string(),
+          max: None,
+          min: None,
           name: "stepBy".to_string(),
+          step: None,
         }),
         ConfigPluginOption::Integer(ConfigPluginOptionInteger  {
           default_value: 2,
+          description: "The minimum length of the sequence. 0 means no padding.".to_string(),
+          max: None,
+          min: Some(0),
           name: "padStart".to_string(),
+          step: None,
         }),
         ConfigPluginOption::String(ConfigPluginOptionString  {
           default_value: "0".to_string(),
+          description: "The string to pad the sequence with.".to_string(),
           name: "padString".to_string(),
         }),
       ],
@@ -92,30 +128,217 @@ fn get_built_in_plugins() -> Vec<ConfigPlugin> {
     ConfigPlugin {
       code: normalize_code(include_str!("../../scripts/js/plugin-to-upper-case.js")),
       description: "Convert all characters of the file name to lowercase.".to_string(),
+      external: false,
       id: "afa82b1a-43de-439e-9f47-b6a666e40511".to_string(),
       name: "To UPPER CASE".to_string(),
+      native: false,
       options: vec![
         ConfigPluginOption::Boolean(ConfigPluginOptionBoolean {
           default_value: true,
+          description: "Whether the name is included.".to_string(),
           name: "includeName".to_string(),
         }),
         ConfigPluginOption::Boolean(ConfigPluginOptionBoolean {
           default_value: false,
+          description: "Whether the extension is included.".to_string(),
           name: "includeExtension".to_string(),
         }),
       ],
     },
-  ]
+  ];
+  plugins.extend(rules::NATIVE_PLUGINS.iter().cloned());
+  plugins
+}
+
+pub fn format_plugin_file(plugin: &ConfigPlugin) -> Result<String> {
+  let mut lines: Vec<String> = vec!["/*".to_string(), " * Batch File Rename Plugin".to_string()];
+  lines.push(format!(" * @version {}", PLUGIN_FILE_VERSION));
+  lines.push(format!(" * @id {}", plugin.id));
+  lines.push(format!(" * @name {}", plugin.name));
+  plugin
+    .description
+    .lines()
+    .for_each(|line| lines.push(format!(" * @description {}", line)));
+  for option in plugin.options.iter() {
+    lines.push(format!(" * @option {}", serde_json::to_string(option)?));
+  }
+  lines.push(" */".to_string());
+  lines.push("".to_string());
+  lines.push("export function plugin($args) {".to_string());
+  lines.push("  const { $sourceItems, $targetItems, $options, $modules } = $args;".to_string());
+  lines.push("  // Plugin Start".to_string());
+  plugin.code.lines().for_each(|line| {
+    lines.push(if line.is_empty() {
+      line.to_string()
+    } else {
+      format!("  {}", line)
+    })
+  });
+  lines.push("  // Plugin End".to_string());
+  lines.push("}".to_string());
+  lines.push("".to_string());
+  Ok(lines.join("\n"))
+}
+
+pub fn get_external_plugins() -> Vec<ConfigPlugin> {
+  EXTERNAL_PLUGINS.read().unwrap().clone()
+}
+
+fn load_external_plugins() -> Vec<ConfigPlugin> {
+  let plugins_path_buf = Config::get_plugins_path_buf();
+  let mut plugins: Vec<ConfigPlugin> = Vec::new();
+  if !plugins_path_buf.is_dir() {
+    return plugins;
+  }
+  log::debug!("Loading external plugins from {}.", plugins_path_buf.display());
+  let entries = match plugins_path_buf.read_dir() {
+    Ok(entries) => entries,
+    Err(err) => {
+      log::error!("Couldn't read {} because {}", plugins_path_buf.display(), err);
+      return plugins;
+    }
+  };
+  let mut paths: Vec<_> = entries
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.path())
+    .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "js"))
+    .collect();
+  paths.sort();
+  for path in paths {
+    match fs::read_to_string(&path)
+      .map_err(anyhow::Error::msg)
+      .and_then(|content| parse_plugin_file(content.as_str()))
+    {
+      Ok(mut plugin) => {
+        if plugins.iter().any(|p| p.id == plugin.id) {
+          log::warn!(
+            "Skipped external plugin {} because its id {} is duplicated.",
+            path.display(),
+            plugin.id
+          );
+        } else {
+          plugin.external = true;
+          plugins.push(plugin);
+        }
+      }
+      Err(err) => log::error!("Couldn't load external plugin {} because {}", path.display(), err),
+    }
+  }
+  plugins
 }
 
 fn normalize_code(code: &str) -> String {
-  let start_index = code.find(PLUGIN_START).expect("Couldn't find '// Plugin Start'.") + PLUGIN_START.len();
-  let end_index = code.find(PLUGIN_END).expect("Couldn't find '// Plugin End'.");
+  parse_code(code).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn parse_code(code: &str) -> Result<String> {
+  let code = code.replace("\r\n", "\n");
+  let start_index = code
+    .find(PLUGIN_START)
+    .ok_or_else(|| anyhow::anyhow!("Couldn't find '// Plugin Start'."))?
+    + PLUGIN_START.len();
+  let end_index = code
+    .find(PLUGIN_END)
+    .ok_or_else(|| anyhow::anyhow!("Couldn't find '// Plugin End'."))?;
   if end_index <= start_index {
-    panic!("{} is invalid.", code);
+    return Err(anyhow::anyhow!("{} is invalid.", code));
+  }
+  Ok(
+    code[(start_index + 1)..end_index]
+      .trim()
+      .replace("\n  ", "\n")
+      .to_string(),
+  )
+}
+
+pub fn parse_plugin_file(content: &str) -> Result<ConfigPlugin> {
+  let code = parse_code(content)?;
+  // The metadata tags are expected in the comments before '// Plugin Start'.
+  let header_end_index = content.find("// Plugin Start").unwrap_or_default();
+  let mut version: Option<String> = None;
+  let mut id: Option<String> = None;
+  let mut name: Option<String> = None;
+  let mut description_lines: Vec<String> = Vec::new();
+  let mut options: Vec<ConfigPluginOption> = Vec::new();
+  for line in content[..header_end_index].lines() {
+    let line = line.trim().trim_start_matches(['*', '/']).trim();
+    if let Some(tag) = line.strip_prefix('@') {
+      let (key, value) = tag.split_once(' ').unwrap_or((tag, ""));
+      let value = value.trim();
+      match key {
+        "description" => description_lines.push(value.to_string()),
+        "id" => id = Some(value.to_string()),
+        "name" => name = Some(value.to_string()),
+        "option" => options.push(
+          serde_json::from_str(value).map_err(|err| anyhow::anyhow!("Option {} is invalid because {}.", value, err))?,
+        ),
+        "version" => version = Some(value.to_string()),
+        _ => log::warn!("Unknown plugin tag @{}", key),
+      }
+    }
+  }
+  let version = version.ok_or_else(|| anyhow::anyhow!("Couldn't find '@version' in the plugin header."))?;
+  if parse_version(version.as_str())? > parse_version(PLUGIN_FILE_VERSION)? {
+    return Err(anyhow::anyhow!(
+      "Plugin requires version {} while the current version is {}.",
+      version,
+      PLUGIN_FILE_VERSION
+    ));
+  }
+  let id = id
+    .filter(|id| !id.is_empty())
+    .ok_or_else(|| anyhow::anyhow!("Couldn't find '@id' in the plugin header."))?;
+  let name = name
+    .filter(|name| !name.is_empty())
+    .ok_or_else(|| anyhow::anyhow!("Couldn't find '@name' in the plugin header."))?;
+  for option in options.iter() {
+    option.validate()?;
+  }
+  Ok(ConfigPlugin {
+    code,
+    description: description_lines.join("\n"),
+    external: false,
+    id,
+    name,
+    native: false,
+    options,
+  })
+}
+
+fn parse_version(version: &str) -> Result<Vec<u32>> {
+  version
+    .split('.')
+    .map(|part| {
+      part
+        .parse::<u32>()
+        .map_err(|_| anyhow::anyhow!("Version {} is invalid.", version))
+    })
+    .collect()
+}
+
+pub fn watch_external_plugins<F>(on_change: F) -> Result<()>
+where
+  F: Fn() + Send + 'static,
+{
+  let plugins_path_buf = Config::get_plugins_path_buf();
+  if !plugins_path_buf.exists() {
+    fs::create_dir_all(&plugins_path_buf).map_err(anyhow::Error::msg)?;
   }
-  code[(start_index + 1)..end_index]
-    .trim()
-    .replace("\n  ", "\n")
-    .to_string()
+  let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+    Ok(event) => {
+      if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() {
+        log::debug!("External plugins changed: {:?}", event.paths);
+        let plugins = load_external_plugins();
+        *EXTERNAL_PLUGINS.write().unwrap() = plugins;
+        on_change();
+      }
+    }
+    Err(err) => log::error!("Couldn't watch external plugins because {}", err),
+  })
+  .map_err(anyhow::Error::msg)?;
+  watcher
+    .watch(plugins_path_buf.as_path(), RecursiveMode::NonRecursive)
+    .map_err(anyhow::Error::msg)?;
+  EXTERNAL_PLUGINS_WATCHER.lock().unwrap().replace(watcher);
+  Ok(())
 }
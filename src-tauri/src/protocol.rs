@@ -16,6 +16,71 @@
 */
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::config;
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum CrcTagAction {
+  Keep,
+  Add,
+  Strip,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum DuplicateAction {
+  Keep,
+  Move,
+  Suffix,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DuplicateGroup {
+  pub hash: String,
+  pub items: Vec<Item>,
+  pub size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DuplicateOptions {
+  pub action: DuplicateAction,
+  pub algorithm: HashAlgorithm,
+  pub folder: String,
+  pub suffix: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum FileSystemProfile {
+  #[serde(rename = "FAT")]
+  Fat,
+  Linux,
+  #[serde(rename = "macOS")]
+  MacOs,
+  Windows,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlattenOptions {
+  pub root: String,
+  pub separator: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum HashAlgorithm {
+  Blake3,
+  Crc32,
+  Md5,
+  Sha1,
+  Sha256,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HashProgress {
+  pub completed: usize,
+  #[serde(rename = "sourcePath")]
+  pub source_path: String,
+  pub total: usize,
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Item {
@@ -25,6 +90,24 @@ pub struct Item {
   pub target_path: String,
   #[serde(rename = "type")]
   pub item_type: ItemType,
+  #[serde(default)]
+  pub metadata: BTreeMap<String, String>,
+  #[serde(default)]
+  pub companions: Vec<String>,
+}
+
+#[cfg(test)]
+impl Item {
+  /// Creates a file item whose target path is the source path.
+  pub fn new_file(path: &str) -> Self {
+    Self {
+      source_path: path.to_string(),
+      target_path: path.to_string(),
+      item_type: ItemType::File,
+      metadata: BTreeMap::new(),
+      companions: Vec::new(),
+    }
+  }
 }
 
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord, Deserialize, Serialize)]
@@ -33,3 +116,223 @@ pub enum ItemType {
   Directory,
   Unknown,
 }
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum MetadataType {
+  Audio,
+  Image,
+  Media,
+  Video,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OrganizeOptions {
+  #[serde(rename = "dateFormat")]
+  pub date_format: String,
+  pub field: String,
+  pub root: String,
+  pub rule: OrganizeRule,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum OrganizeRule {
+  Category,
+  ExifDate,
+  FirstLetter,
+  Metadata,
+  Modified,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NormalizationDuplicateGroup {
+  pub items: Vec<Item>,
+  pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PipelineStep {
+  pub options: BTreeMap<String, serde_json::Value>,
+  pub plugin: config::ConfigPlugin,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegexRenameOptions {
+  #[serde(rename = "caseInsensitive")]
+  pub case_insensitive: bool,
+  #[serde(rename = "maxReplacements")]
+  pub max_replacements: usize,
+  pub pattern: String,
+  pub replacement: String,
+  pub scope: RegexRenameScope,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegexRenamePreview {
+  pub after: String,
+  pub before: String,
+  pub matches: Vec<TextSpan>,
+  #[serde(rename = "sourcePath")]
+  pub source_path: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum RegexRenameScope {
+  Name,
+  Extension,
+  FileName,
+  FullPath,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RemoveDirectoriesResult {
+  pub errors: Vec<String>,
+  #[serde(rename = "removedDirectories")]
+  pub removed_directories: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RenameOptions {
+  #[serde(default)]
+  pub normalization: Option<UnicodeNormalization>,
+  #[serde(default)]
+  pub profile: Option<FileSystemProfile>,
+  #[serde(default, rename = "pruneDirectories")]
+  pub prune_directories: bool,
+  #[serde(default)]
+  pub roots: Vec<String>,
+  #[serde(default)]
+  pub truncate: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RenameResult {
+  pub count: usize,
+  pub error: Option<String>,
+  #[serde(rename = "removedDirectories")]
+  pub removed_directories: Vec<String>,
+  #[serde(rename = "trashedPaths")]
+  pub trashed_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RestoreResult {
+  pub error: Option<String>,
+  #[serde(rename = "restoredPaths")]
+  pub restored_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SanitizeOptions {
+  pub profile: FileSystemProfile,
+  pub replacement: String,
+  #[serde(rename = "validateOnly")]
+  pub validate_only: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SanitizeResult {
+  pub issues: Vec<String>,
+  #[serde(rename = "sourcePath")]
+  pub source_path: String,
+  #[serde(rename = "targetPath")]
+  pub target_path: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScanOptions {
+  pub depth: i32,
+  pub extensions: Vec<String>,
+  #[serde(default, rename = "groupCompanions")]
+  pub group_companions: bool,
+  #[serde(default)]
+  pub hashes: Vec<HashAlgorithm>,
+  #[serde(rename = "includeDirectory")]
+  pub include_directory: bool,
+  #[serde(default)]
+  pub metadata: Vec<MetadataType>,
+  #[serde(default)]
+  pub normalization: Option<UnicodeNormalization>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum SubtitleMatchMode {
+  Episode,
+  Order,
+  Similarity,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SubtitleMatchOptions {
+  pub language: String,
+  pub mode: SubtitleMatchMode,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TagWriteResult {
+  pub error: Option<String>,
+  #[serde(rename = "sourcePath")]
+  pub source_path: String,
+  pub tags: BTreeMap<String, String>,
+}
+
+/// A span in UTF-16 code units so that it can be used by the editor directly.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct TextSpan {
+  pub end: usize,
+  pub start: usize,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum UnflattenMode {
+  Count,
+  Prefix,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UnflattenOptions {
+  pub count: usize,
+  pub mode: UnflattenMode,
+  pub separator: String,
+  #[serde(rename = "stripPrefix")]
+  pub strip_prefix: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum UnicodeNormalization {
+  #[serde(rename = "NFC")]
+  Nfc,
+  #[serde(rename = "NFD")]
+  Nfd,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VerifyOptions {
+  pub action: CrcTagAction,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VerifyResult {
+  pub actual: Option<String>,
+  pub error: Option<String>,
+  pub expected: Option<String>,
+  #[serde(rename = "sourcePath")]
+  pub source_path: String,
+  pub status: VerifyStatus,
+  #[serde(rename = "targetPath")]
+  pub target_path: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum VerifyStatus {
+  Match,
+  Mismatch,
+  Missing,
+  Error,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WriteTagsOptions {
+  #[serde(rename = "dryRun")]
+  pub dry_run: bool,
+  pub pattern: String,
+}
@@ -0,0 +1,1087 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+
+use crate::config::{
+  ConfigPlugin, ConfigPluginOption, ConfigPluginOptionBoolean, ConfigPluginOptionEnum, ConfigPluginOptionInteger,
+  ConfigPluginOptionRegex, ConfigPluginOptionString,
+};
+use crate::media;
+use crate::protocol;
+use crate::sanitize;
+use crate::template::{Template, Value};
+use crate::unicode;
+
+pub const RULE_ADD_PREFIX_AND_SUFFIX: &str = "2b593379-f3c8-48f4-a2bf-971cc022a10a";
+pub const RULE_CHANGE_CASE: &str = "1492a3f5-7def-40d5-b7ae-1a8e33d3ae4c";
+pub const RULE_CHANGE_EXTENSION: &str = "223d8278-77fa-44bb-8b96-019f2c9fde52";
+pub const RULE_FIND_AND_REPLACE: &str = "8164db1a-c29f-49d4-95aa-525e5b4d8f23";
+pub const RULE_INSERT_TEXT: &str = "9f4b590c-5950-45ca-998f-ad8bf822d0e5";
+pub const RULE_MEDIA_LIBRARY: &str = "bc8a7b98-4a0f-49cb-8967-de5d7050a687";
+pub const RULE_REMOVE_CHARACTERS: &str = "bc4c0092-65ee-42c6-9d7c-cc9f2e74c68a";
+pub const RULE_REMOVE_PREFIX_AND_SUFFIX: &str = "dfa1e26e-c3b4-49df-9bc8-9ce0f52328df";
+pub const RULE_SANITIZE: &str = "1f1b33c3-c038-4e39-abdf-cd727a3d7daa";
+pub const RULE_TEMPLATE: &str = "d615e8a8-7bb3-479a-8436-c1f7bf70c9ea";
+pub const RULE_TRANSLITERATE: &str = "805c3510-47f3-4c05-abb0-cf82e8479108";
+pub const RULE_TRIM: &str = "0e8a12ff-e005-4770-bde6-fda292fc1067";
+
+pub static NATIVE_PLUGINS: Lazy<Vec<ConfigPlugin>> = Lazy::new(get_native_plugins);
+
+/// A target path split into the parent path with the trailing separator, the stem and the extension with the dot.
+struct Name {
+  extension: String,
+  item_type: protocol::ItemType,
+  parent: String,
+  stem: String,
+}
+
+impl Name {
+  fn new(path: &str, item_type: protocol::ItemType) -> Self {
+    let (parent, file_name) = match path.rfind(std::path::is_separator) {
+      Some(index) => (&path[..=index], &path[(index + 1)..]),
+      None => ("", path),
+    };
+    let mut name = Self {
+      extension: String::new(),
+      item_type,
+      parent: parent.to_string(),
+      stem: String::new(),
+    };
+    if item_type == protocol::ItemType::Directory {
+      name.stem = file_name.to_string();
+    } else {
+      name.set_file_name(file_name.to_string());
+    }
+    name
+  }
+
+  fn get_path(&self) -> String {
+    format!("{}{}{}", self.parent, self.stem, self.extension)
+  }
+
+  fn set_file_name(&mut self, file_name: String) {
+    // The leading dot of a hidden file is not treated as an extension.
+    match file_name.rfind('.') {
+      Some(index) if index > 0 => {
+        self.extension = file_name[index..].to_string();
+        self.stem = file_name[..index].to_string();
+      }
+      _ => {
+        self.extension = String::new();
+        self.stem = file_name;
+      }
+    }
+  }
+}
+
+enum Rule {
+  AddPrefixAndSuffix {
+    prefix: String,
+    suffix: String,
+  },
+  ChangeCase {
+    case: String,
+    include_extension: bool,
+    include_name: bool,
+  },
+  ChangeExtension {
+    extension: String,
+  },
+  FindAndReplace(RegexRename),
+  InsertText {
+    position: i64,
+    text: String,
+  },
+  MediaLibrary {
+    episode_template: Template,
+    movie_template: Template,
+  },
+  RemoveCharacters {
+    count: usize,
+    start: i64,
+  },
+  RemovePrefixAndSuffix {
+    prefix: String,
+    suffix: String,
+  },
+  Sanitize {
+    profile: protocol::FileSystemProfile,
+    replacement: String,
+  },
+  Template {
+    counter_start: i64,
+    counter_step: i64,
+    now: NaiveDateTime,
+    template: Template,
+  },
+  Transliterate {
+    include_extension: bool,
+  },
+  Trim {
+    characters: Vec<char>,
+    mode: String,
+  },
+}
+
+impl Rule {
+  fn new(id: &str, options: &BTreeMap<String, serde_json::Value>) -> Result<Self> {
+    let rule = match id {
+      RULE_ADD_PREFIX_AND_SUFFIX => Self::AddPrefixAndSuffix {
+        prefix: get_string(options, "prefix")?,
+        suffix: get_string(options, "suffix")?,
+      },
+      RULE_CHANGE_CASE => Self::ChangeCase {
+        case: get_string(options, "case")?,
+        include_extension: get_bool(options, "includeExtension")?,
+        include_name: get_bool(options, "includeName")?,
+      },
+      RULE_CHANGE_EXTENSION => {
+        let extension = get_string(options, "extension")?;
+        let extension = extension.trim_start_matches('.');
+        Self::ChangeExtension {
+          extension: if extension.is_empty() {
+            String::new()
+          } else {
+            format!(".{}", extension)
+          },
+        }
+      }
+      RULE_FIND_AND_REPLACE => Self::FindAndReplace(RegexRename::new(&protocol::RegexRenameOptions {
+        case_insensitive: get_bool(options, "caseInsensitive")?,
+        max_replacements: get_i64(options, "maxReplacements")?.max(0) as usize,
+        pattern: get_string(options, "pattern")?,
+        replacement: get_string(options, "replacement")?,
+        scope: serde_json::from_value(serde_json::Value::from(get_string(options, "scope")?))
+          .map_err(anyhow::Error::msg)?,
+      })?),
+      RULE_INSERT_TEXT => Self::InsertText {
+        position: get_i64(options, "position")?,
+        text: get_string(options, "text")?,
+      },
+      RULE_MEDIA_LIBRARY => Self::MediaLibrary {
+        episode_template: Template::parse(get_string(options, "episodeTemplate")?.as_str())?,
+        movie_template: Template::parse(get_string(options, "movieTemplate")?.as_str())?,
+      },
+      RULE_REMOVE_CHARACTERS => Self::RemoveCharacters {
+        count: get_i64(options, "count")?.max(0) as usize,
+        start: get_i64(options, "start")?,
+      },
+      RULE_REMOVE_PREFIX_AND_SUFFIX => Self::RemovePrefixAndSuffix {
+        prefix: get_string(options, "prefix")?,
+        suffix: get_string(options, "suffix")?,
+      },
+      RULE_SANITIZE => {
+        let profile = serde_json::from_value(serde_json::Value::from(get_string(options, "profile")?))
+          .map_err(anyhow::Error::msg)?;
+        let replacement = get_string(options, "replacement")?;
+        sanitize::validate_replacement(replacement.as_str(), profile)?;
+        Self::Sanitize { profile, replacement }
+      }
+      RULE_TEMPLATE => Self::Template {
+        counter_start: get_i64(options, "counterStart")?,
+        counter_step: get_i64(options, "counterStep")?,
+        now: Local::now().naive_local(),
+        template: Template::parse(get_string(options, "template")?.as_str())?,
+      },
+      RULE_TRANSLITERATE => Self::Transliterate {
+        include_extension: get_bool(options, "includeExtension")?,
+      },
+      RULE_TRIM => Self::Trim {
+        characters: get_string(options, "characters")?.chars().collect(),
+        mode: get_string(options, "mode")?,
+      },
+      _ => return Err(anyhow::anyhow!("Native plugin {} is not found.", id)),
+    };
+    Ok(rule)
+  }
+
+  /// Applies the rule to the name of the item at the given index.
+  fn apply(&self, name: &mut Name, item: &protocol::Item, index: usize) {
+    match self {
+      Self::AddPrefixAndSuffix { prefix, suffix } => {
+        name.stem = format!("{}{}{}", prefix, name.stem, suffix);
+      }
+      Self::ChangeCase {
+        case,
+        include_extension,
+        include_name,
+      } => {
+        if *include_name {
+          name.stem = change_case(name.stem.as_str(), case.as_str());
+        }
+        if *include_extension {
+          name.extension = change_case(name.extension.as_str(), case.as_str());
+        }
+      }
+      Self::ChangeExtension { extension } => {
+        name.extension = extension.clone();
+      }
+      Self::FindAndReplace(regex_rename) => {
+        let path = regex_rename.apply(name);
+        *name = Name::new(path.as_str(), name.item_type);
+      }
+      Self::InsertText { position, text } => {
+        let index = get_byte_index(name.stem.as_str(), *position);
+        name.stem.insert_str(index, text.as_str());
+      }
+      Self::MediaLibrary {
+        episode_template,
+        movie_template,
+      } => {
+        // The name is parsed on the fly if the media metadata was not read during the scan.
+        let mut metadata = item.metadata.clone();
+        if !metadata.contains_key(media::FIELD_MEDIA_TYPE) && name.item_type == protocol::ItemType::File {
+          metadata.extend(media::parse_media_name(name.stem.as_str()));
+        }
+        let template = match metadata
+          .get(media::FIELD_MEDIA_TYPE)
+          .map(|media_type| media_type.as_str())
+        {
+          Some(media::MEDIA_TYPE_EPISODE) => episode_template,
+          Some(media::MEDIA_TYPE_MOVIE) => movie_template,
+          _ => return,
+        };
+        let mut fields: BTreeMap<String, Value> = metadata
+          .into_iter()
+          .map(|(key, value)| (key, Value::Text(value)))
+          .collect();
+        let extension = name.extension.trim_start_matches('.').to_string();
+        fields.insert("ext".to_string(), Value::Text(name.extension.clone()));
+        fields.insert("extension".to_string(), Value::Text(extension));
+        fields.insert("name".to_string(), Value::Text(name.stem.clone()));
+        // A name missing any field of the template is left untouched.
+        if template.get_fields().iter().all(|field| fields.contains_key(*field)) {
+          set_template_path(name, template, &fields);
+        }
+      }
+      Self::RemoveCharacters { count, start } => {
+        let start_index = get_byte_index(name.stem.as_str(), *start);
+        let end_index = name.stem[start_index..]
+          .char_indices()
+          .nth(*count)
+          .map_or(name.stem.len(), |(index, _)| start_index + index);
+        name.stem.replace_range(start_index..end_index, "");
+      }
+      Self::RemovePrefixAndSuffix { prefix, suffix } => {
+        if !prefix.is_empty() {
+          if let Some(stem) = name.stem.strip_prefix(prefix.as_str()) {
+            name.stem = stem.to_string();
+          }
+        }
+        if !suffix.is_empty() {
+          if let Some(stem) = name.stem.strip_suffix(suffix.as_str()) {
+            name.stem = stem.to_string();
+          }
+        }
+      }
+      Self::Sanitize { profile, replacement } => {
+        let path = sanitize::sanitize_path(
+          item.source_path.as_str(),
+          name.get_path().as_str(),
+          name.item_type,
+          *profile,
+          replacement.as_str(),
+        );
+        *name = Name::new(path.as_str(), name.item_type);
+      }
+      Self::Template {
+        counter_start,
+        counter_step,
+        now,
+        template,
+      } => {
+        let mut fields: BTreeMap<String, Value> = item
+          .metadata
+          .iter()
+          .map(|(key, value)| (key.clone(), Value::Text(value.clone())))
+          .collect();
+        let extension = name.extension.trim_start_matches('.').to_string();
+        fields.insert(
+          "counter".to_string(),
+          Value::Text((counter_start + counter_step * index as i64).to_string()),
+        );
+        fields.insert("ext".to_string(), Value::Text(name.extension.clone()));
+        fields.insert("extension".to_string(), Value::Text(extension));
+        fields.insert(
+          "filename".to_string(),
+          Value::Text(format!("{}{}", name.stem, name.extension)),
+        );
+        fields.insert("name".to_string(), Value::Text(name.stem.clone()));
+        fields.insert("now".to_string(), Value::Date(*now));
+        // The parent directories are "parent", "parent2", "parent3" and so on from the nearest one.
+        for (depth, parent) in name
+          .parent
+          .split(std::path::is_separator)
+          .filter(|parent| !parent.is_empty())
+          .rev()
+          .enumerate()
+        {
+          let key = if depth == 0 {
+            "parent".to_string()
+          } else {
+            format!("parent{}", depth + 1)
+          };
+          fields.insert(key, Value::Text(parent.to_string()));
+        }
+        let template_fields = template.get_fields();
+        if template_fields.contains(&"created") || template_fields.contains(&"modified") {
+          if let Ok(metadata) = fs::metadata(item.source_path.as_str()) {
+            for (key, time) in [("created", metadata.created()), ("modified", metadata.modified())] {
+              if let Ok(time) = time {
+                fields.insert(
+                  key.to_string(),
+                  Value::Date(DateTime::<Local>::from(time).naive_local()),
+                );
+              }
+            }
+          }
+        }
+        set_template_path(name, template, &fields);
+      }
+      Self::Transliterate { include_extension } => {
+        name.stem = unicode::transliterate(name.stem.as_str());
+        if *include_extension {
+          name.extension = unicode::transliterate(name.extension.as_str());
+        }
+      }
+      Self::Trim { characters, mode } => {
+        let is_trimmed = |c: char| {
+          if characters.is_empty() {
+            c.is_whitespace()
+          } else {
+            characters.contains(&c)
+          }
+        };
+        name.stem = match mode.as_str() {
+          "Start" => name.stem.trim_start_matches(is_trimmed).to_string(),
+          "End" => name.stem.trim_end_matches(is_trimmed).to_string(),
+          _ => name.stem.trim_matches(is_trimmed).to_string(),
+        };
+      }
+    }
+  }
+}
+
+pub struct RegexRename {
+  max_replacements: usize,
+  regex: Regex,
+  replacement: String,
+  scope: protocol::RegexRenameScope,
+}
+
+impl RegexRename {
+  pub fn new(options: &protocol::RegexRenameOptions) -> Result<Self> {
+    let regex = RegexBuilder::new(options.pattern.as_str())
+      .case_insensitive(options.case_insensitive)
+      .build()
+      .map_err(anyhow::Error::msg)?;
+    Ok(Self {
+      max_replacements: options.max_replacements,
+      regex,
+      replacement: options.replacement.clone(),
+      scope: options.scope,
+    })
+  }
+
+  fn apply(&self, name: &Name) -> String {
+    let path = name.get_path();
+    match self.get_range(name) {
+      Some(range) => format!(
+        "{}{}{}",
+        &path[..range.start],
+        self
+          .regex
+          .replacen(&path[range.clone()], self.max_replacements, self.replacement.as_str()),
+        &path[range.end..]
+      ),
+      None => path,
+    }
+  }
+
+  /// Gets the byte range of the scope in the path. An extension scope without an extension is skipped.
+  fn get_range(&self, name: &Name) -> Option<Range<usize>> {
+    let stem_start = name.parent.len();
+    let extension_start = stem_start + name.stem.len();
+    let end = extension_start + name.extension.len();
+    match self.scope {
+      protocol::RegexRenameScope::Name => Some(stem_start..extension_start),
+      protocol::RegexRenameScope::Extension => {
+        if name.extension.is_empty() {
+          None
+        } else {
+          Some((extension_start + 1)..end)
+        }
+      }
+      protocol::RegexRenameScope::FileName => Some(stem_start..end),
+      protocol::RegexRenameScope::FullPath => Some(0..end),
+    }
+  }
+
+  fn preview(&self, item: &protocol::Item) -> protocol::RegexRenamePreview {
+    let name = Name::new(item.target_path.as_str(), item.item_type);
+    let before = name.get_path();
+    let mut matches: Vec<protocol::TextSpan> = Vec::new();
+    if let Some(range) = self.get_range(&name) {
+      let limit = if self.max_replacements == 0 {
+        usize::MAX
+      } else {
+        self.max_replacements
+      };
+      // The offsets are converted incrementally from bytes to UTF-16 code units.
+      let mut byte_offset = 0;
+      let mut utf16_offset = 0;
+      let mut to_utf16_offset = |byte_index: usize| {
+        utf16_offset += before[byte_offset..byte_index].encode_utf16().count();
+        byte_offset = byte_index;
+        utf16_offset
+      };
+      for m in self.regex.find_iter(&before[range.clone()]).take(limit) {
+        let start = to_utf16_offset(range.start + m.start());
+        let end = to_utf16_offset(range.start + m.end());
+        matches.push(protocol::TextSpan { end, start });
+      }
+    }
+    protocol::RegexRenamePreview {
+      after: self.apply(&name),
+      before,
+      matches,
+      source_path: item.source_path.clone(),
+    }
+  }
+}
+
+pub fn change_case(text: &str, case: &str) -> String {
+  match case {
+    "lower case" => text.to_lowercase(),
+    "UPPER CASE" => text.to_uppercase(),
+    "Sentence case" => {
+      let mut chars = text.chars();
+      match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars.as_str().to_lowercase().chars()).collect(),
+        None => String::new(),
+      }
+    }
+    _ => {
+      // Title Case capitalizes the first letter of every word.
+      let mut new_text = String::with_capacity(text.len());
+      let mut word_start = true;
+      for c in text.chars() {
+        if c.is_alphanumeric() {
+          if word_start {
+            new_text.extend(c.to_uppercase());
+          } else {
+            new_text.extend(c.to_lowercase());
+          }
+          word_start = false;
+        } else {
+          new_text.push(c);
+          word_start = true;
+        }
+      }
+      new_text
+    }
+  }
+}
+
+/// Converts a character position to a byte index. A negative position counts from the end.
+fn get_byte_index(text: &str, position: i64) -> usize {
+  let char_count = text.chars().count() as i64;
+  let position = if position < 0 {
+    (char_count + position).max(0)
+  } else {
+    position.min(char_count)
+  } as usize;
+  text.char_indices().nth(position).map_or(text.len(), |(index, _)| index)
+}
+
+fn get_bool(options: &BTreeMap<String, serde_json::Value>, name: &str) -> Result<bool> {
+  options
+    .get(name)
+    .and_then(|value| value.as_bool())
+    .ok_or_else(|| anyhow::anyhow!("Option {} must be a boolean.", name))
+}
+
+fn get_i64(options: &BTreeMap<String, serde_json::Value>, name: &str) -> Result<i64> {
+  options
+    .get(name)
+    .and_then(|value| value.as_i64())
+    .ok_or_else(|| anyhow::anyhow!("Option {} must be an integer.", name))
+}
+
+fn get_string(options: &BTreeMap<String, serde_json::Value>, name: &str) -> Result<String> {
+  options
+    .get(name)
+    .and_then(|value| value.as_str())
+    .map(|value| value.to_string())
+    .ok_or_else(|| anyhow::anyhow!("Option {} must be a string.", name))
+}
+
+fn get_native_plugins() -> Vec<ConfigPlugin> {
+  vec![
+    new_native_plugin(
+      RULE_ADD_PREFIX_AND_SUFFIX,
+      "Add Prefix and Suffix",
+      "Add a prefix and a suffix to the file name.",
+      vec![
+        new_option_string("prefix", "", "The prefix to be added."),
+        new_option_string("suffix", "", "The suffix to be added before the extension."),
+      ],
+    ),
+    new_native_plugin(
+      RULE_CHANGE_CASE,
+      "Change Case",
+      "Change the case of the file name and the extension.",
+      vec![
+        ConfigPluginOption::Enum(ConfigPluginOptionEnum {
+          default_value: "lower case".to_string(),
+          description: "The case to be applied.".to_string(),
+          name: "case".to_string(),
+          values: vec![
+            "lower case".to_string(),
+            "UPPER CASE".to_string(),
+            "Title Case".to_string(),
+            "Sentence case".to_string(),
+          ],
+        }),
+        new_option_boolean("includeName", true, "Whether the name is included."),
+        new_option_boolean("includeExtension", false, "Whether the extension is included."),
+      ],
+    ),
+    new_native_plugin(
+      RULE_CHANGE_EXTENSION,
+      "Change Extension",
+      "Replace the extension of the file name. An empty extension removes the extension.",
+      vec![new_option_string("extension", "", "The new extension with or without the dot.")],
+    ),
+    new_native_plugin(
+      RULE_FIND_AND_REPLACE,
+      "Find and Replace",
+      "Replace the matches of a regular expression in the file name. The replacement supports capture groups like $1 and ${name}.",
+      vec![
+        ConfigPluginOption::Regex(ConfigPluginOptionRegex {
+          default_value: "".to_string(),
+          description: "The regular expression to be matched.".to_string(),
+          name: "pattern".to_string(),
+        }),
+        new_option_string("replacement", "", "The replacement with optional capture groups."),
+        ConfigPluginOption::Enum(ConfigPluginOptionEnum {
+          default_value: "Name".to_string(),
+          description: "The part of the path to be matched.".to_string(),
+          name: "scope".to_string(),
+          values: vec![
+            "Name".to_string(),
+            "Extension".to_string(),
+            "FileName".to_string(),
+            "FullPath".to_string(),
+          ],
+        }),
+        new_option_boolean("caseInsensitive", false, "Whether the match is case insensitive."),
+        new_option_integer(
+          "maxReplacements",
+          0,
+          Some(0),
+          "The maximum number of replacements. 0 means no limit.",
+        ),
+      ],
+    ),
+    new_native_plugin(
+      RULE_INSERT_TEXT,
+      "Insert Text",
+      "Insert text into the file name at a given position.",
+      vec![
+        new_option_string("text", "", "The text to be inserted."),
+        new_option_integer(
+          "position",
+          0,
+          None,
+          "The character position. A negative position counts from the end.",
+        ),
+      ],
+    ),
+    new_native_plugin(
+      RULE_MEDIA_LIBRARY,
+      "Media Library",
+      "Organize TV episodes and movies parsed from release names like Show.Name.S01E02.1080p.WEB-DL.x264-GRP into a media library. The fields are mediaTitle, mediaSeason, mediaEpisode, mediaEpisodes, mediaYear, mediaResolution, mediaSource, mediaCodec and mediaGroup. A name missing any field of the template is left untouched.",
+      vec![
+        new_option_string(
+          "episodeTemplate",
+          "{mediaTitle}/Season {mediaSeason:02}/{mediaTitle} - S{mediaSeason:02}{mediaEpisodes}{ext}",
+          "The template of the TV episodes. {mediaEpisodes} is like E02 or E02-E03.",
+        ),
+        new_option_string(
+          "movieTemplate",
+          "{mediaTitle} ({mediaYear})/{mediaTitle} ({mediaYear}){ext}",
+          "The template of the movies.",
+        ),
+      ],
+    ),
+    new_native_plugin(
+      RULE_REMOVE_CHARACTERS,
+      "Remove Characters",
+      "Remove a range of characters from the file name.",
+      vec![
+        new_option_integer(
+          "start",
+          0,
+          None,
+          "The first character position. A negative position counts from the end.",
+        ),
+        new_option_integer("count", 1, Some(0), "The number of characters to be removed."),
+      ],
+    ),
+    new_native_plugin(
+      RULE_REMOVE_PREFIX_AND_SUFFIX,
+      "Remove Prefix and Suffix",
+      "Remove a prefix and a suffix from the file name if they are present.",
+      vec![
+        new_option_string("prefix", "", "The prefix to be removed."),
+        new_option_string("suffix", "", "The suffix to be removed before the extension."),
+      ],
+    ),
+    new_native_plugin(
+      RULE_SANITIZE,
+      "Sanitize",
+      "Make the file name valid on a file system by replacing the illegal characters like <>:\"/\\|?* and the control characters, renaming the reserved names like CON, NUL and COM1, trimming the trailing dots and spaces, and truncating the names over the length limits with the extension and the numbering suffix preserved.",
+      vec![
+        ConfigPluginOption::Enum(ConfigPluginOptionEnum {
+          default_value: "Windows".to_string(),
+          description: "The file system profile to be followed.".to_string(),
+          name: "profile".to_string(),
+          values: vec![
+            "Windows".to_string(),
+            "macOS".to_string(),
+            "Linux".to_string(),
+            "FAT".to_string(),
+          ],
+        }),
+        new_option_string("replacement", "_", "The replacement of the illegal characters."),
+      ],
+    ),
+    new_native_plugin(
+      RULE_TEMPLATE,
+      "Template",
+      "Build the file name from a template like {name|lower|slug}_{counter:03}. The extension is appended unless the template contains {ext}, {extension} or {filename}. The fields are name, ext, extension, filename, parent, parent2, counter, now, created, modified and the metadata like {artist}, {track:02}, {height}, {date}, {make} and {crc32}. The filters are upper, lower, title, slug, trim, replace:from:to, truncate:N and pad:N:C. A \"/\" creates a directory.",
+      vec![
+        new_option_string(
+          "template",
+          "{name}_{counter:03}",
+          "The template. {field:0N} pads the field with zeros to N characters and {date:%Y%m%d} formats a date.",
+        ),
+        new_option_integer("counterStart", 1, None, "The first value of the counter."),
+        new_option_integer("counterStep", 1, None, "The increment of the counter."),
+      ],
+    ),
+    new_native_plugin(
+      RULE_TRANSLITERATE,
+      "Transliterate",
+      "Transliterate the file name to ASCII like Ærøskøbing to Aeroskobing, 北京 to BeiJing and ひらがな to hiragana.",
+      vec![new_option_boolean(
+        "includeExtension",
+        false,
+        "Whether the extension is included.",
+      )],
+    ),
+    new_native_plugin(
+      RULE_TRIM,
+      "Trim",
+      "Trim characters from the start and the end of the file name.",
+      vec![
+        new_option_string(
+          "characters",
+          "",
+          "The characters to be trimmed. Empty means whitespace.",
+        ),
+        ConfigPluginOption::Enum(ConfigPluginOptionEnum {
+          default_value: "Both".to_string(),
+          description: "Where the characters are trimmed.".to_string(),
+          name: "mode".to_string(),
+          values: vec!["Both".to_string(), "Start".to_string(), "End".to_string()],
+        }),
+      ],
+    ),
+  ]
+}
+
+fn new_native_plugin(id: &str, name: &str, description: &str, options: Vec<ConfigPluginOption>) -> ConfigPlugin {
+  ConfigPlugin {
+    code: "".to_string(),
+    description: description.to_string(),
+    external: false,
+    id: id.to_string(),
+    name: name.to_string(),
+    native: true,
+    options,
+  }
+}
+
+fn new_option_boolean(name: &str, default_value: bool, description: &str) -> ConfigPluginOption {
+  ConfigPluginOption::Boolean(ConfigPluginOptionBoolean {
+    default_value,
+    description: description.to_string(),
+    name: name.to_string(),
+  })
+}
+
+fn new_option_integer(name: &str, default_value: i32, min: Option<i32>, description: &str) -> ConfigPluginOption {
+  ConfigPluginOption::Integer(ConfigPluginOptionInteger {
+    default_value,
+    description: description.to_string(),
+    max: None,
+    min,
+    name: name.to_string(),
+    step: None,
+  })
+}
+
+fn new_option_string(name: &str, default_value: &str, description: &str) -> ConfigPluginOption {
+  ConfigPluginOption::String(ConfigPluginOptionString {
+    default_value: default_value.to_string(),
+    description: description.to_string(),
+    name: name.to_string(),
+  })
+}
+
+/// Replaces the file name with the rendered template where "/" creates directories.
+/// Empty directories produced by missing fields are dropped, and the directories "." and ".." become "_".
+/// The extension is appended unless the template contains {ext}, {extension} or {filename}.
+fn set_template_path(name: &mut Name, template: &Template, fields: &BTreeMap<String, Value>) {
+  let file_name = template
+    .render(fields)
+    .split('/')
+    .map(|component| component.trim())
+    .filter(|component| !component.is_empty())
+    .map(sanitize::escape_dot_component)
+    .collect::<Vec<&str>>()
+    .join(std::path::MAIN_SEPARATOR_STR);
+  if !file_name.is_empty() {
+    let extension = if template
+      .get_fields()
+      .iter()
+      .any(|field| matches!(*field, "ext" | "extension" | "filename"))
+    {
+      ""
+    } else {
+      name.extension.as_str()
+    };
+    let path = format!("{}{}{}", name.parent, file_name, extension);
+    *name = Name::new(path.as_str(), name.item_type);
+  }
+}
+
+/// Applies the rule to the target paths. The items with an empty target path are sent to the trash,
+/// so they are skipped and not counted either.
+pub fn run(id: &str, options: &BTreeMap<String, serde_json::Value>, items: &mut [protocol::Item]) -> Result<()> {
+  let rule = Rule::new(id, options)?;
+  let mut items: Vec<&mut protocol::Item> = items.iter_mut().filter(|item| !item.target_path.is_empty()).collect();
+  items.par_iter_mut().enumerate().for_each(|(index, item)| {
+    let mut name = Name::new(item.target_path.as_str(), item.item_type);
+    rule.apply(&mut name, item, index);
+    item.target_path = name.get_path();
+  });
+  Ok(())
+}
+
+pub fn preview_regex_rename(
+  items: &[protocol::Item],
+  options: &protocol::RegexRenameOptions,
+) -> Result<Vec<protocol::RegexRenamePreview>> {
+  let regex_rename = RegexRename::new(options)?;
+  Ok(
+    items
+      .par_iter()
+      .filter(|item| !item.target_path.is_empty())
+      .map(|item| regex_rename.preview(item))
+      .collect(),
+  )
+}
+
+pub fn regex_rename_items(items: &mut [protocol::Item], options: &protocol::RegexRenameOptions) -> Result<()> {
+  let regex_rename = RegexRename::new(options)?;
+  items
+    .par_iter_mut()
+    .filter(|item| !item.target_path.is_empty())
+    .for_each(|item| {
+      let name = Name::new(item.target_path.as_str(), item.item_type);
+      item.target_path = regex_rename.apply(&name);
+    });
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_options(options: &[(&str, serde_json::Value)]) -> BTreeMap<String, serde_json::Value> {
+    options
+      .iter()
+      .map(|(name, value)| (name.to_string(), value.clone()))
+      .collect()
+  }
+
+  fn get_target_paths(items: &[protocol::Item]) -> Vec<&str> {
+    items.iter().map(|item| item.target_path.as_str()).collect()
+  }
+
+  /// Runs the native plugin with the default options overridden by the given ones.
+  fn run_rule(id: &str, options: &[(&str, serde_json::Value)], paths: &[&str]) -> Vec<String> {
+    let plugin = NATIVE_PLUGINS.iter().find(|plugin| plugin.id == id).unwrap();
+    let mut merged_options: BTreeMap<String, serde_json::Value> = plugin
+      .options
+      .iter()
+      .map(|option| (option.get_name().to_string(), option.get_default_value()))
+      .collect();
+    merged_options.extend(new_options(options));
+    let mut items: Vec<protocol::Item> = paths.iter().map(|path| protocol::Item::new_file(path)).collect();
+    run(id, &merged_options, &mut items).unwrap();
+    items.into_iter().map(|item| item.target_path).collect()
+  }
+
+  #[test]
+  fn test_run_skips_the_items_sent_to_the_trash() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/a.txt"),
+      protocol::Item::new_file("/x/b.txt"),
+      protocol::Item::new_file("/x/c.txt"),
+    ];
+    items[1].target_path = String::new();
+    let options = new_options(&[
+      ("template", serde_json::Value::from("{name}_{counter:03}")),
+      ("counterStart", serde_json::Value::from(1)),
+      ("counterStep", serde_json::Value::from(1)),
+    ]);
+    run(RULE_TEMPLATE, &options, &mut items).unwrap();
+    assert_eq!(vec!["/x/a_001.txt", "", "/x/c_002.txt"], get_target_paths(&items));
+    let options = new_options(&[
+      ("prefix", serde_json::Value::from("p_")),
+      ("suffix", serde_json::Value::from("")),
+    ]);
+    run(RULE_ADD_PREFIX_AND_SUFFIX, &options, &mut items).unwrap();
+    assert_eq!(vec!["/x/p_a_001.txt", "", "/x/p_c_002.txt"], get_target_paths(&items));
+  }
+
+  #[test]
+  fn test_regex_rename_items_skips_the_items_sent_to_the_trash() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/a.txt"),
+      protocol::Item::new_file("/x/b.txt"),
+    ];
+    items[1].target_path = String::new();
+    let options = protocol::RegexRenameOptions {
+      case_insensitive: false,
+      max_replacements: 0,
+      pattern: "^".to_string(),
+      replacement: "z".to_string(),
+      scope: protocol::RegexRenameScope::Name,
+    };
+    regex_rename_items(&mut items, &options).unwrap();
+    assert_eq!(vec!["/x/za.txt", ""], get_target_paths(&items));
+    assert_eq!(1, preview_regex_rename(&items, &options).unwrap().len());
+  }
+
+  #[test]
+  fn test_change_case() {
+    assert_eq!("hello world-foo", change_case("hello WORLD-foo", "lower case"));
+    assert_eq!("HELLO WORLD-FOO", change_case("hello WORLD-foo", "UPPER CASE"));
+    assert_eq!("Hello world-foo", change_case("hello WORLD-foo", "Sentence case"));
+    assert_eq!("Hello World-Foo", change_case("hello WORLD-foo", "Title Case"));
+    assert_eq!(
+      vec!["/x/my file.txt"],
+      run_rule(
+        RULE_CHANGE_CASE,
+        &[("includeExtension", serde_json::Value::from(true))],
+        &["/x/My File.TXT"],
+      )
+    );
+    assert_eq!(
+      vec!["/x/My File.txt"],
+      run_rule(
+        RULE_CHANGE_CASE,
+        &[
+          ("includeName", serde_json::Value::from(false)),
+          ("includeExtension", serde_json::Value::from(true)),
+        ],
+        &["/x/My File.TXT"],
+      )
+    );
+  }
+
+  #[test]
+  fn test_insert_text_counts_negative_positions_from_the_end() {
+    let paths = ["/x/abcd.txt", "/x/äöü.txt", "/x/.hidden"];
+    let run_insert_text = |position: i64| {
+      run_rule(
+        RULE_INSERT_TEXT,
+        &[
+          ("text", serde_json::Value::from("_")),
+          ("position", serde_json::Value::from(position)),
+        ],
+        &paths,
+      )
+    };
+    assert_eq!(vec!["/x/_abcd.txt", "/x/_äöü.txt", "/x/_.hidden"], run_insert_text(0));
+    assert_eq!(vec!["/x/a_bcd.txt", "/x/ä_öü.txt", "/x/._hidden"], run_insert_text(1));
+    assert_eq!(vec!["/x/abc_d.txt", "/x/äö_ü.txt", "/x/.hidde_n"], run_insert_text(-1));
+    assert_eq!(vec!["/x/abcd_.txt", "/x/äöü_.txt", "/x/.hidden_"], run_insert_text(100));
+    assert_eq!(
+      vec!["/x/_abcd.txt", "/x/_äöü.txt", "/x/_.hidden"],
+      run_insert_text(-100)
+    );
+  }
+
+  #[test]
+  fn test_remove_characters() {
+    let run_remove_characters = |start: i64, count: i64| {
+      run_rule(
+        RULE_REMOVE_CHARACTERS,
+        &[
+          ("start", serde_json::Value::from(start)),
+          ("count", serde_json::Value::from(count)),
+        ],
+        &["/x/abcde.txt", "/x/äöüß.txt"],
+      )
+    };
+    assert_eq!(vec!["/x/ade.txt", "/x/äß.txt"], run_remove_characters(1, 2));
+    assert_eq!(vec!["/x/abc.txt", "/x/äö.txt"], run_remove_characters(-2, 5));
+    assert_eq!(vec!["/x/abcde.txt", "/x/äöüß.txt"], run_remove_characters(1, 0));
+  }
+
+  #[test]
+  fn test_trim() {
+    let run_trim = |characters: &str, mode: &str| {
+      run_rule(
+        RULE_TRIM,
+        &[
+          ("characters", serde_json::Value::from(characters)),
+          ("mode", serde_json::Value::from(mode)),
+        ],
+        &["/x/ -ab- .txt"],
+      )
+      .remove(0)
+    };
+    assert_eq!("/x/-ab-.txt", run_trim("", "Both"));
+    assert_eq!("/x/ab.txt", run_trim(" -", "Both"));
+    assert_eq!("/x/ab- .txt", run_trim(" -", "Start"));
+    assert_eq!("/x/ -ab.txt", run_trim(" -", "End"));
+  }
+
+  #[test]
+  fn test_find_and_replace_scopes() {
+    let run_find_and_replace = |pattern: &str, scope: &str| {
+      run_rule(
+        RULE_FIND_AND_REPLACE,
+        &[
+          ("pattern", serde_json::Value::from(pattern)),
+          ("replacement", serde_json::Value::from("b")),
+          ("scope", serde_json::Value::from(scope)),
+        ],
+        &["/a/a.a", "/a/a"],
+      )
+    };
+    assert_eq!(vec!["/a/b.a", "/a/b"], run_find_and_replace("a", "Name"));
+    assert_eq!(vec!["/a/a.b", "/a/a"], run_find_and_replace("a", "Extension"));
+    assert_eq!(vec!["/a/b.b", "/a/b"], run_find_and_replace("a", "FileName"));
+    assert_eq!(vec!["/b/b.b", "/b/b"], run_find_and_replace("a", "FullPath"));
+    assert_eq!(vec!["/a/aba", "/a/a"], run_find_and_replace(r"\.", "FileName"));
+  }
+
+  #[test]
+  fn test_media_library_organizes_episodes_and_movies() {
+    let target_paths = run_rule(
+      RULE_MEDIA_LIBRARY,
+      &[],
+      &[
+        "/x/Show.Name.S01E02.1080p.WEB-DL.x264-GRP.mkv",
+        "/x/The.Movie.2020.1080p.BluRay.x264.mkv",
+        "/x/notes.txt",
+      ],
+    );
+    assert_eq!(
+      vec![
+        "/x/Show Name/Season 01/Show Name - S01E02.mkv",
+        "/x/The Movie (2020)/The Movie (2020).mkv",
+        "/x/notes.txt",
+      ],
+      target_paths
+    );
+  }
+
+  #[test]
+  fn test_template_appends_the_extension() {
+    let run_template = |template: &str| {
+      run_rule(
+        RULE_TEMPLATE,
+        &[("template", serde_json::Value::from(template))],
+        &["/x/y/a.txt", "/x/y/.hidden"],
+      )
+    };
+    assert_eq!(vec!["/x/y/a-1.txt", "/x/y/.hidden-2"], run_template("{name}-{counter}"));
+    assert_eq!(
+      vec!["/x/y/a.txt.bak", "/x/y/.hidden.bak"],
+      run_template("{filename}.bak")
+    );
+    assert_eq!(vec!["/x/y/a.bak.txt", "/x/y/.hidden.bak"], run_template("{name}.bak"));
+    assert_eq!(vec!["/x/y/y/a.txt", "/x/y/y/.hidden"], run_template("{parent}/{name}"));
+    assert_eq!(vec!["/x/y/a.txt", "/x/y/.hidden"], run_template("{missing}/{name}"));
+  }
+
+  #[test]
+  fn test_template_keeps_the_dot_directories_inside_the_parent() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/y/a.mp3"),
+      protocol::Item::new_file("/x/y/b.mp3"),
+      protocol::Item::new_file("/x/y/c.mp3"),
+    ];
+    for (item, artist) in items.iter_mut().zip(["..", ".", "a/../.."]) {
+      item.metadata.insert("artist".to_string(), artist.to_string());
+      item.metadata.insert("title".to_string(), "..".to_string());
+    }
+    let options = new_options(&[
+      ("template", serde_json::Value::from("{artist}/{title}")),
+      ("counterStart", serde_json::Value::from(1)),
+      ("counterStep", serde_json::Value::from(1)),
+    ]);
+    run(RULE_TEMPLATE, &options, &mut items).unwrap();
+    assert_eq!(
+      vec!["/x/y/_/_.mp3", "/x/y/_/_.mp3", "/x/y/a_.._../_.mp3"],
+      get_target_paths(&items)
+    );
+  }
+
+  #[test]
+  fn test_sanitize() {
+    assert_eq!(vec!["/x/a_b_.txt"], run_rule(RULE_SANITIZE, &[], &["/x/a:b?.txt"]));
+    let options = new_options(&[
+      ("profile", serde_json::Value::from("Windows")),
+      ("replacement", serde_json::Value::from("?")),
+    ]);
+    assert!(run(RULE_SANITIZE, &options, &mut [protocol::Item::new_file("/x/a.txt")]).is_err());
+  }
+
+  #[test]
+  fn test_run_rejects_unknown_plugins() {
+    let error = run("unknown", &BTreeMap::new(), &mut [protocol::Item::new_file("/x/a.txt")]).unwrap_err();
+    assert_eq!("Native plugin unknown is not found.", error.to_string());
+  }
+}
@@ -0,0 +1,468 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+
+use crate::protocol;
+
+/// The maximum length of a name is 255 on all the supported file systems.
+const MAX_NAME_LENGTH: usize = 255;
+const RESERVED_NAMES: [&str; 22] = [
+  "AUX", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "CON", "LPT1", "LPT2", "LPT3", "LPT4",
+  "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "NUL", "PRN",
+];
+const WINDOWS_ILLEGAL_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+static NUMBERING_SUFFIX_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\s*\(\d+\)|[ ._-]\d+)$").unwrap());
+
+/// Replaces the component "." or ".." with "_" so that a name built from the metadata never leaves its directory.
+pub fn escape_dot_component(component: &str) -> &str {
+  if component == "." || component == ".." {
+    "_"
+  } else {
+    component
+  }
+}
+
+/// Gets the profile of the file system of the current platform.
+pub fn get_current_profile() -> protocol::FileSystemProfile {
+  if cfg!(target_os = "windows") {
+    protocol::FileSystemProfile::Windows
+  } else if cfg!(target_os = "macos") {
+    protocol::FileSystemProfile::MacOs
+  } else {
+    protocol::FileSystemProfile::Linux
+  }
+}
+
+/// Gets the issues of the name on the file system.
+pub fn get_issues(name: &str, profile: protocol::FileSystemProfile) -> Vec<String> {
+  let mut issues: Vec<String> = Vec::new();
+  if name.is_empty() {
+    issues.push("Name is empty.".to_string());
+    return issues;
+  }
+  if name == "." || name == ".." {
+    issues.push(format!("Name {} is reserved.", name));
+    return issues;
+  }
+  for c in name.chars() {
+    if c.is_control() {
+      issues.push(format!(
+        "Name {:?} contains control character U+{:04X}.",
+        name, c as u32
+      ));
+    } else if is_illegal_character(c, profile) {
+      issues.push(format!("Name {} contains character {} which is not allowed.", name, c));
+    }
+  }
+  if is_reserved_name(name, profile) {
+    issues.push(format!("Name {} is reserved.", name));
+  }
+  if is_windows_like(profile) && name.ends_with(['.', ' ']) {
+    issues.push(format!("Name {} ends with a dot or a space.", name));
+  }
+  issues.dedup();
+  issues
+}
+
+/// Gets the length of the text in bytes of UTF-8 on Linux and macOS, or in UTF-16 code units on Windows and FAT.
+fn get_length(text: &str, profile: protocol::FileSystemProfile) -> usize {
+  if is_windows_like(profile) {
+    text.encode_utf16().count()
+  } else {
+    text.len()
+  }
+}
+
+/// Gets the issues of the lengths of the names, and the full path if required, on the file system.
+pub fn get_length_issues(path: &str, profile: protocol::FileSystemProfile, include_path: bool) -> Vec<String> {
+  let unit = get_length_unit(profile);
+  let mut issues: Vec<String> = Path::new(path)
+    .components()
+    .filter_map(|component| match component {
+      Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+      _ => None,
+    })
+    .filter(|name| get_length(name.as_str(), profile) > MAX_NAME_LENGTH)
+    .map(|name| format!("Name {} exceeds the limit of {} {}.", name, MAX_NAME_LENGTH, unit))
+    .collect();
+  let max_path_length = get_max_path_length(profile);
+  if include_path && get_length(path, profile) > max_path_length {
+    issues.push(format!(
+      "Path {} exceeds the limit of {} {}.",
+      path, max_path_length, unit
+    ));
+  }
+  issues
+}
+
+fn get_length_unit(profile: protocol::FileSystemProfile) -> &'static str {
+  if is_windows_like(profile) {
+    "characters"
+  } else {
+    "bytes"
+  }
+}
+
+/// Gets the maximum length of a full path without the terminating null.
+fn get_max_path_length(profile: protocol::FileSystemProfile) -> usize {
+  match profile {
+    protocol::FileSystemProfile::Fat | protocol::FileSystemProfile::Windows => 259,
+    protocol::FileSystemProfile::Linux => 4095,
+    protocol::FileSystemProfile::MacOs => 1023,
+  }
+}
+
+/// Gets the index of the first component of the target path which is not in the parent directory of the source path.
+pub fn get_new_component_index(source_path: &Path, target_path: &Path) -> usize {
+  let source_parent_path = source_path.parent().unwrap_or(Path::new(""));
+  source_parent_path
+    .components()
+    .zip(target_path.components())
+    .take_while(|(a, b)| a == b)
+    .count()
+}
+
+fn is_illegal_character(c: char, profile: protocol::FileSystemProfile) -> bool {
+  c.is_control()
+    || match profile {
+      protocol::FileSystemProfile::Fat | protocol::FileSystemProfile::Windows => {
+        WINDOWS_ILLEGAL_CHARACTERS.contains(&c)
+      }
+      protocol::FileSystemProfile::Linux => c == '/',
+      protocol::FileSystemProfile::MacOs => c == '/' || c == ':',
+    }
+}
+
+/// Checks the reserved device names like "CON" and "com1.txt" which are reserved with any extension.
+fn is_reserved_name(name: &str, profile: protocol::FileSystemProfile) -> bool {
+  match profile {
+    protocol::FileSystemProfile::Fat | protocol::FileSystemProfile::Windows => {
+      let stem = name.split('.').next().unwrap_or_default().trim_end();
+      RESERVED_NAMES
+        .iter()
+        .any(|reserved_name| reserved_name.eq_ignore_ascii_case(stem))
+    }
+    _ => false,
+  }
+}
+
+fn is_windows_like(profile: protocol::FileSystemProfile) -> bool {
+  matches!(
+    profile,
+    protocol::FileSystemProfile::Fat | protocol::FileSystemProfile::Windows
+  )
+}
+
+/// Validates the target paths of the items on the file system, and sanitizes them unless it is validate only.
+pub fn sanitize_items(
+  items: &[protocol::Item],
+  options: &protocol::SanitizeOptions,
+) -> Result<Vec<protocol::SanitizeResult>> {
+  if !options.validate_only {
+    validate_replacement(options.replacement.as_str(), options.profile)?;
+  }
+  Ok(
+    items
+      .par_iter()
+      .map(|item| {
+        let target_path = Path::new(item.target_path.as_str());
+        let index = get_new_component_index(Path::new(item.source_path.as_str()), target_path);
+        let issues: Vec<String> = target_path
+          .components()
+          .skip(index)
+          .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+          })
+          .flat_map(|name| get_issues(name.as_str(), options.profile))
+          .chain(get_length_issues(item.target_path.as_str(), options.profile, true))
+          .collect();
+        let target_path = if options.validate_only || issues.is_empty() {
+          item.target_path.clone()
+        } else {
+          sanitize_path(
+            item.source_path.as_str(),
+            item.target_path.as_str(),
+            item.item_type,
+            options.profile,
+            options.replacement.as_str(),
+          )
+        };
+        protocol::SanitizeResult {
+          issues,
+          source_path: item.source_path.clone(),
+          target_path,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Replaces the illegal characters, renames the reserved names and trims the trailing dots and spaces.
+pub fn sanitize_name(name: &str, profile: protocol::FileSystemProfile, replacement: &str) -> String {
+  let mut name: String = name
+    .chars()
+    .map(|c| {
+      if is_illegal_character(c, profile) {
+        replacement.to_string()
+      } else {
+        c.to_string()
+      }
+    })
+    .collect();
+  if is_windows_like(profile) {
+    name = name.trim_end_matches(['.', ' ']).to_string();
+  }
+  if is_reserved_name(name.as_str(), profile) {
+    name = match name.find('.') {
+      Some(index) => format!("{}{}{}", &name[..index], replacement, &name[index..]),
+      None => format!("{}{}", name, replacement),
+    };
+  }
+  if name.is_empty() || name == "." || name == ".." {
+    name = replacement.to_string();
+  }
+  name
+}
+
+/// Sanitizes the components of the target path beyond the parent directory of the source path
+/// so that the directories created by the rename are sanitized as well, and truncates the name if it is too long.
+pub fn sanitize_path(
+  source_path: &str,
+  target_path: &str,
+  item_type: protocol::ItemType,
+  profile: protocol::FileSystemProfile,
+  replacement: &str,
+) -> String {
+  let target_path = Path::new(target_path);
+  let index = get_new_component_index(Path::new(source_path), target_path);
+  let path: PathBuf = target_path
+    .components()
+    .enumerate()
+    .map(|(component_index, component)| match component {
+      Component::Normal(name) if component_index >= index => {
+        PathBuf::from(sanitize_name(name.to_string_lossy().as_ref(), profile, replacement))
+      }
+      component => PathBuf::from(component.as_os_str()),
+    })
+    .collect();
+  truncate_path(path.to_string_lossy().as_ref(), item_type, profile, true)
+}
+
+/// Truncates the name of the path to fit the length limits of the file system with the full path if required.
+/// The extension and the numbering suffix like " (2)" or "_001" are preserved.
+/// The path is returned as is if it cannot be fixed by truncating the name.
+pub fn truncate_path(
+  path: &str,
+  item_type: protocol::ItemType,
+  profile: protocol::FileSystemProfile,
+  include_path: bool,
+) -> String {
+  let (parent, file_name) = match path.rfind(std::path::is_separator) {
+    Some(index) => (&path[..=index], &path[(index + 1)..]),
+    None => ("", path),
+  };
+  let max_length = if include_path {
+    get_max_path_length(profile)
+      .saturating_sub(get_length(parent, profile))
+      .min(MAX_NAME_LENGTH)
+  } else {
+    MAX_NAME_LENGTH
+  };
+  if get_length(file_name, profile) <= max_length {
+    return path.to_string();
+  }
+  let (stem, extension) = match file_name.rfind('.') {
+    Some(index) if index > 0 && item_type != protocol::ItemType::Directory => file_name.split_at(index),
+    _ => (file_name, ""),
+  };
+  let (body, suffix) = stem.split_at(NUMBERING_SUFFIX_REGEX.find(stem).map_or(stem.len(), |m| m.start()));
+  let max_body_length = match max_length.checked_sub(get_length(suffix, profile) + get_length(extension, profile)) {
+    Some(max_body_length) => max_body_length,
+    None => return path.to_string(),
+  };
+  let mut body_length = 0;
+  let body_end = body
+    .char_indices()
+    .take_while(|(_, c)| {
+      body_length += get_length(c.encode_utf8(&mut [0; 4]), profile);
+      body_length <= max_body_length
+    })
+    .last()
+    .map_or(0, |(index, c)| index + c.len_utf8());
+  let body = body[..body_end].trim_end_matches([' ', '.']);
+  if body.is_empty() {
+    return path.to_string();
+  }
+  format!("{}{}{}{}", parent, body, suffix, extension)
+}
+
+/// Validates the replacement which must be a legal name on the file system.
+pub fn validate_replacement(replacement: &str, profile: protocol::FileSystemProfile) -> Result<()> {
+  if replacement.is_empty() {
+    return Err(anyhow::anyhow!("Replacement is not specified."));
+  }
+  if !get_issues(replacement, profile).is_empty() {
+    return Err(anyhow::anyhow!(
+      "Replacement {} is not allowed on {:?}.",
+      replacement,
+      profile
+    ));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_truncate_path_keeps_the_utf8_boundaries() {
+    let profile = protocol::FileSystemProfile::Linux;
+    let path = format!("/x/{}.txt", "é".repeat(200));
+    assert_eq!(
+      format!("/x/{}.txt", "é".repeat(125)),
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+    let path = format!("/x/{}.txt", "😀".repeat(70));
+    assert_eq!(
+      format!("/x/{}.txt", "😀".repeat(62)),
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+    // A name of exactly 255 bytes is kept.
+    let path = format!("/x/{}a.txt", "é".repeat(125));
+    assert_eq!(
+      path,
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+  }
+
+  #[test]
+  fn test_truncate_path_keeps_the_extension_and_the_numbering_suffix() {
+    let profile = protocol::FileSystemProfile::Linux;
+    for suffix in [" (2)", "_001", "-12", ".3"] {
+      let path = format!("/x/{}{}.txt", "é".repeat(200), suffix);
+      let max_body_length = (MAX_NAME_LENGTH - suffix.len() - 4) / 2;
+      assert_eq!(
+        format!("/x/{}{}.txt", "é".repeat(max_body_length), suffix),
+        truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+      );
+    }
+    // The name of a directory doesn't have an extension.
+    let path = format!("/x/{}.d", "a".repeat(300));
+    assert_eq!(
+      format!("/x/{}", "a".repeat(255)),
+      truncate_path(path.as_str(), protocol::ItemType::Directory, profile, false)
+    );
+    // The trailing spaces and dots of the truncated body are trimmed.
+    let path = format!("/x/{} .{}.txt", "a".repeat(249), "b".repeat(10));
+    assert_eq!(
+      format!("/x/{}.txt", "a".repeat(249)),
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+    // The path is kept if the extension alone exceeds the limit.
+    let path = format!("/x/a.{}", "b".repeat(300));
+    assert_eq!(
+      path,
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+  }
+
+  #[test]
+  fn test_truncate_path_counts_utf16_on_windows() {
+    let profile = protocol::FileSystemProfile::Windows;
+    let path = format!("/x/{}.txt", "é".repeat(300));
+    assert_eq!(
+      format!("/x/{}.txt", "é".repeat(251)),
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+    let path = format!("/x/{}.txt", "😀".repeat(130));
+    assert_eq!(
+      format!("/x/{}.txt", "😀".repeat(125)),
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+    // The full path is limited to 259 characters.
+    let parent = format!("/{}/", "p".repeat(200));
+    let path = format!("{}{}.txt", parent, "é".repeat(100));
+    assert_eq!(
+      format!("{}{}.txt", parent, "é".repeat(53)),
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, true)
+    );
+    assert_eq!(
+      path,
+      truncate_path(path.as_str(), protocol::ItemType::File, profile, false)
+    );
+  }
+
+  #[test]
+  fn test_sanitize_name_renames_the_reserved_names() {
+    for profile in [protocol::FileSystemProfile::Fat, protocol::FileSystemProfile::Windows] {
+      assert_eq!("CON_", sanitize_name("CON", profile, "_"));
+      assert_eq!("con_.txt", sanitize_name("con.txt", profile, "_"));
+      assert_eq!("COM1_.tar.gz", sanitize_name("COM1.tar.gz", profile, "_"));
+      assert_eq!("Lpt9_", sanitize_name("Lpt9", profile, "_"));
+      assert_eq!("CONSOLE.txt", sanitize_name("CONSOLE.txt", profile, "_"));
+      assert_eq!("COM10", sanitize_name("COM10", profile, "_"));
+      assert_eq!(vec!["Name nul.txt is reserved."], get_issues("nul.txt", profile));
+    }
+    assert_eq!("CON", sanitize_name("CON", protocol::FileSystemProfile::Linux, "_"));
+    assert!(get_issues("con.txt", protocol::FileSystemProfile::MacOs).is_empty());
+  }
+
+  #[test]
+  fn test_sanitize_name_trims_the_trailing_dots_and_spaces() {
+    for profile in [protocol::FileSystemProfile::Fat, protocol::FileSystemProfile::Windows] {
+      assert_eq!("a", sanitize_name("a. .", profile, "_"));
+      assert_eq!("a.txt", sanitize_name("a.txt ", profile, "_"));
+      assert_eq!("_", sanitize_name("...", profile, "_"));
+      assert_eq!("aux_", sanitize_name("aux.", profile, "_"));
+      assert_eq!(vec!["Name a. ends with a dot or a space."], get_issues("a.", profile));
+    }
+    assert_eq!("a.", sanitize_name("a.", protocol::FileSystemProfile::Linux, "_"));
+    assert!(get_issues("a ", protocol::FileSystemProfile::Linux).is_empty());
+  }
+
+  #[test]
+  fn test_sanitize_name_replaces_the_illegal_characters_by_profile() {
+    let name = "a<b>c:d\"e|f?g*h\u{1}";
+    assert_eq!(
+      "a_b_c_d_e_f_g_h_",
+      sanitize_name(name, protocol::FileSystemProfile::Fat, "_")
+    );
+    assert_eq!(
+      "a_b_c_d_e_f_g_h_",
+      sanitize_name(name, protocol::FileSystemProfile::Windows, "_")
+    );
+    assert_eq!(
+      "a<b>c-d\"e|f?g*h-",
+      sanitize_name(name, protocol::FileSystemProfile::MacOs, "-")
+    );
+    assert_eq!(
+      "a<b>c:d\"e|f?g*h-",
+      sanitize_name(name, protocol::FileSystemProfile::Linux, "-")
+    );
+    assert_eq!("_", sanitize_name("..", protocol::FileSystemProfile::Linux, "_"));
+    assert!(validate_replacement("?", protocol::FileSystemProfile::Windows).is_err());
+    assert!(validate_replacement("?", protocol::FileSystemProfile::Linux).is_ok());
+  }
+}
@@ -0,0 +1,321 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::media;
+use crate::protocol;
+use crate::video;
+
+pub const SUBTITLE_EXTENSIONS: [&str; 4] = ["ass", "srt", "sub", "vtt"];
+
+const ISO_639_1_CODES: [&str; 184] = [
+  "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bh", "bi", "bm", "bn",
+  "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de", "dv", "dz", "ee", "el", "en",
+  "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy", "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he",
+  "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia", "id", "ie", "ig", "ii", "ik", "io", "is", "it", "iu", "ja", "jv",
+  "ka", "kg", "ki", "kj", "kk", "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li",
+  "ln", "lo", "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb", "nd", "ne",
+  "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi", "pl", "ps", "pt", "qu", "rm",
+  "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk", "sl", "sm", "sn", "so", "sq", "sr", "ss", "st",
+  "su", "sv", "sw", "ta", "te", "tg", "th", "ti", "tk", "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk",
+  "ur", "uz", "ve", "vi", "vo", "wa", "wo", "xh", "yi", "yo", "za", "zh", "zu",
+];
+const LANGUAGE_CODES: [&str; 30] = [
+  "ara", "chi", "chs", "cht", "cze", "dan", "dut", "eng", "fin", "fre", "ger", "gre", "heb", "hun", "ind", "ita",
+  "jpn", "kor", "nor", "pol", "por", "rum", "rus", "spa", "swe", "tha", "tur", "ukr", "vie", "zho",
+];
+const MIN_SIMILARITY: f64 = 0.3;
+
+/// Gets the set of the character bigrams of the normalized name.
+fn get_bigrams(name: &str) -> HashSet<(char, char)> {
+  let chars: Vec<char> = name
+    .to_lowercase()
+    .chars()
+    .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+    .collect::<String>()
+    .split_whitespace()
+    .collect::<Vec<&str>>()
+    .join(" ")
+    .chars()
+    .collect();
+  chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Gets the season and the episode parsed from the name.
+fn get_episode(name: &str) -> Option<(String, String)> {
+  let fields = media::parse_media_name(name);
+  match (fields.get(media::FIELD_SEASON), fields.get(media::FIELD_EPISODE)) {
+    (Some(season), Some(episode)) => Some((season.clone(), episode.clone())),
+    _ => None,
+  }
+}
+
+/// Gets the language tag at the end of the stem like "en", "pt-BR" or "eng".
+fn get_language(stem: &str) -> Option<String> {
+  let (_, language) = stem.rsplit_once('.')?;
+  let (code, region) = match language.split_once('-') {
+    Some((code, region)) => (code, Some(region)),
+    None => (language, None),
+  };
+  let code = code.to_lowercase();
+  let is_code = ISO_639_1_CODES.contains(&code.as_str()) || LANGUAGE_CODES.contains(&code.as_str());
+  let is_region =
+    region.is_none_or(|region| (2..=4).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric()));
+  if is_code && is_region {
+    Some(language.to_string())
+  } else {
+    None
+  }
+}
+
+fn get_stem(path: &str) -> String {
+  Path::new(path)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+/// Gets the Sørensen-Dice coefficient of the character bigrams.
+fn get_similarity(a: &HashSet<(char, char)>, b: &HashSet<(char, char)>) -> f64 {
+  if a.is_empty() && b.is_empty() {
+    return 0.0;
+  }
+  2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+pub fn is_subtitle_file(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| SUBTITLE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Proposes the target paths of the subtitles so that each subtitle takes the base name of its video
+/// plus a language code, next to the target of the video.
+/// The language tag already in the subtitle name wins over the language in the options.
+/// Every video is matched once per language, and the subtitles that are not matched keep their target paths.
+pub fn match_subtitles(items: &mut [protocol::Item], options: &protocol::SubtitleMatchOptions) -> Result<()> {
+  // The items with an empty target path are sent to the trash and never matched.
+  let is_file = |item: &protocol::Item| item.item_type == protocol::ItemType::File && !item.target_path.is_empty();
+  let mut video_indexes: Vec<usize> = (0..items.len())
+    .filter(|index| is_file(&items[*index]) && video::is_video_file(Path::new(items[*index].source_path.as_str())))
+    .collect();
+  let mut subtitle_indexes: Vec<usize> = (0..items.len())
+    .filter(|index| is_file(&items[*index]) && is_subtitle_file(Path::new(items[*index].source_path.as_str())))
+    .collect();
+  if video_indexes.is_empty() || subtitle_indexes.is_empty() {
+    return Err(anyhow::anyhow!("Both videos and subtitles are required."));
+  }
+  video_indexes.sort_by(|a, b| items[*a].source_path.cmp(&items[*b].source_path));
+  subtitle_indexes.sort_by(|a, b| items[*a].source_path.cmp(&items[*b].source_path));
+  let language_map: BTreeMap<usize, String> = subtitle_indexes
+    .iter()
+    .filter_map(|subtitle_index| {
+      get_language(get_stem(items[*subtitle_index].source_path.as_str()).as_str())
+        .map(|language| (*subtitle_index, language))
+    })
+    .collect();
+  // The subtitle index is mapped to the video index.
+  let mut match_map: BTreeMap<usize, usize> = BTreeMap::new();
+  // The video index is paired with the language so that the target paths never collide.
+  let mut matched_video_set: HashSet<(usize, Option<String>)> = HashSet::new();
+  match options.mode {
+    protocol::SubtitleMatchMode::Episode => {
+      let mut episode_map: BTreeMap<(String, String), usize> = BTreeMap::new();
+      for video_index in video_indexes.iter() {
+        if let Some(episode) = get_episode(get_stem(items[*video_index].source_path.as_str()).as_str()) {
+          episode_map.entry(episode).or_insert(*video_index);
+        }
+      }
+      // Multiple subtitles of different languages may match the same video.
+      for subtitle_index in subtitle_indexes.iter() {
+        if let Some(video_index) = get_episode(get_stem(items[*subtitle_index].source_path.as_str()).as_str())
+          .and_then(|episode| episode_map.get(&episode))
+        {
+          let key = (
+            *video_index,
+            language_map.get(subtitle_index).map(|language| language.to_lowercase()),
+          );
+          if matched_video_set.insert(key) {
+            match_map.insert(*subtitle_index, *video_index);
+          }
+        }
+      }
+    }
+    protocol::SubtitleMatchMode::Order => {
+      for (subtitle_index, video_index) in subtitle_indexes.iter().zip(video_indexes.iter()) {
+        match_map.insert(*subtitle_index, *video_index);
+      }
+    }
+    protocol::SubtitleMatchMode::Similarity => {
+      // The pairs are assigned greedily from the most similar one and every video is matched once per language.
+      let mut pairs: Vec<(f64, usize, usize)> = Vec::new();
+      for subtitle_index in subtitle_indexes.iter() {
+        let subtitle_stem = get_stem(items[*subtitle_index].source_path.as_str());
+        let subtitle_stem = match language_map.get(subtitle_index) {
+          Some(language) => subtitle_stem[..(subtitle_stem.len() - language.len() - 1)].to_string(),
+          None => subtitle_stem,
+        };
+        let subtitle_bigrams = get_bigrams(subtitle_stem.as_str());
+        for video_index in video_indexes.iter() {
+          let video_bigrams = get_bigrams(get_stem(items[*video_index].source_path.as_str()).as_str());
+          let similarity = get_similarity(&subtitle_bigrams, &video_bigrams);
+          if similarity >= MIN_SIMILARITY {
+            pairs.push((similarity, *subtitle_index, *video_index));
+          }
+        }
+      }
+      pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+      for (_, subtitle_index, video_index) in pairs {
+        let key = (
+          video_index,
+          language_map
+            .get(&subtitle_index)
+            .map(|language| language.to_lowercase()),
+        );
+        if !match_map.contains_key(&subtitle_index) && !matched_video_set.contains(&key) {
+          match_map.insert(subtitle_index, video_index);
+          matched_video_set.insert(key);
+        }
+      }
+    }
+  }
+  let language = options.language.trim().trim_matches('.').to_string();
+  for (subtitle_index, video_index) in match_map {
+    let subtitle_path = Path::new(items[subtitle_index].source_path.as_str());
+    let extension = subtitle_path
+      .extension()
+      .map(|extension| extension.to_string_lossy().to_string())
+      .unwrap_or_default();
+    let language = language_map
+      .get(&subtitle_index)
+      .cloned()
+      .unwrap_or_else(|| language.clone());
+    let video_path = Path::new(items[video_index].target_path.as_str());
+    let file_name = if language.is_empty() {
+      format!("{}.{}", get_stem(items[video_index].target_path.as_str()), extension)
+    } else {
+      format!(
+        "{}.{}.{}",
+        get_stem(items[video_index].target_path.as_str()),
+        language,
+        extension
+      )
+    };
+    items[subtitle_index].target_path = video_path
+      .parent()
+      .unwrap_or(Path::new(""))
+      .join(file_name)
+      .to_string_lossy()
+      .to_string();
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn match_paths(items: &mut [protocol::Item], mode: protocol::SubtitleMatchMode, language: &str) -> Vec<String> {
+    let options = protocol::SubtitleMatchOptions {
+      language: language.to_string(),
+      mode,
+    };
+    match_subtitles(items, &options).unwrap();
+    items.iter().map(|item| item.target_path.clone()).collect()
+  }
+
+  #[test]
+  fn test_get_language() {
+    assert_eq!(Some("en".to_string()), get_language("Show.en"));
+    assert_eq!(Some("pt-BR".to_string()), get_language("Show.pt-BR"));
+    assert_eq!(Some("eng".to_string()), get_language("Show.eng"));
+    assert_eq!(None, get_language("Show.S01E02"));
+    assert_eq!(None, get_language("Show"));
+  }
+
+  #[test]
+  fn test_match_subtitles_by_episode_once_per_language() {
+    // The subtitles are matched in the order of the source paths.
+    let mut items = vec![
+      protocol::Item::new_file("/x/Show.S01E02.mkv"),
+      protocol::Item::new_file("/x/subs/show 1x02.en.srt"),
+      protocol::Item::new_file("/x/subs/Show.S01E02.HI.en.srt"),
+      protocol::Item::new_file("/x/subs/Show.S01E02.fr.srt"),
+      protocol::Item::new_file("/x/subs/Show.S01E03.srt"),
+    ];
+    items[0].target_path = "/y/Show - 1x02.mkv".to_string();
+    assert_eq!(
+      vec![
+        "/y/Show - 1x02.mkv",
+        "/x/subs/show 1x02.en.srt",
+        "/y/Show - 1x02.en.srt",
+        "/y/Show - 1x02.fr.srt",
+        "/x/subs/Show.S01E03.srt",
+      ],
+      match_paths(&mut items, protocol::SubtitleMatchMode::Episode, "")
+    );
+  }
+
+  #[test]
+  fn test_match_subtitles_by_order_and_similarity() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/b.mkv"),
+      protocol::Item::new_file("/x/a.mkv"),
+      protocol::Item::new_file("/x/2.srt"),
+      protocol::Item::new_file("/x/1.srt"),
+    ];
+    assert_eq!(
+      vec!["/x/b.mkv", "/x/a.mkv", "/x/b.en.srt", "/x/a.en.srt"],
+      match_paths(&mut items, protocol::SubtitleMatchMode::Order, ".en.")
+    );
+    let mut items = vec![
+      protocol::Item::new_file("/x/The Great Movie (2019).mkv"),
+      protocol::Item::new_file("/x/Another Film.mkv"),
+      protocol::Item::new_file("/x/another_film.ger.srt"),
+      protocol::Item::new_file("/x/great.movie.srt"),
+    ];
+    assert_eq!(
+      vec![
+        "/x/The Great Movie (2019).mkv",
+        "/x/Another Film.mkv",
+        "/x/Another Film.ger.srt",
+        "/x/The Great Movie (2019).srt",
+      ],
+      match_paths(&mut items, protocol::SubtitleMatchMode::Similarity, "")
+    );
+  }
+
+  #[test]
+  fn test_match_subtitles_skips_the_items_sent_to_the_trash() {
+    let mut items = vec![
+      protocol::Item::new_file("/x/Show.S01E01.mkv"),
+      protocol::Item::new_file("/x/Show.S01E01.en.srt"),
+      protocol::Item::new_file("/x/Show.S01E02.mkv"),
+      protocol::Item::new_file("/x/Show.S01E02.en.srt"),
+    ];
+    items[0].target_path = String::new();
+    items[3].target_path = String::new();
+    assert_eq!(
+      vec!["", "/x/Show.S01E01.en.srt", "/x/Show.S01E02.mkv", ""],
+      match_paths(&mut items, protocol::SubtitleMatchMode::Episode, "")
+    );
+  }
+}
@@ -0,0 +1,1452 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const AUDIO_EXTENSIONS: [&str; 11] = [
+  "aac", "ape", "flac", "m4a", "m4b", "mp2", "mp3", "oga", "ogg", "opus", "wv",
+];
+
+pub const TAG_ALBUM: &str = "album";
+pub const TAG_ARTIST: &str = "artist";
+pub const TAG_DISC: &str = "disc";
+pub const TAG_GENRE: &str = "genre";
+pub const TAG_TITLE: &str = "title";
+pub const TAG_TRACK: &str = "track";
+pub const TAG_YEAR: &str = "year";
+
+pub const TAGS: [&str; 7] = [
+  TAG_ALBUM, TAG_ARTIST, TAG_DISC, TAG_GENRE, TAG_TITLE, TAG_TRACK, TAG_YEAR,
+];
+
+const ID3V1_SIZE: i64 = 128;
+const APE_FOOTER_SIZE: i64 = 32;
+const MAX_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+const ID3V2_PADDING_SIZE: usize = 1024;
+const VORBIS_VENDOR: &str = "BatchFileRename";
+
+#[derive(Clone)]
+pub struct Mp4Atom {
+  pub atom_type: [u8; 4],
+  pub body_start: usize,
+  pub end: usize,
+  pub start: usize,
+}
+
+#[derive(Clone)]
+struct OggPage {
+  body: Vec<u8>,
+  header: [u8; 27],
+  lacing_values: Vec<u8>,
+}
+
+impl OggPage {
+  fn get_serial(&self) -> u32 {
+    u32::from_le_bytes([self.header[14], self.header[15], self.header[16], self.header[17]])
+  }
+
+  /// Writes the page with the new sequence number and the recalculated checksum.
+  fn write(&mut self, sequence: u32, data: &mut Vec<u8>) {
+    self.header[18..22].copy_from_slice(&sequence.to_le_bytes());
+    self.header[22..26].copy_from_slice(&[0, 0, 0, 0]);
+    self.header[26] = self.lacing_values.len() as u8;
+    let start = data.len();
+    data.extend_from_slice(&self.header);
+    data.extend_from_slice(&self.lacing_values);
+    data.extend_from_slice(&self.body);
+    let crc = get_ogg_crc(&data[start..]);
+    data[(start + 22)..(start + 26)].copy_from_slice(&crc.to_le_bytes());
+  }
+}
+
+fn decode_latin1(bytes: &[u8]) -> String {
+  bytes.iter().map(|&b| b as char).collect()
+}
+
+fn decode_utf16(bytes: &[u8], big_endian: bool) -> String {
+  let units: Vec<u16> = bytes
+    .chunks_exact(2)
+    .map(|chunk| {
+      if big_endian {
+        u16::from_be_bytes([chunk[0], chunk[1]])
+      } else {
+        u16::from_le_bytes([chunk[0], chunk[1]])
+      }
+    })
+    .collect();
+  String::from_utf16_lossy(&units)
+}
+
+/// Decodes an ID3v2 text frame and returns the first value.
+fn decode_id3v2_text(frame: &[u8]) -> String {
+  if frame.is_empty() {
+    return String::new();
+  }
+  let text = &frame[1..];
+  let value = match frame[0] {
+    0 => decode_latin1(text),
+    1 => {
+      if text.starts_with(&[0xFE, 0xFF]) {
+        decode_utf16(&text[2..], true)
+      } else if text.starts_with(&[0xFF, 0xFE]) {
+        decode_utf16(&text[2..], false)
+      } else {
+        decode_utf16(text, false)
+      }
+    }
+    2 => decode_utf16(text, true),
+    _ => String::from_utf8_lossy(text).to_string(),
+  };
+  value.split('\0').next().unwrap_or_default().to_string()
+}
+
+fn get_id3v2_tag_name(id: &[u8]) -> Option<&'static str> {
+  match id {
+    b"TALB" | b"TAL" => Some(TAG_ALBUM),
+    b"TPE1" | b"TP1" => Some(TAG_ARTIST),
+    b"TPOS" | b"TPA" => Some(TAG_DISC),
+    b"TCON" | b"TCO" => Some(TAG_GENRE),
+    b"TIT2" | b"TT2" => Some(TAG_TITLE),
+    b"TRCK" | b"TRK" => Some(TAG_TRACK),
+    b"TDRC" | b"TYER" | b"TYE" => Some(TAG_YEAR),
+    _ => None,
+  }
+}
+
+fn get_vorbis_tag_name(key: &str) -> Option<&'static str> {
+  match key.to_uppercase().as_str() {
+    "ALBUM" => Some(TAG_ALBUM),
+    "ARTIST" => Some(TAG_ARTIST),
+    "DISC" | "DISCNUMBER" => Some(TAG_DISC),
+    "GENRE" => Some(TAG_GENRE),
+    "TITLE" => Some(TAG_TITLE),
+    "TRACK" | "TRACKNUMBER" => Some(TAG_TRACK),
+    "DATE" | "YEAR" => Some(TAG_YEAR),
+    _ => None,
+  }
+}
+
+fn get_id3v2_frame_id(name: &str, major_version: u8) -> &'static [u8; 4] {
+  match name {
+    TAG_ALBUM => b"TALB",
+    TAG_ARTIST => b"TPE1",
+    TAG_DISC => b"TPOS",
+    TAG_GENRE => b"TCON",
+    TAG_TITLE => b"TIT2",
+    TAG_TRACK => b"TRCK",
+    _ => {
+      if major_version == 4 {
+        b"TDRC"
+      } else {
+        b"TYER"
+      }
+    }
+  }
+}
+
+/// Gets the size of the header written for the body which is 16 with a 64-bit size or 8 otherwise.
+fn get_mp4_atom_header_size(body_size: usize) -> usize {
+  if body_size + 8 > u32::MAX as usize {
+    16
+  } else {
+    8
+  }
+}
+
+fn get_mp4_atom_type(name: &str) -> &'static [u8; 4] {
+  match name {
+    TAG_ALBUM => b"\xA9alb",
+    TAG_ARTIST => b"\xA9ART",
+    TAG_DISC => b"disk",
+    TAG_GENRE => b"\xA9gen",
+    TAG_TITLE => b"\xA9nam",
+    TAG_TRACK => b"trkn",
+    _ => b"\xA9day",
+  }
+}
+
+fn get_mp4_tag_name(atom_type: &[u8]) -> Option<&'static str> {
+  match atom_type {
+    b"\xA9alb" => Some(TAG_ALBUM),
+    b"\xA9ART" => Some(TAG_ARTIST),
+    b"disk" => Some(TAG_DISC),
+    b"\xA9gen" | b"gnre" => Some(TAG_GENRE),
+    b"\xA9nam" => Some(TAG_TITLE),
+    b"trkn" => Some(TAG_TRACK),
+    b"\xA9day" => Some(TAG_YEAR),
+    _ => None,
+  }
+}
+
+fn get_ogg_crc(data: &[u8]) -> u32 {
+  data.iter().fold(0u32, |crc, &b| {
+    let mut crc = crc ^ ((b as u32) << 24);
+    for _ in 0..8 {
+      crc = if crc & 0x8000_0000 != 0 {
+        (crc << 1) ^ 0x04C1_1DB7
+      } else {
+        crc << 1
+      };
+    }
+    crc
+  })
+}
+
+fn get_vorbis_key(name: &str) -> &'static str {
+  match name {
+    TAG_ALBUM => "ALBUM",
+    TAG_ARTIST => "ARTIST",
+    TAG_DISC => "DISCNUMBER",
+    TAG_GENRE => "GENRE",
+    TAG_TITLE => "TITLE",
+    TAG_TRACK => "TRACKNUMBER",
+    _ => "DATE",
+  }
+}
+
+/// Inserts the normalized value unless the tag has been read from a preferred source.
+fn insert_tag(tags: &mut BTreeMap<String, String>, name: &str, value: &str) {
+  if !tags.contains_key(name) {
+    if let Some(value) = normalize_tag(name, value) {
+      tags.insert(name.to_string(), value);
+    }
+  }
+}
+
+pub fn is_audio_file(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Normalizes the value of a tag. It returns None if the value is empty or invalid.
+pub fn normalize_tag(name: &str, value: &str) -> Option<String> {
+  let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+  if value.is_empty() {
+    return None;
+  }
+  match name {
+    TAG_DISC | TAG_TRACK => {
+      // "3/12" is normalized to "3".
+      let number = value.split('/').next().unwrap_or_default().trim();
+      number.parse::<u32>().ok().map(|number| number.to_string())
+    }
+    TAG_YEAR => {
+      // "2004-05-01" is normalized to "2004".
+      let year: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+      if year.len() == 4 {
+        Some(year)
+      } else {
+        None
+      }
+    }
+    _ => Some(value.to_string()),
+  }
+}
+
+pub fn parse_mp4_atoms(data: &[u8]) -> Result<Vec<Mp4Atom>> {
+  let mut atoms: Vec<Mp4Atom> = Vec::new();
+  let mut position = 0;
+  while position + 8 <= data.len() {
+    let size = u32::from_be_bytes(data[position..(position + 4)].try_into()?) as usize;
+    let atom_type: [u8; 4] = data[(position + 4)..(position + 8)].try_into()?;
+    let get_error = || anyhow::anyhow!("Atom {} is invalid.", String::from_utf8_lossy(&atom_type));
+    let (body_start, end) = match size {
+      0 => (position + 8, data.len()),
+      1 => {
+        let large_size = data
+          .get((position + 8)..(position + 16))
+          .ok_or_else(|| anyhow::anyhow!("Atom {} is truncated.", String::from_utf8_lossy(&atom_type)))?;
+        let end = usize::try_from(u64::from_be_bytes(large_size.try_into()?))
+          .ok()
+          .and_then(|large_size| position.checked_add(large_size))
+          .ok_or_else(get_error)?;
+        (position + 16, end)
+      }
+      _ => (position + 8, position.checked_add(size).ok_or_else(get_error)?),
+    };
+    if end < body_start || end > data.len() {
+      return Err(get_error());
+    }
+    atoms.push(Mp4Atom {
+      atom_type,
+      body_start,
+      end,
+      start: position,
+    });
+    position = end;
+  }
+  Ok(atoms)
+}
+
+fn parse_ogg_pages(data: &[u8]) -> Result<Vec<OggPage>> {
+  let mut pages: Vec<OggPage> = Vec::new();
+  let mut position = 0;
+  while position < data.len() {
+    let header = data
+      .get(position..(position + 27))
+      .filter(|header| header.starts_with(b"OggS"))
+      .ok_or_else(|| anyhow::anyhow!("Ogg page at {} is invalid.", position))?;
+    let segment_count = header[26] as usize;
+    let lacing_values = data
+      .get((position + 27)..(position + 27 + segment_count))
+      .ok_or_else(|| anyhow::anyhow!("Ogg page at {} is truncated.", position))?;
+    let body_start = position + 27 + segment_count;
+    let body_end = body_start + lacing_values.iter().map(|&value| value as usize).sum::<usize>();
+    let body = data
+      .get(body_start..body_end)
+      .ok_or_else(|| anyhow::anyhow!("Ogg page at {} is truncated.", position))?;
+    pages.push(OggPage {
+      body: body.to_vec(),
+      header: header.try_into()?,
+      lacing_values: lacing_values.to_vec(),
+    });
+    position = body_end;
+  }
+  Ok(pages)
+}
+
+/// Parses a vorbis comment into the vendor and the comments.
+fn parse_vorbis_comment(data: &[u8]) -> Option<(Vec<u8>, Vec<String>)> {
+  let read_u32 = |position: usize| -> Option<usize> {
+    data
+      .get(position..(position + 4))
+      .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+  };
+  let vendor_size = read_u32(0)?;
+  let vendor = data.get(4..(4 + vendor_size))?.to_vec();
+  let mut position = 4 + vendor_size;
+  let count = read_u32(position)?;
+  position += 4;
+  let mut comments: Vec<String> = Vec::new();
+  for _ in 0..count {
+    let size = read_u32(position)?;
+    position += 4;
+    comments.push(String::from_utf8_lossy(data.get(position..(position + size))?).to_string());
+    position += size;
+  }
+  Some((vendor, comments))
+}
+
+pub fn read_audio_tags(path: &Path) -> Result<BTreeMap<String, String>> {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension.to_lowercase())
+    .unwrap_or_default();
+  let mut reader = BufReader::new(File::open(path).map_err(anyhow::Error::msg)?);
+  let mut tags: BTreeMap<String, String> = BTreeMap::new();
+  match extension.as_str() {
+    "flac" => {
+      let offset = read_id3v2(&mut reader, &mut tags)?;
+      read_flac(&mut reader, offset, &mut tags)?;
+    }
+    "m4a" | "m4b" => read_mp4(&mut reader, &mut tags)?,
+    "oga" | "ogg" | "opus" => read_ogg(&mut reader, &mut tags)?,
+    _ => {
+      read_id3v2(&mut reader, &mut tags)?;
+      read_ape(&mut reader, &mut tags)?;
+      read_id3v1(&mut reader, &mut tags)?;
+    }
+  }
+  Ok(tags)
+}
+
+fn read_ape<R: Read + Seek>(reader: &mut R, tags: &mut BTreeMap<String, String>) -> Result<()> {
+  let file_size = reader.seek(SeekFrom::End(0))? as i64;
+  // The APE tag is either at the end of the file or right before the ID3v1 tag.
+  for footer_offset in [file_size - APE_FOOTER_SIZE, file_size - ID3V1_SIZE - APE_FOOTER_SIZE] {
+    if footer_offset < 0 {
+      continue;
+    }
+    reader.seek(SeekFrom::Start(footer_offset as u64))?;
+    let mut footer = [0u8; APE_FOOTER_SIZE as usize];
+    reader.read_exact(&mut footer)?;
+    if &footer[0..8] != b"APETAGEX" {
+      continue;
+    }
+    let size = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as i64;
+    let item_count = u32::from_le_bytes([footer[16], footer[17], footer[18], footer[19]]);
+    let items_offset = footer_offset + APE_FOOTER_SIZE - size;
+    if size < APE_FOOTER_SIZE || items_offset < 0 || size as u64 > MAX_BLOCK_SIZE {
+      return Ok(());
+    }
+    reader.seek(SeekFrom::Start(items_offset as u64))?;
+    let mut data = vec![0u8; (size - APE_FOOTER_SIZE) as usize];
+    reader.read_exact(&mut data)?;
+    let mut position = 0;
+    for _ in 0..item_count {
+      if position + 8 > data.len() {
+        break;
+      }
+      let value_size = u32::from_le_bytes(data[position..(position + 4)].try_into()?) as usize;
+      let flags = u32::from_le_bytes(data[(position + 4)..(position + 8)].try_into()?);
+      position += 8;
+      let key_end = match data[position..].iter().position(|&b| b == 0) {
+        Some(index) => position + index,
+        None => break,
+      };
+      let key = String::from_utf8_lossy(&data[position..key_end]).to_string();
+      position = key_end + 1;
+      if position + value_size > data.len() {
+        break;
+      }
+      // Only the text items are read.
+      if (flags >> 1) & 0x03 == 0 {
+        if let Some(name) = get_vorbis_tag_name(key.as_str()) {
+          let value = String::from_utf8_lossy(&data[position..(position + value_size)]).to_string();
+          insert_tag(tags, name, value.as_str());
+        }
+      }
+      position += value_size;
+    }
+    return Ok(());
+  }
+  Ok(())
+}
+
+fn read_flac<R: Read + Seek>(reader: &mut R, offset: u64, tags: &mut BTreeMap<String, String>) -> Result<()> {
+  reader.seek(SeekFrom::Start(offset))?;
+  let mut signature = [0u8; 4];
+  if reader.read_exact(&mut signature).is_err() || &signature != b"fLaC" {
+    return Ok(());
+  }
+  loop {
+    let mut header = [0u8; 4];
+    if reader.read_exact(&mut header).is_err() {
+      break;
+    }
+    let is_last = header[0] & 0x80 != 0;
+    let block_type = header[0] & 0x7F;
+    let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as u64;
+    if block_type == 4 {
+      let mut data = vec![0u8; size as usize];
+      reader.read_exact(&mut data)?;
+      read_vorbis_comment(&data, tags);
+    } else {
+      reader.seek(SeekFrom::Current(size as i64))?;
+    }
+    if is_last {
+      break;
+    }
+  }
+  Ok(())
+}
+
+fn read_id3v1<R: Read + Seek>(reader: &mut R, tags: &mut BTreeMap<String, String>) -> Result<()> {
+  let file_size = reader.seek(SeekFrom::End(0))? as i64;
+  if file_size < ID3V1_SIZE {
+    return Ok(());
+  }
+  reader.seek(SeekFrom::End(-ID3V1_SIZE))?;
+  let mut data = [0u8; ID3V1_SIZE as usize];
+  reader.read_exact(&mut data)?;
+  if &data[0..3] != b"TAG" {
+    return Ok(());
+  }
+  insert_tag(tags, TAG_TITLE, decode_latin1(&data[3..33]).as_str());
+  insert_tag(tags, TAG_ARTIST, decode_latin1(&data[33..63]).as_str());
+  insert_tag(tags, TAG_ALBUM, decode_latin1(&data[63..93]).as_str());
+  insert_tag(tags, TAG_YEAR, decode_latin1(&data[93..97]).as_str());
+  // ID3v1.1 stores the track in the last byte of the comment.
+  if data[125] == 0 && data[126] != 0 {
+    insert_tag(tags, TAG_TRACK, data[126].to_string().as_str());
+  }
+  Ok(())
+}
+
+/// Reads the ID3v2 tag at the beginning of the file and returns the size of the tag.
+fn read_id3v2<R: Read + Seek>(reader: &mut R, tags: &mut BTreeMap<String, String>) -> Result<u64> {
+  reader.seek(SeekFrom::Start(0))?;
+  let mut header = [0u8; 10];
+  if reader.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" {
+    return Ok(0);
+  }
+  let major_version = header[3];
+  let flags = header[5];
+  let size = read_syncsafe(&header[6..10]) as usize;
+  let mut data = vec![0u8; size];
+  reader.read_exact(&mut data)?;
+  if flags & 0x80 != 0 && major_version < 4 {
+    data = remove_unsynchronization(&data);
+  }
+  let mut position = 0;
+  if flags & 0x40 != 0 && data.len() >= 4 {
+    position = match major_version {
+      3 => 4 + u32::from_be_bytes(data[0..4].try_into()?) as usize,
+      4 => read_syncsafe(&data[0..4]) as usize,
+      _ => 0,
+    };
+  }
+  let (id_size, header_size) = if major_version == 2 { (3, 6) } else { (4, 10) };
+  while position + header_size <= data.len() {
+    let id = &data[position..(position + id_size)];
+    if id[0] == 0 {
+      break;
+    }
+    let frame_size = match major_version {
+      2 => u32::from_be_bytes([0, data[position + 3], data[position + 4], data[position + 5]]) as usize,
+      3 => u32::from_be_bytes(data[(position + 4)..(position + 8)].try_into()?) as usize,
+      _ => read_syncsafe(&data[(position + 4)..(position + 8)]) as usize,
+    };
+    let format_flags = if major_version == 2 { 0 } else { data[position + 9] };
+    let frame_start = position + header_size;
+    let frame_end = frame_start + frame_size;
+    if frame_end > data.len() {
+      break;
+    }
+    position = frame_end;
+    if let Some(name) = get_id3v2_tag_name(id) {
+      let mut frame = data[frame_start..frame_end].to_vec();
+      if major_version == 4 {
+        // Compressed or encrypted frames are not supported.
+        if format_flags & 0x0C != 0 {
+          continue;
+        }
+        // Some writers set the tag level unsynchronisation without the frame level flags.
+        if format_flags & 0x02 != 0 || flags & 0x80 != 0 {
+          frame = remove_unsynchronization(&frame);
+        }
+        if format_flags & 0x01 != 0 && frame.len() >= 4 {
+          frame.drain(0..4);
+        }
+      } else if major_version == 3 && format_flags & 0xC0 != 0 {
+        continue;
+      }
+      insert_tag(tags, name, decode_id3v2_text(&frame).as_str());
+    }
+  }
+  Ok(10 + size as u64)
+}
+
+fn read_mp4<R: Read + Seek>(reader: &mut R, tags: &mut BTreeMap<String, String>) -> Result<()> {
+  let file_size = reader.seek(SeekFrom::End(0))?;
+  let path: [&[u8; 4]; 4] = [b"moov", b"udta", b"meta", b"ilst"];
+  let mut range = (0, file_size);
+  for name in path {
+    range = match read_mp4_atom(reader, range, name)? {
+      Some((start, end)) => {
+        // The meta atom is a full atom with 4 bytes of version and flags.
+        if name == b"meta" {
+          (start + 4, end)
+        } else {
+          (start, end)
+        }
+      }
+      None => return Ok(()),
+    };
+  }
+  let (mut position, end) = range;
+  while position + 8 <= end {
+    reader.seek(SeekFrom::Start(position))?;
+    let (atom_type, body_start, atom_end) = read_mp4_atom_header(reader, position, end)?;
+    position = atom_end;
+    let name = match &atom_type {
+      b"\xA9alb" => TAG_ALBUM,
+      b"\xA9ART" => TAG_ARTIST,
+      b"disk" => TAG_DISC,
+      b"\xA9gen" => TAG_GENRE,
+      b"\xA9nam" => TAG_TITLE,
+      b"trkn" => TAG_TRACK,
+      b"\xA9day" => TAG_YEAR,
+      _ => continue,
+    };
+    if let Some((data_start, data_end)) = read_mp4_atom(reader, (body_start, atom_end), b"data")? {
+      // The data atom starts with 4 bytes of type and 4 bytes of locale.
+      if data_end < data_start + 8 || data_end - data_start > MAX_BLOCK_SIZE {
+        continue;
+      }
+      reader.seek(SeekFrom::Start(data_start + 8))?;
+      let mut value = vec![0u8; (data_end - data_start - 8) as usize];
+      reader.read_exact(&mut value)?;
+      if name == TAG_DISC || name == TAG_TRACK {
+        if value.len() >= 4 {
+          let number = u16::from_be_bytes([value[2], value[3]]);
+          insert_tag(tags, name, number.to_string().as_str());
+        }
+      } else {
+        insert_tag(tags, name, String::from_utf8_lossy(&value).as_ref());
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Finds the first child atom with the given name in the range and returns the range of its body.
+pub fn read_mp4_atom<R: Read + Seek>(reader: &mut R, range: (u64, u64), name: &[u8; 4]) -> Result<Option<(u64, u64)>> {
+  let (mut position, end) = range;
+  while position + 8 <= end {
+    reader.seek(SeekFrom::Start(position))?;
+    let (atom_type, body_start, atom_end) = read_mp4_atom_header(reader, position, end)?;
+    if &atom_type == name {
+      return Ok(Some((body_start, atom_end)));
+    }
+    position = atom_end;
+  }
+  Ok(None)
+}
+
+fn read_mp4_atom_header<R: Read + Seek>(reader: &mut R, position: u64, end: u64) -> Result<([u8; 4], u64, u64)> {
+  let mut header = [0u8; 8];
+  reader.read_exact(&mut header)?;
+  let mut atom_type = [0u8; 4];
+  atom_type.copy_from_slice(&header[4..8]);
+  let size = u32::from_be_bytes(header[0..4].try_into()?) as u64;
+  let get_error = || anyhow::anyhow!("Atom {} is invalid.", String::from_utf8_lossy(&atom_type));
+  let (body_start, atom_end) = match size {
+    0 => (position + 8, end),
+    1 => {
+      let mut large_size = [0u8; 8];
+      reader.read_exact(&mut large_size)?;
+      let atom_end = position
+        .checked_add(u64::from_be_bytes(large_size))
+        .ok_or_else(get_error)?;
+      (position + 16, atom_end)
+    }
+    _ => (position + 8, position + size),
+  };
+  if atom_end < body_start || atom_end > end {
+    return Err(get_error());
+  }
+  Ok((atom_type, body_start, atom_end))
+}
+
+fn read_ogg<R: Read + Seek>(reader: &mut R, tags: &mut BTreeMap<String, String>) -> Result<()> {
+  reader.seek(SeekFrom::Start(0))?;
+  // The comment header is the second packet of the first logical stream.
+  let mut packets: Vec<Vec<u8>> = Vec::new();
+  let mut packet: Vec<u8> = Vec::new();
+  let mut serial: Option<u32> = None;
+  while packets.len() < 2 {
+    let mut header = [0u8; 27];
+    if reader.read_exact(&mut header).is_err() || &header[0..4] != b"OggS" {
+      break;
+    }
+    let page_serial = u32::from_le_bytes(header[14..18].try_into()?);
+    let mut lacing_values = vec![0u8; header[26] as usize];
+    reader.read_exact(&mut lacing_values)?;
+    let page_size: u64 = lacing_values.iter().map(|&value| value as u64).sum();
+    if *serial.get_or_insert(page_serial) != page_serial {
+      reader.seek(SeekFrom::Current(page_size as i64))?;
+      continue;
+    }
+    for lacing_value in lacing_values {
+      let position = packet.len();
+      packet.resize(position + lacing_value as usize, 0);
+      reader.read_exact(&mut packet[position..])?;
+      if lacing_value < 255 {
+        packets.push(std::mem::take(&mut packet));
+      }
+    }
+    if packet.len() as u64 > MAX_BLOCK_SIZE {
+      break;
+    }
+  }
+  if let Some(packet) = packets.get(1) {
+    if packet.starts_with(b"\x03vorbis") {
+      read_vorbis_comment(&packet[7..], tags);
+    } else if packet.starts_with(b"OpusTags") {
+      read_vorbis_comment(&packet[8..], tags);
+    }
+  }
+  Ok(())
+}
+
+fn read_syncsafe(bytes: &[u8]) -> u32 {
+  bytes.iter().fold(0, |value, &b| (value << 7) | (b & 0x7F) as u32)
+}
+
+fn read_vorbis_comment(data: &[u8], tags: &mut BTreeMap<String, String>) {
+  if let Some((_, comments)) = parse_vorbis_comment(data) {
+    for comment in comments {
+      if let Some((key, value)) = comment.split_once('=') {
+        if let Some(name) = get_vorbis_tag_name(key) {
+          insert_tag(tags, name, value);
+        }
+      }
+    }
+  }
+}
+
+fn remove_unsynchronization(data: &[u8]) -> Vec<u8> {
+  let mut new_data = Vec::with_capacity(data.len());
+  let mut previous = 0u8;
+  for &b in data {
+    if !(previous == 0xFF && b == 0) {
+      new_data.push(b);
+    }
+    previous = b;
+  }
+  new_data
+}
+
+/// Writes the tags into the audio file. The other tags in the file are preserved.
+/// The file is written to a temporary file first and then replaces the original file.
+pub fn write_audio_tags(path: &Path, tags: &BTreeMap<String, String>) -> Result<()> {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension.to_lowercase())
+    .unwrap_or_default();
+  let data = fs::read(path).map_err(anyhow::Error::msg)?;
+  let new_data = match extension.as_str() {
+    "flac" => write_flac(&data, tags)?,
+    "m4a" | "m4b" => write_mp4(&data, tags)?,
+    "mp3" => write_id3v2(&data, tags)?,
+    "oga" | "ogg" | "opus" => write_ogg(&data, tags)?,
+    _ => return Err(anyhow::anyhow!("Writing tags to {} files is not supported.", extension)),
+  };
+  let temp_path = path.with_file_name(format!(".{}.tmp", uuid::Uuid::new_v4()));
+  fs::write(&temp_path, new_data).map_err(anyhow::Error::msg)?;
+  let result = fs::metadata(path)
+    .and_then(|metadata| fs::set_permissions(&temp_path, metadata.permissions()))
+    .and_then(|_| fs::rename(&temp_path, path));
+  if let Err(err) = result {
+    let _ = fs::remove_file(&temp_path);
+    return Err(anyhow::Error::msg(err));
+  }
+  Ok(())
+}
+
+fn write_flac(data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+  // The ID3v2 tag in front of some FLAC files is kept as is.
+  let offset = if data.starts_with(b"ID3") && data.len() >= 10 {
+    10 + read_syncsafe(&data[6..10]) as usize
+  } else {
+    0
+  };
+  if data.get(offset..(offset + 4)) != Some(b"fLaC") {
+    return Err(anyhow::anyhow!("FLAC signature is not found."));
+  }
+  let mut blocks: Vec<(u8, &[u8])> = Vec::new();
+  let mut position = offset + 4;
+  loop {
+    let header = data
+      .get(position..(position + 4))
+      .ok_or_else(|| anyhow::anyhow!("FLAC metadata block at {} is truncated.", position))?;
+    let is_last = header[0] & 0x80 != 0;
+    let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+    let body = data
+      .get((position + 4)..(position + 4 + size))
+      .ok_or_else(|| anyhow::anyhow!("FLAC metadata block at {} is truncated.", position))?;
+    blocks.push((header[0] & 0x7F, body));
+    position += 4 + size;
+    if is_last {
+      break;
+    }
+  }
+  let existing_comment = blocks
+    .iter()
+    .find(|(block_type, _)| *block_type == 4)
+    .map(|(_, body)| *body);
+  let comment = write_vorbis_comment(existing_comment, tags);
+  if comment.len() >= 1 << 24 {
+    return Err(anyhow::anyhow!("FLAC vorbis comment is too large."));
+  }
+  let mut new_blocks: Vec<(u8, &[u8])> = blocks.into_iter().filter(|(block_type, _)| *block_type != 4).collect();
+  // The vorbis comment is placed right after the stream info.
+  new_blocks.insert(1.min(new_blocks.len()), (4, comment.as_slice()));
+  let mut new_data = Vec::with_capacity(data.len() + comment.len());
+  new_data.extend_from_slice(&data[..(offset + 4)]);
+  let block_count = new_blocks.len();
+  for (index, (block_type, body)) in new_blocks.into_iter().enumerate() {
+    let is_last = if index + 1 == block_count { 0x80 } else { 0 };
+    new_data.push(block_type | is_last);
+    new_data.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    new_data.extend_from_slice(body);
+  }
+  new_data.extend_from_slice(&data[position..]);
+  Ok(new_data)
+}
+
+fn write_id3v2(data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+  let mut major_version = 3;
+  let mut frames: Vec<u8> = Vec::new();
+  let mut audio_start = 0;
+  if data.starts_with(b"ID3") && data.len() >= 10 {
+    major_version = data[3];
+    let flags = data[5];
+    let size = read_syncsafe(&data[6..10]) as usize;
+    audio_start = 10 + size + if major_version == 4 && flags & 0x10 != 0 { 10 } else { 0 };
+    if !(3..=4).contains(&major_version) {
+      return Err(anyhow::anyhow!("ID3v2.{} tag is not supported.", major_version));
+    }
+    let mut tag = data
+      .get(10..(10 + size))
+      .ok_or_else(|| anyhow::anyhow!("ID3v2 tag is truncated."))?
+      .to_vec();
+    if flags & 0x80 != 0 && major_version == 3 {
+      tag = remove_unsynchronization(&tag);
+    }
+    // The extended header is dropped.
+    let mut position = 0;
+    if flags & 0x40 != 0 && tag.len() >= 4 {
+      position = match major_version {
+        3 => 4 + u32::from_be_bytes(tag[0..4].try_into()?) as usize,
+        _ => read_syncsafe(&tag[0..4]) as usize,
+      };
+    }
+    // The existing frames are kept unless they are overwritten.
+    while position + 10 <= tag.len() && tag[position] != 0 {
+      let id = &tag[position..(position + 4)];
+      let frame_size = match major_version {
+        3 => u32::from_be_bytes(tag[(position + 4)..(position + 8)].try_into()?) as usize,
+        _ => read_syncsafe(&tag[(position + 4)..(position + 8)]) as usize,
+      };
+      let frame_end = (position + 10 + frame_size).min(tag.len());
+      if !get_id3v2_tag_name(id).is_some_and(|name| tags.contains_key(name)) {
+        let frame = &tag[position..frame_end];
+        if major_version == 4 && flags & 0x80 != 0 && frame[9] & 0x02 == 0 {
+          // The tag level unsynchronisation is dropped so the frame is decoded.
+          let body = remove_unsynchronization(&frame[10..]);
+          frames.extend_from_slice(&frame[0..4]);
+          frames.extend_from_slice(&write_syncsafe(body.len() as u32));
+          frames.extend_from_slice(&frame[8..10]);
+          frames.extend(body);
+        } else {
+          frames.extend_from_slice(frame);
+        }
+      }
+      position = frame_end;
+    }
+  }
+  for (name, value) in tags {
+    let mut body: Vec<u8> = Vec::new();
+    if major_version == 4 {
+      body.push(3);
+      body.extend_from_slice(value.as_bytes());
+    } else {
+      body.extend_from_slice(&[1, 0xFF, 0xFE]);
+      value
+        .encode_utf16()
+        .for_each(|unit| body.extend_from_slice(&unit.to_le_bytes()));
+    }
+    frames.extend_from_slice(get_id3v2_frame_id(name, major_version));
+    if major_version == 4 {
+      frames.extend_from_slice(&write_syncsafe(body.len() as u32));
+    } else {
+      frames.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    }
+    frames.extend_from_slice(&[0, 0]);
+    frames.extend_from_slice(&body);
+  }
+  let size = frames.len() + ID3V2_PADDING_SIZE;
+  if size >= 1 << 28 {
+    return Err(anyhow::anyhow!("ID3v2 tag is too large."));
+  }
+  let audio = data
+    .get(audio_start..)
+    .ok_or_else(|| anyhow::anyhow!("ID3v2 tag is truncated."))?;
+  let mut new_data = Vec::with_capacity(10 + size + audio.len());
+  new_data.extend_from_slice(&[b'I', b'D', b'3', major_version, 0, 0]);
+  new_data.extend_from_slice(&write_syncsafe(size as u32));
+  new_data.extend_from_slice(&frames);
+  new_data.resize(new_data.len() + ID3V2_PADDING_SIZE, 0);
+  new_data.extend_from_slice(audio);
+  Ok(new_data)
+}
+
+fn write_mp4(data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+  let atoms = parse_mp4_atoms(data)?;
+  if atoms.iter().any(|atom| &atom.atom_type == b"moof") {
+    return Err(anyhow::anyhow!("Fragmented MP4 files are not supported."));
+  }
+  let moov = atoms
+    .iter()
+    .find(|atom| &atom.atom_type == b"moov")
+    .ok_or_else(|| anyhow::anyhow!("Atom moov is not found."))?;
+  let write_ilst = |body: Option<&[u8]>| -> Result<Vec<u8>> {
+    let mut new_body: Vec<u8> = Vec::new();
+    if let Some(body) = body {
+      for atom in parse_mp4_atoms(body)? {
+        if !get_mp4_tag_name(&atom.atom_type).is_some_and(|name| tags.contains_key(name)) {
+          new_body.extend_from_slice(&body[atom.start..atom.end]);
+        }
+      }
+    }
+    for (name, value) in tags {
+      // The data atom starts with 4 bytes of type and 4 bytes of locale.
+      let mut data_body: Vec<u8> = Vec::new();
+      match name.as_str() {
+        TAG_DISC | TAG_TRACK => {
+          let number = value.parse::<u16>().map_err(anyhow::Error::msg)?;
+          data_body.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+          data_body.extend_from_slice(&number.to_be_bytes());
+          data_body.extend_from_slice(&[0, 0]);
+          if name == TAG_TRACK {
+            data_body.extend_from_slice(&[0, 0]);
+          }
+        }
+        _ => {
+          data_body.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0]);
+          data_body.extend_from_slice(value.as_bytes());
+        }
+      }
+      new_body.extend(write_mp4_atom(
+        get_mp4_atom_type(name),
+        &write_mp4_atom(b"data", &data_body),
+      ));
+    }
+    Ok(new_body)
+  };
+  let moov_body = &data[moov.body_start..moov.end];
+  let mut new_moov_body = write_mp4_path(moov_body, &[*b"udta", *b"meta", *b"ilst"], &write_ilst)?;
+  // The delta includes the change of the header size like a 64-bit size header rewritten as a 32-bit one.
+  let delta =
+    (get_mp4_atom_header_size(new_moov_body.len()) + new_moov_body.len()) as i64 - (moov.end - moov.start) as i64;
+  // The chunk offsets of the media data after the moov atom are shifted.
+  if delta != 0
+    && atoms
+      .iter()
+      .any(|atom| &atom.atom_type == b"mdat" && atom.start > moov.start)
+  {
+    write_mp4_chunk_offsets(&mut new_moov_body, moov.end as u64, delta)?;
+  }
+  let mut new_data = Vec::with_capacity((data.len() as i64 + delta) as usize + 16);
+  new_data.extend_from_slice(&data[..moov.start]);
+  new_data.extend(write_mp4_atom(b"moov", &new_moov_body));
+  new_data.extend_from_slice(&data[moov.end..]);
+  Ok(new_data)
+}
+
+fn write_mp4_atom(atom_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+  let mut atom = Vec::with_capacity(body.len() + 16);
+  if get_mp4_atom_header_size(body.len()) == 16 {
+    atom.extend_from_slice(&1u32.to_be_bytes());
+    atom.extend_from_slice(atom_type);
+    atom.extend_from_slice(&(body.len() as u64 + 16).to_be_bytes());
+  } else {
+    atom.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    atom.extend_from_slice(atom_type);
+  }
+  atom.extend_from_slice(body);
+  atom
+}
+
+/// Adds the delta to the chunk offsets beyond the end of the original moov atom.
+fn write_mp4_chunk_offsets(body: &mut [u8], moov_end: u64, delta: i64) -> Result<()> {
+  for atom in parse_mp4_atoms(body)? {
+    match &atom.atom_type {
+      b"trak" | b"mdia" | b"minf" | b"stbl" => {
+        write_mp4_chunk_offsets(&mut body[atom.body_start..atom.end], moov_end, delta)?;
+      }
+      b"stco" | b"co64" => {
+        let entry_size = if &atom.atom_type == b"stco" { 4 } else { 8 };
+        let table = &mut body[atom.body_start..atom.end];
+        let count = table
+          .get(4..8)
+          .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+          .ok_or_else(|| anyhow::anyhow!("Atom stco is truncated."))?;
+        for index in 0..count {
+          let start = 8 + index * entry_size;
+          let entry = table
+            .get_mut(start..(start + entry_size))
+            .ok_or_else(|| anyhow::anyhow!("Atom stco is truncated."))?;
+          let offset = if entry_size == 4 {
+            u32::from_be_bytes(entry[..].try_into()?) as u64
+          } else {
+            u64::from_be_bytes(entry[..].try_into()?)
+          };
+          if offset >= moov_end {
+            let offset = (offset as i64 + delta) as u64;
+            if entry_size == 4 {
+              let offset =
+                u32::try_from(offset).map_err(|_| anyhow::anyhow!("Chunk offset {} is too large.", offset))?;
+              entry.copy_from_slice(&offset.to_be_bytes());
+            } else {
+              entry.copy_from_slice(&offset.to_be_bytes());
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+/// Rebuilds the container along the path and creates the missing atoms.
+fn write_mp4_path<F: Fn(Option<&[u8]>) -> Result<Vec<u8>>>(
+  body: &[u8],
+  path: &[[u8; 4]],
+  write_leaf: &F,
+) -> Result<Vec<u8>> {
+  let atom_type = &path[0];
+  // The meta atom is a full atom with 4 bytes of version and flags.
+  let write_body = |child_body: Option<&[u8]>| -> Result<Vec<u8>> {
+    if path.len() == 1 {
+      return write_leaf(child_body);
+    }
+    if atom_type == b"meta" {
+      let (header, children) = match child_body {
+        Some(child_body) if child_body.len() >= 4 => (child_body[..4].to_vec(), Some(&child_body[4..])),
+        _ => {
+          // The handler is required by iTunes.
+          let mut header = vec![0, 0, 0, 0];
+          let mut hdlr = vec![0, 0, 0, 0, 0, 0, 0, 0];
+          hdlr.extend_from_slice(b"mdirappl");
+          hdlr.extend_from_slice(&[0; 9]);
+          header.extend(write_mp4_atom(b"hdlr", &hdlr));
+          (header, None)
+        }
+      };
+      let mut new_body = header;
+      new_body.extend(write_mp4_path(children.unwrap_or_default(), &path[1..], write_leaf)?);
+      Ok(new_body)
+    } else {
+      write_mp4_path(child_body.unwrap_or_default(), &path[1..], write_leaf)
+    }
+  };
+  let mut new_body: Vec<u8> = Vec::with_capacity(body.len());
+  let mut found = false;
+  for atom in parse_mp4_atoms(body)? {
+    if !found && &atom.atom_type == atom_type {
+      found = true;
+      new_body.extend(write_mp4_atom(
+        atom_type,
+        &write_body(Some(&body[atom.body_start..atom.end]))?,
+      ));
+    } else {
+      new_body.extend_from_slice(&body[atom.start..atom.end]);
+    }
+  }
+  if !found {
+    new_body.extend(write_mp4_atom(atom_type, &write_body(None)?));
+  }
+  Ok(new_body)
+}
+
+fn write_ogg(data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+  let pages = parse_ogg_pages(data)?;
+  let serial = pages
+    .first()
+    .map(|page| page.get_serial())
+    .ok_or_else(|| anyhow::anyhow!("Ogg page is not found."))?;
+  // Vorbis has 3 header packets and Opus has 2 header packets.
+  let mut packets: Vec<Vec<u8>> = Vec::new();
+  let mut packet: Vec<u8> = Vec::new();
+  let mut header_page_count = 0;
+  let mut header_packet_count = usize::MAX;
+  for page in pages.iter() {
+    if page.get_serial() != serial {
+      return Err(anyhow::anyhow!("Multiplexed Ogg streams are not supported."));
+    }
+    header_page_count += 1;
+    let mut position = 0;
+    for &lacing_value in page.lacing_values.iter() {
+      packet.extend_from_slice(&page.body[position..(position + lacing_value as usize)]);
+      position += lacing_value as usize;
+      if lacing_value < 255 {
+        packets.push(std::mem::take(&mut packet));
+        if packets.len() == 1 {
+          header_packet_count = if packets[0].starts_with(b"\x01vorbis") {
+            3
+          } else if packets[0].starts_with(b"OpusHead") {
+            2
+          } else {
+            return Err(anyhow::anyhow!("Ogg stream is neither Vorbis nor Opus."));
+          };
+        }
+      }
+    }
+    if packets.len() >= header_packet_count {
+      break;
+    }
+  }
+  if packets.len() != header_packet_count || !packet.is_empty() {
+    return Err(anyhow::anyhow!("Ogg header packets are invalid."));
+  }
+  packets[1] = if header_packet_count == 3 {
+    let mut comment = b"\x03vorbis".to_vec();
+    comment.extend(write_vorbis_comment(packets[1].get(7..), tags));
+    // The framing bit.
+    comment.push(1);
+    comment
+  } else {
+    let mut comment = b"OpusTags".to_vec();
+    comment.extend(write_vorbis_comment(packets[1].get(8..), tags));
+    comment
+  };
+  // The first packet is on its own page and the other header packets fill the following pages.
+  let mut lacing_values: Vec<u8> = Vec::new();
+  let mut body: Vec<u8> = Vec::new();
+  for packet in packets.iter().skip(1) {
+    lacing_values.resize(lacing_values.len() + packet.len() / 255, 255);
+    lacing_values.push((packet.len() % 255) as u8);
+    body.extend_from_slice(packet);
+  }
+  let mut new_pages: Vec<OggPage> = vec![pages[0].clone()];
+  let mut position = 0;
+  for chunk in lacing_values.chunks(255) {
+    let size: usize = chunk.iter().map(|&value| value as usize).sum();
+    let mut page = OggPage {
+      body: body[position..(position + size)].to_vec(),
+      header: pages[0].header,
+      lacing_values: chunk.to_vec(),
+    };
+    let is_continued = new_pages.len() > 1 && new_pages.last().and_then(|page| page.lacing_values.last()) == Some(&255);
+    page.header[5] = if is_continued { 0x01 } else { 0 };
+    // A page without a completed packet has no granule position.
+    let granule_position = if chunk.iter().any(|&value| value < 255) {
+      0
+    } else {
+      u64::MAX
+    };
+    page.header[6..14].copy_from_slice(&granule_position.to_le_bytes());
+    new_pages.push(page);
+    position += size;
+  }
+  new_pages.extend(pages.into_iter().skip(header_page_count));
+  let mut new_data = Vec::with_capacity(data.len() + 4096);
+  for (sequence, page) in new_pages.iter_mut().enumerate() {
+    page.write(sequence as u32, &mut new_data);
+  }
+  Ok(new_data)
+}
+
+/// Rebuilds a vorbis comment with the tags replacing the existing comments of the same tags.
+fn write_vorbis_comment(data: Option<&[u8]>, tags: &BTreeMap<String, String>) -> Vec<u8> {
+  let (vendor, comments) = data
+    .and_then(parse_vorbis_comment)
+    .unwrap_or_else(|| (VORBIS_VENDOR.as_bytes().to_vec(), Vec::new()));
+  let mut new_comments: Vec<String> = comments
+    .into_iter()
+    .filter(|comment| {
+      !comment
+        .split_once('=')
+        .and_then(|(key, _)| get_vorbis_tag_name(key))
+        .is_some_and(|name| tags.contains_key(name))
+    })
+    .collect();
+  for (name, value) in tags {
+    new_comments.push(format!("{}={}", get_vorbis_key(name), value));
+  }
+  let mut new_data: Vec<u8> = Vec::new();
+  new_data.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+  new_data.extend_from_slice(&vendor);
+  new_data.extend_from_slice(&(new_comments.len() as u32).to_le_bytes());
+  for comment in new_comments {
+    new_data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+    new_data.extend_from_slice(comment.as_bytes());
+  }
+  new_data
+}
+
+fn write_syncsafe(value: u32) -> [u8; 4] {
+  [
+    ((value >> 21) & 0x7F) as u8,
+    ((value >> 14) & 0x7F) as u8,
+    ((value >> 7) & 0x7F) as u8,
+    (value & 0x7F) as u8,
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  const MEDIA_DATA: &[u8] = b"MEDIA-DATA-0123456789";
+
+  /// Creates an MP4 file with the moov atom pointing to the media data in the mdat atom after it.
+  fn new_mp4(large_moov: bool, co64: bool) -> Vec<u8> {
+    let ftyp = write_mp4_atom(b"ftyp", b"M4A \0\0\0\0M4A isom");
+    let new_moov = |offset: u64| -> Vec<u8> {
+      let mut table = vec![0, 0, 0, 0];
+      table.extend_from_slice(&1u32.to_be_bytes());
+      if co64 {
+        table.extend_from_slice(&offset.to_be_bytes());
+      } else {
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+      }
+      let mut body = write_mp4_atom(if co64 { b"co64" } else { b"stco" }, &table);
+      for atom_type in [b"stbl", b"minf", b"mdia", b"trak"] {
+        body = write_mp4_atom(atom_type, &body);
+      }
+      if large_moov {
+        let mut moov = 1u32.to_be_bytes().to_vec();
+        moov.extend_from_slice(b"moov");
+        moov.extend_from_slice(&(body.len() as u64 + 16).to_be_bytes());
+        moov.extend(body);
+        moov
+      } else {
+        write_mp4_atom(b"moov", &body)
+      }
+    };
+    let offset = (ftyp.len() + new_moov(0).len() + 8) as u64;
+    let mut data = ftyp;
+    data.extend(new_moov(offset));
+    data.extend(write_mp4_atom(b"mdat", MEDIA_DATA));
+    data
+  }
+
+  /// Gets the first chunk offset in the stco or co64 atom.
+  fn get_mp4_chunk_offset(data: &[u8]) -> Option<u64> {
+    parse_mp4_atoms(data).unwrap().into_iter().find_map(|atom| {
+      let body = &data[atom.body_start..atom.end];
+      match &atom.atom_type {
+        b"moov" | b"trak" | b"mdia" | b"minf" | b"stbl" => get_mp4_chunk_offset(body),
+        b"stco" => Some(u32::from_be_bytes(body[8..12].try_into().unwrap()) as u64),
+        b"co64" => Some(u64::from_be_bytes(body[8..16].try_into().unwrap())),
+        _ => None,
+      }
+    })
+  }
+
+  /// Creates an ID3v2 tag with the text frames followed by the audio.
+  /// The unsynchronisation covers the whole tag in ID3v2.3 and each frame body in ID3v2.4.
+  fn new_id3v2(major_version: u8, flags: u8, frames: &[(&[u8; 4], &[u8])], audio: &[u8]) -> Vec<u8> {
+    let add_unsynchronization = |data: &[u8]| -> Vec<u8> {
+      if flags & 0x80 != 0 {
+        data
+          .iter()
+          .flat_map(|&b| if b == 0xFF { vec![b, 0] } else { vec![b] })
+          .collect()
+      } else {
+        data.to_vec()
+      }
+    };
+    let mut tag: Vec<u8> = Vec::new();
+    for (id, body) in frames {
+      tag.extend_from_slice(*id);
+      if major_version == 4 {
+        let body = add_unsynchronization(body);
+        tag.extend_from_slice(&write_syncsafe(body.len() as u32));
+        tag.extend_from_slice(&[0, 0]);
+        tag.extend(body);
+      } else {
+        tag.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        tag.extend_from_slice(&[0, 0]);
+        tag.extend_from_slice(body);
+      }
+    }
+    if major_version == 3 {
+      tag = add_unsynchronization(&tag);
+    }
+    let mut data = vec![b'I', b'D', b'3', major_version, 0, flags];
+    data.extend_from_slice(&write_syncsafe(tag.len() as u32));
+    data.extend(tag);
+    data.extend_from_slice(audio);
+    data
+  }
+
+  /// Creates a FLAC file with the stream info, the vorbis comment and the padding.
+  fn new_flac(comments: &[&str]) -> Vec<u8> {
+    let mut comment = Vec::new();
+    comment.extend_from_slice(&4u32.to_le_bytes());
+    comment.extend_from_slice(b"test");
+    comment.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for value in comments {
+      comment.extend_from_slice(&(value.len() as u32).to_le_bytes());
+      comment.extend_from_slice(value.as_bytes());
+    }
+    let mut data = b"fLaC".to_vec();
+    for (block_type, body) in [(0u8, vec![0u8; 34]), (4, comment), (0x81, vec![0u8; 16])] {
+      data.push(block_type);
+      data.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+      data.extend(body);
+    }
+    data.extend_from_slice(MEDIA_DATA);
+    data
+  }
+
+  /// Creates a Vorbis stream with each packet on its own page.
+  fn new_ogg(packets: &[&[u8]]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (sequence, packet) in packets.iter().enumerate() {
+      let mut header = [0u8; 27];
+      header[0..4].copy_from_slice(b"OggS");
+      header[5] = if sequence == 0 { 0x02 } else { 0 };
+      header[14..18].copy_from_slice(&1234u32.to_le_bytes());
+      let mut lacing_values = vec![255u8; packet.len() / 255];
+      lacing_values.push((packet.len() % 255) as u8);
+      let mut page = OggPage {
+        body: packet.to_vec(),
+        header,
+        lacing_values,
+      };
+      page.write(sequence as u32, &mut data);
+    }
+    data
+  }
+
+  fn new_tags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+      .iter()
+      .map(|(name, value)| (name.to_string(), value.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn test_parse_mp4_atoms_rejects_overflowing_large_size() {
+    let mut data = 1u32.to_be_bytes().to_vec();
+    data.extend_from_slice(b"moov");
+    data.extend_from_slice(&u64::MAX.to_be_bytes());
+    let error = parse_mp4_atoms(&data).err().unwrap();
+    assert_eq!("Atom moov is invalid.", error.to_string());
+  }
+
+  #[test]
+  fn test_read_mp4_atom_rejects_overflowing_large_size() {
+    let mut data = 1u32.to_be_bytes().to_vec();
+    data.extend_from_slice(b"moov");
+    data.extend_from_slice(&u64::MAX.to_be_bytes());
+    let mut reader = Cursor::new(data.as_slice());
+    let error = read_mp4_atom(&mut reader, (0, data.len() as u64), b"moov")
+      .err()
+      .unwrap();
+    assert_eq!("Atom moov is invalid.", error.to_string());
+  }
+
+  #[test]
+  fn test_write_mp4_shifts_chunk_offsets_of_large_moov() {
+    let data = new_mp4(true, false);
+    let new_data = write_mp4(&data, &new_tags(&[(TAG_TITLE, "Title")])).unwrap();
+    let offset = get_mp4_chunk_offset(&new_data).unwrap() as usize;
+    assert_eq!(MEDIA_DATA, &new_data[offset..(offset + MEDIA_DATA.len())]);
+  }
+
+  #[test]
+  fn test_write_id3v2_and_read_id3v2_of_v3() {
+    let album: &[u8] = &[1, 0xFF, 0xFE, b'A', 0, 0xFF, 0, b'b', 0];
+    let data = new_id3v2(3, 0x80, &[(b"TALB", album), (b"TIT2", b"\0Old")], MEDIA_DATA);
+    let new_data = write_id3v2(
+      &data,
+      &new_tags(&[(TAG_TITLE, "Titre \u{e9}t\u{e9}"), (TAG_TRACK, "3/12")]),
+    )
+    .unwrap();
+    assert_eq!(3, new_data[3]);
+    assert!(new_data.ends_with(MEDIA_DATA));
+    let mut tags = BTreeMap::new();
+    let size = read_id3v2(&mut Cursor::new(new_data.as_slice()), &mut tags).unwrap();
+    assert_eq!(MEDIA_DATA, &new_data[(size as usize)..]);
+    assert_eq!(
+      new_tags(&[
+        (TAG_ALBUM, "A\u{FF}b"),
+        (TAG_TITLE, "Titre \u{e9}t\u{e9}"),
+        (TAG_TRACK, "3")
+      ]),
+      tags
+    );
+  }
+
+  #[test]
+  fn test_write_id3v2_and_read_id3v2_of_v4() {
+    let data = new_id3v2(
+      4,
+      0,
+      &[(b"TALB", b"\x03Album"), (b"TDRC", b"\x032004-05-01")],
+      MEDIA_DATA,
+    );
+    let new_data = write_id3v2(&data, &new_tags(&[(TAG_TITLE, "\u{6807}\u{9898}"), (TAG_YEAR, "2010")])).unwrap();
+    assert_eq!(4, new_data[3]);
+    let mut tags = BTreeMap::new();
+    let size = read_id3v2(&mut Cursor::new(new_data.as_slice()), &mut tags).unwrap();
+    assert_eq!(MEDIA_DATA, &new_data[(size as usize)..]);
+    assert_eq!(
+      new_tags(&[
+        (TAG_ALBUM, "Album"),
+        (TAG_TITLE, "\u{6807}\u{9898}"),
+        (TAG_YEAR, "2010")
+      ]),
+      tags
+    );
+  }
+
+  #[test]
+  fn test_write_id3v2_decodes_tag_level_unsynchronization_of_v4() {
+    // The album frame has no frame level unsynchronisation flag.
+    let data = new_id3v2(4, 0x80, &[(b"TALB", &[0, b'a', 0xFF, 0xE0, b'b'])], MEDIA_DATA);
+    let mut tags = BTreeMap::new();
+    read_id3v2(&mut Cursor::new(data.as_slice()), &mut tags).unwrap();
+    assert_eq!(new_tags(&[(TAG_ALBUM, "a\u{FF}\u{E0}b")]), tags);
+    let new_data = write_id3v2(&data, &new_tags(&[(TAG_TITLE, "Title")])).unwrap();
+    assert_eq!(0, new_data[5]);
+    let mut tags = BTreeMap::new();
+    read_id3v2(&mut Cursor::new(new_data.as_slice()), &mut tags).unwrap();
+    assert_eq!(new_tags(&[(TAG_ALBUM, "a\u{FF}\u{E0}b"), (TAG_TITLE, "Title")]), tags);
+  }
+
+  #[test]
+  fn test_write_flac_and_read_flac() {
+    let data = new_flac(&["DATE=2004", "TITLE=Old", "COMMENT=Kept"]);
+    let new_data = write_flac(&data, &new_tags(&[(TAG_TITLE, "New"), (TAG_TRACK, "7")])).unwrap();
+    assert!(new_data.ends_with(MEDIA_DATA));
+    let mut tags = BTreeMap::new();
+    read_flac(&mut Cursor::new(new_data.as_slice()), 0, &mut tags).unwrap();
+    assert_eq!(
+      new_tags(&[(TAG_TITLE, "New"), (TAG_TRACK, "7"), (TAG_YEAR, "2004")]),
+      tags
+    );
+    // The vorbis comment follows the signature and the stream info.
+    let new_comments = parse_vorbis_comment(&new_data[46..]).unwrap().1;
+    assert_eq!(
+      vec!["DATE=2004", "COMMENT=Kept", "TITLE=New", "TRACKNUMBER=7"],
+      new_comments
+    );
+  }
+
+  #[test]
+  fn test_write_flac_and_read_flac_after_id3v2() {
+    let mut data = new_id3v2(3, 0, &[(b"TALB", b"\0Album")], &[]);
+    let offset = data.len() as u64;
+    data.extend(new_flac(&["TITLE=Old"]));
+    let new_data = write_flac(&data, &new_tags(&[(TAG_TITLE, "New")])).unwrap();
+    assert_eq!(&data[..(offset as usize)], &new_data[..(offset as usize)]);
+    assert!(new_data.ends_with(MEDIA_DATA));
+    let mut reader = Cursor::new(new_data.as_slice());
+    let mut tags = BTreeMap::new();
+    assert_eq!(offset, read_id3v2(&mut reader, &mut tags).unwrap());
+    read_flac(&mut reader, offset, &mut tags).unwrap();
+    assert_eq!(new_tags(&[(TAG_ALBUM, "Album"), (TAG_TITLE, "New")]), tags);
+  }
+
+  #[test]
+  fn test_write_ogg_and_read_ogg_across_pages() {
+    let mut comment = b"\x03vorbis".to_vec();
+    comment.extend(write_vorbis_comment(None, &new_tags(&[(TAG_YEAR, "2004")])));
+    comment.push(1);
+    let data = new_ogg(&[b"\x01vorbis-identification", &comment, b"\x05vorbis-setup", MEDIA_DATA]);
+    // The title is too long to fit the comment header in one page.
+    let title = "t".repeat(70000);
+    let new_data = write_ogg(&data, &new_tags(&[(TAG_TITLE, title.as_str())])).unwrap();
+    let pages = parse_ogg_pages(&new_data).unwrap();
+    assert_eq!(4, pages.len());
+    assert_eq!(0x01, pages[2].header[5]);
+    assert_eq!(MEDIA_DATA, pages[3].body.as_slice());
+    // The pages are written with the recalculated checksums.
+    let mut page_data = Vec::new();
+    for (sequence, mut page) in pages.into_iter().enumerate() {
+      page.write(sequence as u32, &mut page_data);
+    }
+    assert_eq!(new_data, page_data);
+    let mut tags = BTreeMap::new();
+    read_ogg(&mut Cursor::new(new_data.as_slice()), &mut tags).unwrap();
+    assert_eq!(new_tags(&[(TAG_TITLE, title.as_str()), (TAG_YEAR, "2004")]), tags);
+    // The comment header across the pages is rewritten into one page.
+    let new_data = write_ogg(&new_data, &new_tags(&[(TAG_TITLE, "Title")])).unwrap();
+    assert_eq!(3, parse_ogg_pages(&new_data).unwrap().len());
+    let mut tags = BTreeMap::new();
+    read_ogg(&mut Cursor::new(new_data.as_slice()), &mut tags).unwrap();
+    assert_eq!(new_tags(&[(TAG_TITLE, "Title"), (TAG_YEAR, "2004")]), tags);
+  }
+}
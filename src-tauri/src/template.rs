@@ -0,0 +1,440 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use chrono::format::{Fixed, Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+use crate::rules;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+enum Filter {
+  Lower,
+  Pad { fill: char, width: usize },
+  Replace { from: String, to: String },
+  Slug,
+  Title,
+  Trim(Vec<char>),
+  Truncate(usize),
+  Upper,
+}
+
+impl Filter {
+  fn new(template: &str, name: &str, args: &[String]) -> Result<Self> {
+    let get_usize = |index: usize| -> Result<usize> {
+      args
+        .get(index)
+        .and_then(|arg| arg.parse::<usize>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Template {} has an invalid number in filter {}.", template, name))
+    };
+    let filter = match name {
+      "lower" => Self::Lower,
+      "pad" => Self::Pad {
+        fill: args.get(1).and_then(|arg| arg.chars().next()).unwrap_or('0'),
+        width: get_usize(0)?,
+      },
+      "replace" => Self::Replace {
+        from: args
+          .first()
+          .filter(|arg| !arg.is_empty())
+          .cloned()
+          .ok_or_else(|| anyhow::anyhow!("Template {} has an empty text in filter replace.", template))?,
+        to: args.get(1).cloned().unwrap_or_default(),
+      },
+      "slug" => Self::Slug,
+      "title" => Self::Title,
+      "trim" => Self::Trim(args.first().map(|arg| arg.chars().collect()).unwrap_or_default()),
+      "truncate" => Self::Truncate(get_usize(0)?),
+      "upper" => Self::Upper,
+      _ => return Err(anyhow::anyhow!("Template {} has an unknown filter {}.", template, name)),
+    };
+    Ok(filter)
+  }
+
+  fn apply(&self, value: String) -> String {
+    match self {
+      Self::Lower => value.to_lowercase(),
+      Self::Pad { fill, width } => pad(value, *fill, *width),
+      Self::Replace { from, to } => value.replace(from.as_str(), to.as_str()),
+      Self::Slug => {
+        // Every run of non-alphanumeric characters becomes a single "-".
+        let mut slug = String::with_capacity(value.len());
+        for c in value.chars().flat_map(|c| c.to_lowercase()) {
+          if c.is_alphanumeric() {
+            slug.push(c);
+          } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+          }
+        }
+        slug.trim_end_matches('-').to_string()
+      }
+      Self::Title => rules::change_case(value.as_str(), "Title Case"),
+      Self::Trim(characters) => {
+        if characters.is_empty() {
+          value.trim().to_string()
+        } else {
+          value.trim_matches(characters.as_slice()).to_string()
+        }
+      }
+      Self::Truncate(count) => value.chars().take(*count).collect(),
+      Self::Upper => value.to_uppercase(),
+    }
+  }
+}
+
+enum Format {
+  Date(String),
+  None,
+  Pad { fill: char, width: usize },
+}
+
+enum Part {
+  Field {
+    filters: Vec<Filter>,
+    format: Format,
+    name: String,
+  },
+  Text(String),
+}
+
+/// A parsed template like "{name|lower|slug}_{counter:03}{ext}".
+/// A field is followed by an optional format and filters separated by "|" with arguments separated by ":".
+/// The format is either a width with an optional leading zero as the fill character,
+/// or a date format starting with "%".
+/// "{{" and "}}" are literal braces and "\" escapes the next character in a field.
+pub struct Template {
+  parts: Vec<Part>,
+}
+
+/// A regular expression with a group per field in the order of appearance.
+pub struct Matcher {
+  fields: Vec<String>,
+  regex: Regex,
+}
+
+impl Matcher {
+  /// Gets the values of the fields. A field appearing more than once must have the same value everywhere.
+  pub fn captures(&self, text: &str) -> Option<BTreeMap<String, String>> {
+    let captures = self.regex.captures(text)?;
+    let mut values: BTreeMap<String, String> = BTreeMap::new();
+    for (field, value) in self.fields.iter().zip(captures.iter().skip(1)) {
+      let value = value.map_or("", |value| value.as_str());
+      match values.get(field) {
+        Some(old_value) if old_value != value => return None,
+        Some(_) => {}
+        None => {
+          values.insert(field.clone(), value.to_string());
+        }
+      }
+    }
+    Some(values)
+  }
+}
+
+/// The value of a field. A date is formatted as "%Y-%m-%d" unless a date format is given.
+pub enum Value {
+  Date(NaiveDateTime),
+  Text(String),
+}
+
+impl Template {
+  pub fn parse(template: &str) -> Result<Self> {
+    let mut parts: Vec<Part> = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+      match c {
+        '{' if chars.peek() == Some(&'{') => {
+          chars.next();
+          text.push('{');
+        }
+        '}' if chars.peek() == Some(&'}') => {
+          chars.next();
+          text.push('}');
+        }
+        '{' => {
+          // The field is split into segments by "|" and every segment is split into arguments by ":".
+          let mut segments: Vec<Vec<String>> = vec![vec![String::new()]];
+          loop {
+            let c = match chars.next() {
+              Some('}') => break,
+              Some('{') => return Err(anyhow::anyhow!("Template {} has a nested brace.", template)),
+              Some('\\') => chars.next(),
+              Some('|') => {
+                segments.push(vec![String::new()]);
+                continue;
+              }
+              Some(':') => {
+                segments.last_mut().unwrap().push(String::new());
+                continue;
+              }
+              c => c,
+            };
+            match c {
+              Some(c) => segments.last_mut().unwrap().last_mut().unwrap().push(c),
+              None => return Err(anyhow::anyhow!("Template {} has an unclosed brace.", template)),
+            }
+          }
+          if !text.is_empty() {
+            parts.push(Part::Text(std::mem::take(&mut text)));
+          }
+          parts.push(parse_field(template, segments)?);
+        }
+        '}' => return Err(anyhow::anyhow!("Template {} has an unmatched closing brace.", template)),
+        _ => text.push(c),
+      }
+    }
+    if !text.is_empty() {
+      parts.push(Part::Text(text));
+    }
+    Ok(Self { parts })
+  }
+
+  /// Gets the fields in the order of appearance.
+  pub fn get_fields(&self) -> Vec<&str> {
+    self
+      .parts
+      .iter()
+      .filter_map(|part| match part {
+        Part::Field { name, .. } => Some(name.as_str()),
+        Part::Text(_) => None,
+      })
+      .collect()
+  }
+
+  /// Gets a matcher of a whole text rendered by the template.
+  pub fn get_matcher(&self) -> Result<Matcher> {
+    let mut pattern = String::from("^");
+    for part in &self.parts {
+      match part {
+        Part::Field { .. } => pattern.push_str("(.+?)"),
+        Part::Text(text) => pattern.push_str(regex::escape(text).as_str()),
+      }
+    }
+    pattern.push('$');
+    Ok(Matcher {
+      fields: self.get_fields().into_iter().map(|field| field.to_string()).collect(),
+      regex: Regex::new(pattern.as_str()).map_err(anyhow::Error::msg)?,
+    })
+  }
+
+  /// Gets the count of "/" in the text, which is the count of the parent directories a rendered text spans.
+  pub fn get_separator_count(&self) -> usize {
+    self
+      .parts
+      .iter()
+      .map(|part| match part {
+        Part::Field { .. } => 0,
+        Part::Text(text) => text.matches('/').count(),
+      })
+      .sum()
+  }
+
+  /// Renders the template. A missing field is rendered as an empty string.
+  /// Path separators in the values are replaced with "_" so that only the template creates directories.
+  pub fn render(&self, fields: &BTreeMap<String, Value>) -> String {
+    let mut output = String::new();
+    for part in &self.parts {
+      match part {
+        Part::Field { filters, format, name } => {
+          let mut value = match (fields.get(name), format) {
+            (None, _) => String::new(),
+            (Some(Value::Date(date_time)), Format::Date(date_format)) => date_time.format(date_format).to_string(),
+            (Some(Value::Date(date_time)), _) => date_time.format(DEFAULT_DATE_FORMAT).to_string(),
+            // A text like "2024-06-01" can be formatted as a date as well.
+            (Some(Value::Text(text)), Format::Date(date_format)) => match parse_date_time(text) {
+              Some(date_time) => date_time.format(date_format).to_string(),
+              None => text.clone(),
+            },
+            (Some(Value::Text(text)), _) => text.clone(),
+          };
+          if let Format::Pad { fill, width } = format {
+            value = pad(value, *fill, *width);
+          }
+          for filter in filters {
+            value = filter.apply(value);
+          }
+          output.extend(value.chars().map(|c| if std::path::is_separator(c) { '_' } else { c }));
+        }
+        Part::Text(text) => output.push_str(text),
+      }
+    }
+    output
+  }
+}
+
+fn pad(value: String, fill: char, width: usize) -> String {
+  let count = value.chars().count();
+  if count >= width {
+    return value;
+  }
+  let mut new_value = fill.to_string().repeat(width - count);
+  new_value.push_str(value.as_str());
+  new_value
+}
+
+fn parse_date_time(text: &str) -> Option<NaiveDateTime> {
+  NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S")
+    .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S"))
+    .ok()
+    .or_else(|| {
+      NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+}
+
+/// Validates a date format for a date without a time zone.
+/// The time zone items like "%z" and "%Z" can't be formatted and are rejected.
+pub fn validate_date_format(date_format: &str) -> Result<()> {
+  if StrftimeItems::new(date_format).any(|item| {
+    matches!(
+      item,
+      Item::Error
+        | Item::Fixed(
+          Fixed::TimezoneName
+            | Fixed::TimezoneOffset
+            | Fixed::TimezoneOffsetColon
+            | Fixed::TimezoneOffsetColonZ
+            | Fixed::TimezoneOffsetDoubleColon
+            | Fixed::TimezoneOffsetTripleColon
+            | Fixed::TimezoneOffsetZ
+            | Fixed::RFC2822
+            | Fixed::RFC3339
+            | Fixed::Internal(_)
+        )
+    )
+  }) {
+    return Err(anyhow::anyhow!("Date format {} is invalid.", date_format));
+  }
+  Ok(())
+}
+
+fn parse_field(template: &str, mut segments: Vec<Vec<String>>) -> Result<Part> {
+  let mut head = segments.remove(0).into_iter();
+  let name = head.next().unwrap_or_default().trim().to_string();
+  if name.is_empty() {
+    return Err(anyhow::anyhow!("Template {} has an empty field.", template));
+  }
+  // A date format may contain ":" as well.
+  let format = head.collect::<Vec<String>>().join(":");
+  let format = if format.is_empty() {
+    Format::None
+  } else if format.starts_with('%') {
+    validate_date_format(format.as_str())?;
+    Format::Date(format)
+  } else {
+    Format::Pad {
+      fill: if format.starts_with('0') { '0' } else { ' ' },
+      width: format.parse::<usize>().map_err(|_| {
+        anyhow::anyhow!(
+          "Template {} has an invalid format {} for field {}.",
+          template,
+          format,
+          name
+        )
+      })?,
+    }
+  };
+  let filters = segments
+    .into_iter()
+    .map(|segment| {
+      let filter_name = segment[0].trim().to_string();
+      Filter::new(template, filter_name.as_str(), &segment[1..])
+    })
+    .collect::<Result<Vec<Filter>>>()?;
+  Ok(Part::Field { filters, format, name })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_date_fields() -> BTreeMap<String, Value> {
+    let date_time = NaiveDate::from_ymd_opt(2024, 6, 1)
+      .unwrap()
+      .and_hms_opt(12, 30, 0)
+      .unwrap();
+    let mut fields: BTreeMap<String, Value> = BTreeMap::new();
+    fields.insert("mtime".to_string(), Value::Date(date_time));
+    fields.insert("now".to_string(), Value::Text("2024-06-01".to_string()));
+    fields
+  }
+
+  #[test]
+  fn test_render_formats_dates() {
+    let template = Template::parse("{mtime}_{mtime:%Y/%H:%M}_{now:%d.%m}").unwrap();
+    assert_eq!("2024-06-01_2024_12:30_01.06", template.render(&new_date_fields()));
+  }
+
+  #[test]
+  fn test_render_pads_and_filters_the_fields() {
+    let mut fields: BTreeMap<String, Value> = BTreeMap::new();
+    fields.insert("name".to_string(), Value::Text(" Hello, World/Again ".to_string()));
+    fields.insert("counter".to_string(), Value::Text("7".to_string()));
+    let template = Template::parse("{name|trim|slug}_{counter:03}{{{missing}}}").unwrap();
+    assert_eq!("hello-world-again_007{}", template.render(&fields));
+  }
+
+  #[test]
+  fn test_get_matcher_captures_the_repeated_fields() {
+    let template = Template::parse("{artist}/{album}/{artist} - {title} ({artist})").unwrap();
+    let matcher = template.get_matcher().unwrap();
+    let values = matcher.captures("A/B/A - C (A)").unwrap();
+    let values: Vec<(&str, &str)> = values.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    assert_eq!(vec![("album", "B"), ("artist", "A"), ("title", "C")], values);
+    assert!(matcher.captures("A/B/A - C (D)").is_none());
+    assert!(matcher.captures("A - C (A)").is_none());
+  }
+
+  #[test]
+  fn test_get_separator_count_ignores_the_fields() {
+    assert_eq!(
+      2,
+      Template::parse("{artist}/{album}/{title}")
+        .unwrap()
+        .get_separator_count()
+    );
+    assert_eq!(
+      0,
+      Template::parse("{title:%Y/%m}{a\\/b}").unwrap().get_separator_count()
+    );
+  }
+
+  #[test]
+  fn test_parse_rejects_invalid_templates() {
+    assert!(Template::parse("{name").is_err());
+    assert!(Template::parse("name}").is_err());
+    assert!(Template::parse("{}").is_err());
+    assert!(Template::parse("{name|unknown}").is_err());
+    assert!(Template::parse("{counter:x3}").is_err());
+  }
+
+  #[test]
+  fn test_parse_rejects_the_time_zone_in_date_formats() {
+    for date_format in ["%z", "%Z", "%:z", "%::z", "%#z", "%+", "%c%z", "%Q"] {
+      let error = Template::parse(format!("{{now:{}}}", date_format).as_str())
+        .err()
+        .unwrap();
+      assert_eq!(format!("Date format {} is invalid.", date_format), error.to_string());
+    }
+    assert!(validate_date_format("%Y-%m-%d %H:%M:%S %a %b %p").is_ok());
+  }
+}
@@ -0,0 +1,354 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use chrono::{Local, NaiveDateTime};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::protocol;
+
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+
+/// A record of the trash info in the freedesktop trash.
+struct TrashInfo {
+  deletion_date: NaiveDateTime,
+  info_path: PathBuf,
+  name: String,
+}
+
+/// Checks if the file or directory can be moved to the home trash which must be on the same device.
+#[cfg(target_os = "linux")]
+pub fn check_trash(path: &str) -> Result<()> {
+  use std::os::unix::fs::MetadataExt;
+  let device = fs::symlink_metadata(path).map_err(anyhow::Error::msg)?.dev();
+  let trash_path = get_trash_path()?;
+  // The nearest existing ancestor tells the device of the trash before it is created.
+  let trash_device = trash_path
+    .ancestors()
+    .find_map(|ancestor_path| fs::metadata(ancestor_path).ok())
+    .map(|metadata| metadata.dev())
+    .ok_or_else(|| anyhow::anyhow!("Trash {} is not found.", trash_path.display()))?;
+  if device != trash_device {
+    return Err(anyhow::anyhow!(
+      "Path {} cannot be sent to the trash {} on another device.",
+      path,
+      trash_path.display()
+    ));
+  }
+  Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn check_trash(_path: &str) -> Result<()> {
+  get_trash_path().map(|_| ())
+}
+
+/// Decodes the percent encoded path in the trash info.
+fn decode_path(text: &str) -> String {
+  let bytes = text.as_bytes();
+  let mut decoded_bytes: Vec<u8> = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    if bytes[index] == b'%' && index + 2 < bytes.len() {
+      if let Some(byte) = std::str::from_utf8(&bytes[(index + 1)..(index + 3)])
+        .ok()
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+      {
+        decoded_bytes.push(byte);
+        index += 3;
+        continue;
+      }
+    }
+    decoded_bytes.push(bytes[index]);
+    index += 1;
+  }
+  String::from_utf8_lossy(&decoded_bytes).to_string()
+}
+
+/// Encodes the path with the percent encoding except the unreserved characters and "/".
+fn encode_path(path: &str) -> String {
+  path
+    .bytes()
+    .map(|byte| {
+      if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+        (byte as char).to_string()
+      } else {
+        format!("%{:02X}", byte)
+      }
+    })
+    .collect()
+}
+
+/// Gets the home trash which is $XDG_DATA_HOME/Trash or ~/.local/share/Trash.
+#[cfg(target_os = "linux")]
+fn get_trash_path() -> Result<PathBuf> {
+  match std::env::var_os("XDG_DATA_HOME").filter(|path| !path.is_empty()) {
+    Some(path) => Ok(PathBuf::from(path).join("Trash")),
+    None => std::env::var_os("HOME")
+      .filter(|path| !path.is_empty())
+      .map(|path| PathBuf::from(path).join(".local").join("share").join("Trash"))
+      .ok_or_else(|| anyhow::anyhow!("Home directory is not found.")),
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_trash_path() -> Result<PathBuf> {
+  Err(anyhow::anyhow!("Trash is only supported on Linux."))
+}
+
+/// Reads the trash info records by the original paths with the latest deletion date winning.
+fn read_trash_infos(trash_path: &Path) -> Result<HashMap<String, TrashInfo>> {
+  let mut trash_info_map: HashMap<String, TrashInfo> = HashMap::new();
+  let info_path = trash_path.join("info");
+  if !info_path.is_dir() {
+    return Ok(trash_info_map);
+  }
+  for entry in info_path.read_dir().map_err(anyhow::Error::msg)? {
+    let info_path = entry.map_err(anyhow::Error::msg)?.path();
+    if info_path
+      .extension()
+      .is_none_or(|extension| extension != TRASH_INFO_EXTENSION)
+    {
+      continue;
+    }
+    let name = match info_path.file_stem() {
+      Some(name) => name.to_string_lossy().to_string(),
+      None => continue,
+    };
+    let text = match fs::read_to_string(info_path.as_path()) {
+      Ok(text) => text,
+      Err(err) => {
+        log::warn!("Couldn't read {} because {}", info_path.display(), err);
+        continue;
+      }
+    };
+    let mut path: Option<String> = None;
+    let mut deletion_date = NaiveDateTime::MIN;
+    for line in text.lines() {
+      if let Some(value) = line.strip_prefix("Path=") {
+        path = Some(decode_path(value.trim()));
+      } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+        deletion_date = NaiveDateTime::parse_from_str(value.trim(), DELETION_DATE_FORMAT).unwrap_or(deletion_date);
+      }
+    }
+    if let Some(path) = path {
+      if trash_info_map
+        .get(path.as_str())
+        .is_none_or(|trash_info| trash_info.deletion_date <= deletion_date)
+      {
+        trash_info_map.insert(
+          path,
+          TrashInfo {
+            deletion_date,
+            info_path,
+            name,
+          },
+        );
+      }
+    }
+  }
+  Ok(trash_info_map)
+}
+
+/// Restores the items from the freedesktop trash to their original paths by the trash info records.
+/// The partial result is returned along with the error so that the remaining items can be restored later.
+pub fn restore_from_trash(paths: &[String]) -> Result<protocol::RestoreResult> {
+  restore_from_trash_path(get_trash_path()?.as_path(), paths)
+}
+
+fn restore_from_trash_path(trash_path: &Path, paths: &[String]) -> Result<protocol::RestoreResult> {
+  let mut trash_info_map = read_trash_infos(trash_path)?;
+  let mut trash_infos: Vec<(&String, TrashInfo)> = Vec::new();
+  for path in paths.iter() {
+    let trash_info = trash_info_map
+      .remove(path.as_str())
+      .ok_or_else(|| anyhow::anyhow!("Path {} is not found in the trash.", path))?;
+    if Path::new(path.as_str()).exists() {
+      return Err(anyhow::anyhow!("Path {} exists.", path));
+    }
+    trash_infos.push((path, trash_info));
+  }
+  let mut restored_paths: Vec<String> = Vec::new();
+  let result = (|| -> Result<()> {
+    for (path, trash_info) in trash_infos {
+      let original_path = Path::new(path.as_str());
+      if let Some(parent_path) = original_path.parent() {
+        fs::create_dir_all(parent_path).map_err(anyhow::Error::msg)?;
+      }
+      fs::rename(trash_path.join("files").join(trash_info.name), original_path).map_err(anyhow::Error::msg)?;
+      restored_paths.push(path.clone());
+      // The item is restored even if its record cannot be removed.
+      if let Err(err) = fs::remove_file(trash_info.info_path.as_path()) {
+        log::warn!("Couldn't remove {} because {}", trash_info.info_path.display(), err);
+      }
+    }
+    Ok(())
+  })();
+  Ok(protocol::RestoreResult {
+    error: result.err().map(|err| err.to_string()),
+    restored_paths,
+  })
+}
+
+/// Moves the file or directory to the freedesktop home trash with a .trashinfo record, and returns the recorded path.
+/// The name in the trash is reserved by creating the record exclusively, and suffixed with a counter if it is taken.
+pub fn send_to_trash(path: &str) -> Result<String> {
+  let source_path = std::path::absolute(path).map_err(anyhow::Error::msg)?;
+  let source_path_string = source_path.to_string_lossy().to_string();
+  let trash_path = get_trash_path()?;
+  let files_path = trash_path.join("files");
+  let info_path = trash_path.join("info");
+  fs::create_dir_all(files_path.as_path()).map_err(anyhow::Error::msg)?;
+  fs::create_dir_all(info_path.as_path()).map_err(anyhow::Error::msg)?;
+  let file_name = source_path
+    .file_name()
+    .map(|file_name| file_name.to_string_lossy().to_string())
+    .ok_or_else(|| anyhow::anyhow!("Path {} cannot be sent to the trash.", path))?;
+  let mut counter = 1;
+  loop {
+    let name = if counter == 1 {
+      file_name.clone()
+    } else {
+      let file_path = Path::new(file_name.as_str());
+      match (file_path.file_stem(), file_path.extension()) {
+        (Some(stem), Some(extension)) => {
+          format!("{} {}.{}", stem.to_string_lossy(), counter, extension.to_string_lossy())
+        }
+        _ => format!("{} {}", file_name, counter),
+      }
+    };
+    let trash_info_path = info_path.join(format!("{}.{}", name, TRASH_INFO_EXTENSION));
+    let trash_file_path = files_path.join(name.as_str());
+    counter += 1;
+    if trash_file_path.exists() {
+      continue;
+    }
+    let mut file = match fs::OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(trash_info_path.as_path())
+    {
+      Ok(file) => file,
+      Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+      Err(err) => return Err(anyhow::Error::msg(err)),
+    };
+    write!(
+      file,
+      "[Trash Info]\nPath={}\nDeletionDate={}\n",
+      encode_path(source_path_string.as_str()),
+      Local::now().format(DELETION_DATE_FORMAT)
+    )
+    .map_err(anyhow::Error::msg)?;
+    // The record is removed if the file cannot be moved like the one on another file system.
+    if let Err(err) = fs::rename(source_path.as_path(), trash_file_path) {
+      let _ = fs::remove_file(trash_info_path);
+      return Err(anyhow::anyhow!(
+        "Path {} cannot be sent to the trash because {}",
+        path,
+        err
+      ));
+    }
+    return Ok(source_path_string);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_trash_path() -> PathBuf {
+    let trash_path = std::env::temp_dir().join(format!("BatchFR-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(trash_path.join("files")).unwrap();
+    fs::create_dir_all(trash_path.join("info")).unwrap();
+    trash_path
+  }
+
+  fn write_trash_info(trash_path: &Path, name: &str, path: &str, deletion_date: &str) {
+    fs::write(
+      trash_path
+        .join("info")
+        .join(format!("{}.{}", name, TRASH_INFO_EXTENSION)),
+      format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(path),
+        deletion_date
+      ),
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn test_encode_path_and_decode_path() {
+    for path in ["/x/a b.txt", "/x/100%.txt", "/x/äöü/日本.txt", "/x/a+b=c&d#e?.txt"] {
+      assert_eq!(path, decode_path(encode_path(path).as_str()));
+    }
+    assert_eq!("/x/a%20b.txt", encode_path("/x/a b.txt"));
+    assert_eq!("/x/100%25.txt", encode_path("/x/100%.txt"));
+    assert_eq!("/x/%C3%A4.txt", encode_path("/x/ä.txt"));
+    // The bytes which are not UTF-8 are replaced, and the invalid escapes are kept as they are.
+    assert_eq!("/x/\u{FFFD}.txt", decode_path("/x/%FF.txt"));
+    assert_eq!("/x/%zz%", decode_path("/x/%zz%"));
+  }
+
+  #[test]
+  fn test_read_trash_infos() {
+    let trash_path = new_trash_path();
+    write_trash_info(&trash_path, "a b.txt", "/x/a b.txt", "2024-01-02T03:04:05");
+    write_trash_info(&trash_path, "a b 2.txt", "/x/a b.txt", "2024-01-03T03:04:05");
+    write_trash_info(&trash_path, "a b 3.txt", "/x/a b.txt", "2023-12-31T03:04:05");
+    write_trash_info(&trash_path, "%.txt", "/x/100%.txt", "invalid");
+    fs::write(trash_path.join("info").join("other.txt"), "Path=/x/other.txt\n").unwrap();
+    let trash_info_map = read_trash_infos(trash_path.as_path()).unwrap();
+    fs::remove_dir_all(trash_path).unwrap();
+    assert_eq!(2, trash_info_map.len());
+    let trash_info = trash_info_map.get("/x/a b.txt").unwrap();
+    assert_eq!("a b 2.txt", trash_info.name);
+    assert_eq!(
+      NaiveDateTime::parse_from_str("2024-01-03T03:04:05", DELETION_DATE_FORMAT).unwrap(),
+      trash_info.deletion_date
+    );
+    let trash_info = trash_info_map.get("/x/100%.txt").unwrap();
+    assert_eq!("%.txt", trash_info.name);
+    assert_eq!(NaiveDateTime::MIN, trash_info.deletion_date);
+  }
+
+  #[test]
+  fn test_restore_from_trash_returns_the_partial_result() {
+    let trash_path = new_trash_path();
+    let restore_path = trash_path.join("restore");
+    let paths: Vec<String> = ["a.txt", "b.txt"]
+      .iter()
+      .map(|name| restore_path.join(name).to_string_lossy().to_string())
+      .collect();
+    write_trash_info(&trash_path, "a.txt", paths[0].as_str(), "2024-01-02T03:04:05");
+    write_trash_info(&trash_path, "b.txt", paths[1].as_str(), "2024-01-02T03:04:05");
+    // Only the first item is in the trash, so the second one fails to be restored.
+    fs::write(trash_path.join("files").join("a.txt"), "a").unwrap();
+    let result = restore_from_trash_path(trash_path.as_path(), &paths).unwrap();
+    let restored = Path::new(paths[0].as_str()).exists();
+    let info_removed = !trash_path.join("info").join("a.txt.trashinfo").exists();
+    fs::remove_dir_all(trash_path).unwrap();
+    assert_eq!(vec![paths[0].clone()], result.restored_paths);
+    assert!(result.error.is_some());
+    assert!(restored);
+    assert!(info_removed);
+  }
+}
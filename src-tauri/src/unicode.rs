@@ -0,0 +1,194 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::protocol;
+use crate::sanitize;
+
+/// Groups the items by the NFC form of the target paths,
+/// and returns the groups of the target paths which differ only by the normalization.
+pub fn find_normalization_duplicates(items: &[protocol::Item]) -> Vec<protocol::NormalizationDuplicateGroup> {
+  let mut item_map: BTreeMap<String, Vec<protocol::Item>> = BTreeMap::new();
+  for item in items.iter() {
+    item_map
+      .entry(normalize(
+        item.target_path.as_str(),
+        protocol::UnicodeNormalization::Nfc,
+      ))
+      .or_default()
+      .push(item.clone());
+  }
+  item_map
+    .into_iter()
+    .filter(|(_, items)| items.iter().any(|item| item.target_path != items[0].target_path))
+    .map(|(path, items)| protocol::NormalizationDuplicateGroup { items, path })
+    .collect()
+}
+
+/// Checks whether the paths refer to the same file which happens on macOS where the names are normalization insensitive.
+pub fn is_same_file(path: &str, other_path: &str) -> bool {
+  #[cfg(unix)]
+  {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(path), fs::metadata(other_path)) {
+      (Ok(metadata), Ok(other_metadata)) => {
+        metadata.dev() == other_metadata.dev() && metadata.ino() == other_metadata.ino()
+      }
+      _ => false,
+    }
+  }
+  #[cfg(not(unix))]
+  {
+    path == other_path
+  }
+}
+
+pub fn normalize(text: &str, normalization: protocol::UnicodeNormalization) -> String {
+  match normalization {
+    protocol::UnicodeNormalization::Nfc => text.nfc().collect(),
+    protocol::UnicodeNormalization::Nfd => text.nfd().collect(),
+  }
+}
+
+/// Normalizes the scanned items by setting the normalized target paths,
+/// and drops the items which are the same file as an earlier one under another normalization.
+pub fn normalize_items(
+  items: Vec<protocol::Item>,
+  normalization: protocol::UnicodeNormalization,
+) -> Vec<protocol::Item> {
+  let mut source_path_map: HashMap<String, Vec<String>> = HashMap::new();
+  items
+    .into_iter()
+    .filter_map(|mut item| {
+      let source_paths = source_path_map
+        .entry(normalize(item.source_path.as_str(), normalization))
+        .or_default();
+      if source_paths
+        .iter()
+        .any(|source_path| is_same_file(source_path.as_str(), item.source_path.as_str()))
+      {
+        log::debug!(
+          "Skipping {} which is scanned under another normalization.",
+          item.source_path
+        );
+        return None;
+      }
+      source_paths.push(item.source_path.clone());
+      item.target_path = normalize_path(item.source_path.as_str(), item.target_path.as_str(), normalization);
+      Some(item)
+    })
+    .collect()
+}
+
+/// Normalizes the components of the target path beyond the parent directory of the source path
+/// so that the existing parent directories are kept as is.
+pub fn normalize_path(source_path: &str, target_path: &str, normalization: protocol::UnicodeNormalization) -> String {
+  let target_path = Path::new(target_path);
+  let index = sanitize::get_new_component_index(Path::new(source_path), target_path);
+  let path: PathBuf = target_path
+    .components()
+    .enumerate()
+    .map(|(component_index, component)| match component {
+      Component::Normal(name) if component_index >= index => {
+        PathBuf::from(normalize(name.to_string_lossy().as_ref(), normalization))
+      }
+      component => PathBuf::from(component.as_os_str()),
+    })
+    .collect();
+  path.to_string_lossy().to_string()
+}
+
+/// Transliterates the text to ASCII like "Ærøskøbing" to "Aeroskobing", "北京" to "BeiJing" and "ひらがな" to "hiragana".
+/// The path separators produced by symbols like "½" are replaced with "-" so that no directory is created.
+pub fn transliterate(text: &str) -> String {
+  any_ascii::any_ascii(text).replace(['/', '\\'], "-")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_item(source_path: &str, target_path: &str) -> protocol::Item {
+    protocol::Item {
+      target_path: target_path.to_string(),
+      ..protocol::Item::new_file(source_path)
+    }
+  }
+
+  #[test]
+  fn test_normalize_path_keeps_the_existing_parent_directories() {
+    let nfc_path = "/caf\u{e9}/r\u{e9}sum\u{e9}/\u{e9}t\u{e9}.txt";
+    let nfd_path = "/cafe\u{301}/re\u{301}sume\u{301}/e\u{301}te\u{301}.txt";
+    assert_eq!(
+      "/caf\u{e9}/re\u{301}sume\u{301}/e\u{301}te\u{301}.txt",
+      normalize_path("/caf\u{e9}/a.txt", nfc_path, protocol::UnicodeNormalization::Nfd)
+    );
+    assert_eq!(
+      "/cafe\u{301}/r\u{e9}sum\u{e9}/\u{e9}t\u{e9}.txt",
+      normalize_path("/cafe\u{301}/a.txt", nfd_path, protocol::UnicodeNormalization::Nfc)
+    );
+    // The target path outside the parent directory of the source path is normalized after the common components.
+    assert_eq!(
+      "/cafe\u{301}/r\u{e9}sum\u{e9}/\u{e9}t\u{e9}.txt",
+      normalize_path("/cafe\u{301}/x/a.txt", nfd_path, protocol::UnicodeNormalization::Nfc)
+    );
+    assert_eq!(
+      nfd_path,
+      normalize_path(
+        "/cafe\u{301}/re\u{301}sume\u{301}/a.txt",
+        nfd_path,
+        protocol::UnicodeNormalization::Nfd
+      )
+    );
+  }
+
+  #[test]
+  fn test_find_normalization_duplicates() {
+    let items = vec![
+      new_item("/a/1.txt", "/a/caf\u{e9}.txt"),
+      new_item("/a/2.txt", "/a/other.txt"),
+      new_item("/a/3.txt", "/a/cafe\u{301}.txt"),
+      new_item("/a/4.txt", "/a/same.txt"),
+      new_item("/a/5.txt", "/a/same.txt"),
+    ];
+    let groups = find_normalization_duplicates(&items);
+    assert_eq!(1, groups.len());
+    assert_eq!("/a/caf\u{e9}.txt", groups[0].path);
+    assert_eq!(
+      vec!["/a/1.txt", "/a/3.txt"],
+      groups[0]
+        .items
+        .iter()
+        .map(|item| item.source_path.as_str())
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_transliterate() {
+    assert_eq!("Aeroskobing", transliterate("\u{c6}r\u{f8}sk\u{f8}bing"));
+    assert_eq!("BeiJing", transliterate("\u{5317}\u{4eac}"));
+    assert_eq!("hiragana", transliterate("\u{3072}\u{3089}\u{304c}\u{306a}"));
+    // The path separators produced by the symbols are replaced.
+    assert_eq!("1-2 cup", transliterate("\u{bd} cup"));
+    assert_eq!("a-b-c", transliterate("a/b\\c"));
+  }
+}
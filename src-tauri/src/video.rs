@@ -0,0 +1,774 @@
+/*
+* Copyright (c) 2024-2025. caoccao.com Sam Cao
+* All rights reserved.
+
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+
+* http://www.apache.org/licenses/LICENSE-2.0
+
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*/
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::tags;
+
+pub const VIDEO_EXTENSIONS: [&str; 6] = ["avi", "m4v", "mkv", "mov", "mp4", "webm"];
+
+pub const FIELD_CODEC: &str = "codec";
+pub const FIELD_DURATION: &str = "duration";
+pub const FIELD_FPS: &str = "fps";
+pub const FIELD_HEIGHT: &str = "height";
+pub const FIELD_RESOLUTION: &str = "resolution";
+pub const FIELD_TITLE: &str = "title";
+pub const FIELD_WIDTH: &str = "width";
+
+const EBML_CLUSTER: u32 = 0x1F43B675;
+const EBML_CODEC_ID: u32 = 0x86;
+const EBML_DEFAULT_DURATION: u32 = 0x23E383;
+const EBML_DURATION: u32 = 0x4489;
+const EBML_HEADER: u32 = 0x1A45DFA3;
+const EBML_INFO: u32 = 0x1549A966;
+const EBML_PIXEL_HEIGHT: u32 = 0xBA;
+const EBML_PIXEL_WIDTH: u32 = 0xB0;
+const EBML_SEGMENT: u32 = 0x18538067;
+const EBML_TIMECODE_SCALE: u32 = 0x2AD7B1;
+const EBML_TITLE: u32 = 0x7BA9;
+const EBML_TRACK_ENTRY: u32 = 0xAE;
+const EBML_TRACK_TYPE: u32 = 0x83;
+const EBML_TRACKS: u32 = 0x1654AE6B;
+const EBML_VIDEO: u32 = 0xE0;
+const MAX_BLOCK_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The properties of the first video stream.
+#[derive(Default)]
+struct VideoInfo {
+  codec: Option<String>,
+  duration: Option<f64>,
+  fps: Option<f64>,
+  height: Option<u64>,
+  title: Option<String>,
+  width: Option<u64>,
+}
+
+impl VideoInfo {
+  fn into_fields(self) -> BTreeMap<String, String> {
+    let mut fields: BTreeMap<String, String> = BTreeMap::new();
+    if let Some(codec) = self.codec.filter(|codec| !codec.is_empty()) {
+      fields.insert(FIELD_CODEC.to_string(), codec);
+    }
+    if let Some(duration) = self.duration.filter(|duration| duration.is_finite() && *duration > 0.0) {
+      fields.insert(FIELD_DURATION.to_string(), format!("{}", duration.round() as u64));
+    }
+    if let Some(fps) = self.fps.filter(|fps| fps.is_finite() && *fps > 0.0) {
+      // 23.976023976 is formatted as "23.976" and 25.0 is formatted as "25".
+      let fps = format!("{:.3}", fps);
+      let fps = fps.trim_end_matches('0').trim_end_matches('.');
+      fields.insert(FIELD_FPS.to_string(), fps.to_string());
+    }
+    if let (Some(width), Some(height)) = (self.width, self.height) {
+      if width > 0 && height > 0 {
+        fields.insert(FIELD_HEIGHT.to_string(), height.to_string());
+        fields.insert(FIELD_RESOLUTION.to_string(), format!("{}x{}", width, height));
+        fields.insert(FIELD_WIDTH.to_string(), width.to_string());
+      }
+    }
+    if let Some(title) = self.title {
+      let title = title.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+      if !title.is_empty() {
+        fields.insert(FIELD_TITLE.to_string(), title.to_string());
+      }
+    }
+    fields
+  }
+}
+
+fn get_avi_codec(fourcc: &[u8]) -> String {
+  let fourcc = String::from_utf8_lossy(fourcc)
+    .trim_matches(|c: char| c == '\0' || c == ' ')
+    .to_uppercase();
+  match fourcc.as_str() {
+    "AVC1" | "H264" | "X264" => "h264".to_string(),
+    "H265" | "HEVC" | "HVC1" => "hevc".to_string(),
+    "DIVX" | "DX50" | "FMP4" | "MP4V" | "XVID" => "mpeg4".to_string(),
+    "MJPG" => "mjpeg".to_string(),
+    _ => fourcc.to_lowercase(),
+  }
+}
+
+fn get_matroska_codec(codec_id: &str) -> String {
+  match codec_id {
+    "V_AV1" => "av1".to_string(),
+    "V_MPEG2" => "mpeg2".to_string(),
+    "V_MPEG4/ISO/AVC" => "h264".to_string(),
+    "V_MPEG4/ISO/ASP" | "V_MPEG4/ISO/SP" | "V_MPEG4/ISO/AP" => "mpeg4".to_string(),
+    "V_MPEGH/ISO/HEVC" => "hevc".to_string(),
+    "V_VP8" => "vp8".to_string(),
+    "V_VP9" => "vp9".to_string(),
+    _ => codec_id.trim_start_matches("V_").to_lowercase(),
+  }
+}
+
+fn get_mp4_codec(fourcc: &[u8]) -> String {
+  match fourcc {
+    b"av01" => "av1".to_string(),
+    b"avc1" | b"avc3" => "h264".to_string(),
+    b"hev1" | b"hvc1" => "hevc".to_string(),
+    b"mp4v" => "mpeg4".to_string(),
+    b"vp08" => "vp8".to_string(),
+    b"vp09" => "vp9".to_string(),
+    _ => String::from_utf8_lossy(fourcc).trim().to_lowercase(),
+  }
+}
+
+pub fn is_video_file(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| VIDEO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn read_avi<R: Read + Seek>(reader: &mut R, info: &mut VideoInfo) -> Result<()> {
+  let mut header = [0u8; 12];
+  reader.read_exact(&mut header)?;
+  if &header[0..4] != b"RIFF" || &header[8..12] != b"AVI " {
+    return Err(anyhow::anyhow!("AVI signature is not found."));
+  }
+  let end = 8 + u32::from_le_bytes(header[4..8].try_into()?) as u64;
+  read_avi_list(reader, 12, end, info)
+}
+
+/// Reads the chunks in a RIFF list. Only hdrl, strl and INFO lists are entered.
+fn read_avi_list<R: Read + Seek>(reader: &mut R, start: u64, end: u64, info: &mut VideoInfo) -> Result<()> {
+  let mut position = start;
+  let mut is_video_stream = false;
+  while position + 8 <= end {
+    reader.seek(SeekFrom::Start(position))?;
+    let mut header = [0u8; 8];
+    if reader.read_exact(&mut header).is_err() {
+      break;
+    }
+    let size = u32::from_le_bytes(header[4..8].try_into()?) as u64;
+    let body_start = position + 8;
+    // Chunks are padded to an even size.
+    position = body_start + size + (size & 1);
+    match &header[0..4] {
+      b"LIST" => {
+        let mut list_type = [0u8; 4];
+        reader.read_exact(&mut list_type)?;
+        if matches!(&list_type, b"hdrl" | b"strl" | b"INFO") {
+          read_avi_list(reader, body_start + 4, (body_start + size).min(end), info)?;
+        }
+      }
+      b"avih" | b"strh" | b"strf" | b"INAM" if size <= MAX_BLOCK_SIZE => {
+        let mut body = vec![0u8; size as usize];
+        reader.read_exact(&mut body)?;
+        let read_u32 = |offset: usize| -> Option<u32> {
+          body
+            .get(offset..(offset + 4))
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        match &header[0..4] {
+          b"avih" => {
+            if let (Some(micro_seconds_per_frame), Some(total_frames)) = (read_u32(0), read_u32(16)) {
+              info.duration = Some(micro_seconds_per_frame as f64 * total_frames as f64 / 1_000_000.0);
+            }
+            info.width = info.width.or(read_u32(32).map(|width| width as u64));
+            info.height = info.height.or(read_u32(36).map(|height| height as u64));
+          }
+          b"strh" => {
+            is_video_stream = body.starts_with(b"vids") && info.fps.is_none();
+            if is_video_stream {
+              if let (Some(scale), Some(rate)) = (read_u32(20), read_u32(24)) {
+                if scale > 0 {
+                  info.fps = Some(rate as f64 / scale as f64);
+                }
+              }
+            }
+          }
+          b"strf" => {
+            if is_video_stream && body.len() >= 20 {
+              info.codec = Some(get_avi_codec(&body[16..20]));
+              is_video_stream = false;
+            }
+          }
+          _ => {
+            info.title = Some(String::from_utf8_lossy(&body).to_string());
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+/// Reads the element ID with the length marker kept.
+fn read_ebml_id<R: Read>(reader: &mut R) -> Result<(u32, u64)> {
+  let mut first = [0u8; 1];
+  reader.read_exact(&mut first)?;
+  let length = first[0].leading_zeros() as usize + 1;
+  if length > 4 {
+    return Err(anyhow::anyhow!("EBML element ID is invalid."));
+  }
+  let mut id = first[0] as u32;
+  for _ in 1..length {
+    reader.read_exact(&mut first)?;
+    id = (id << 8) | first[0] as u32;
+  }
+  Ok((id, length as u64))
+}
+
+/// Reads the element size. An unknown size is returned as None.
+fn read_ebml_size<R: Read>(reader: &mut R) -> Result<(Option<u64>, u64)> {
+  let mut first = [0u8; 1];
+  reader.read_exact(&mut first)?;
+  let length = first[0].leading_zeros() as usize + 1;
+  if length > 8 {
+    return Err(anyhow::anyhow!("EBML element size is invalid."));
+  }
+  let mut size = (first[0] as u64) & (0xFF >> length);
+  let mut is_unknown = size == (0xFF >> length);
+  for _ in 1..length {
+    reader.read_exact(&mut first)?;
+    size = (size << 8) | first[0] as u64;
+    is_unknown = is_unknown && first[0] == 0xFF;
+  }
+  Ok((if is_unknown { None } else { Some(size) }, length as u64))
+}
+
+fn read_ebml_float(data: &[u8]) -> Option<f64> {
+  match data.len() {
+    4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+    8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+    _ => None,
+  }
+}
+
+fn read_ebml_uint(data: &[u8]) -> u64 {
+  data.iter().fold(0, |value, &b| (value << 8) | b as u64)
+}
+
+/// Parses the children of an element that has been read into memory.
+fn read_ebml_children(data: &[u8]) -> Vec<(u32, &[u8])> {
+  let mut children: Vec<(u32, &[u8])> = Vec::new();
+  let mut cursor = std::io::Cursor::new(data);
+  while (cursor.position() as usize) < data.len() {
+    let id = match read_ebml_id(&mut cursor) {
+      Ok((id, _)) => id,
+      Err(_) => break,
+    };
+    let size = match read_ebml_size(&mut cursor) {
+      Ok((Some(size), _)) => size as usize,
+      _ => break,
+    };
+    let start = cursor.position() as usize;
+    match data.get(start..(start + size)) {
+      Some(body) => children.push((id, body)),
+      None => break,
+    }
+    cursor.set_position((start + size) as u64);
+  }
+  children
+}
+
+fn read_matroska<R: Read + Seek>(reader: &mut R, info: &mut VideoInfo) -> Result<()> {
+  let file_size = reader.seek(SeekFrom::End(0))?;
+  reader.seek(SeekFrom::Start(0))?;
+  let (id, _) = read_ebml_id(reader)?;
+  if id != EBML_HEADER {
+    return Err(anyhow::anyhow!("EBML header is not found."));
+  }
+  let (size, _) = read_ebml_size(reader)?;
+  reader.seek(SeekFrom::Current(size.unwrap_or_default() as i64))?;
+  let (id, _) = read_ebml_id(reader)?;
+  if id != EBML_SEGMENT {
+    return Err(anyhow::anyhow!("Matroska segment is not found."));
+  }
+  let (size, _) = read_ebml_size(reader)?;
+  let segment_end = match size {
+    Some(size) => (reader.stream_position()? + size).min(file_size),
+    None => file_size,
+  };
+  let mut timecode_scale = 1_000_000.0;
+  let mut duration: Option<f64> = None;
+  let mut has_info = false;
+  let mut has_tracks = false;
+  while reader.stream_position()? < segment_end && !(has_info && has_tracks) {
+    let (id, _) = read_ebml_id(reader)?;
+    let (size, _) = read_ebml_size(reader)?;
+    // The clusters hold the media data, so the metadata before them is enough.
+    if id == EBML_CLUSTER {
+      break;
+    }
+    let size = size.ok_or_else(|| anyhow::anyhow!("Matroska element {:X} has an unknown size.", id))?;
+    if (id == EBML_INFO || id == EBML_TRACKS) && size <= MAX_BLOCK_SIZE {
+      let mut body = vec![0u8; size as usize];
+      reader.read_exact(&mut body)?;
+      if id == EBML_INFO {
+        has_info = true;
+        for (child_id, child) in read_ebml_children(&body) {
+          match child_id {
+            EBML_DURATION => duration = read_ebml_float(child),
+            EBML_TIMECODE_SCALE => timecode_scale = read_ebml_uint(child) as f64,
+            EBML_TITLE => info.title = Some(String::from_utf8_lossy(child).to_string()),
+            _ => {}
+          }
+        }
+      } else {
+        has_tracks = true;
+        read_matroska_tracks(&body, info);
+      }
+    } else {
+      reader.seek(SeekFrom::Current(size as i64))?;
+    }
+  }
+  info.duration = duration.map(|duration| duration * timecode_scale / 1_000_000_000.0);
+  Ok(())
+}
+
+fn read_matroska_tracks(data: &[u8], info: &mut VideoInfo) {
+  for (_, track) in read_ebml_children(data)
+    .into_iter()
+    .filter(|(id, _)| *id == EBML_TRACK_ENTRY)
+  {
+    let children = read_ebml_children(track);
+    let is_video = children
+      .iter()
+      .any(|(id, body)| *id == EBML_TRACK_TYPE && read_ebml_uint(body) == 1);
+    if !is_video {
+      continue;
+    }
+    for (id, body) in children {
+      match id {
+        EBML_CODEC_ID => {
+          let codec_id = String::from_utf8_lossy(body).trim_end_matches('\0').to_string();
+          info.codec = Some(get_matroska_codec(codec_id.as_str()));
+        }
+        EBML_DEFAULT_DURATION => {
+          let nano_seconds = read_ebml_uint(body);
+          if nano_seconds > 0 {
+            info.fps = Some(1_000_000_000.0 / nano_seconds as f64);
+          }
+        }
+        EBML_VIDEO => {
+          for (video_id, video_body) in read_ebml_children(body) {
+            match video_id {
+              EBML_PIXEL_HEIGHT => info.height = Some(read_ebml_uint(video_body)),
+              EBML_PIXEL_WIDTH => info.width = Some(read_ebml_uint(video_body)),
+              _ => {}
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+    break;
+  }
+}
+
+fn read_mp4<R: Read + Seek>(reader: &mut R, info: &mut VideoInfo) -> Result<()> {
+  let file_size = reader.seek(SeekFrom::End(0))?;
+  let (start, end) =
+    tags::read_mp4_atom(reader, (0, file_size), b"moov")?.ok_or_else(|| anyhow::anyhow!("Atom moov is not found."))?;
+  if end - start > MAX_BLOCK_SIZE {
+    return Err(anyhow::anyhow!("Atom moov is too large."));
+  }
+  reader.seek(SeekFrom::Start(start))?;
+  let mut moov = vec![0u8; (end - start) as usize];
+  reader.read_exact(&mut moov)?;
+  let find = |data: &'_ [u8], atom_type: &[u8; 4]| -> Option<(usize, usize)> {
+    tags::parse_mp4_atoms(data)
+      .ok()?
+      .into_iter()
+      .find(|atom| &atom.atom_type == atom_type)
+      .map(|atom| (atom.body_start, atom.end))
+  };
+  let read_u32 = |data: &[u8], offset: usize| -> Option<u64> {
+    data
+      .get(offset..(offset + 4))
+      .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+  };
+  let read_u64 = |data: &[u8], offset: usize| -> Option<u64> {
+    data
+      .get(offset..(offset + 8))
+      .and_then(|bytes| bytes.try_into().ok())
+      .map(u64::from_be_bytes)
+  };
+  // The version of mvhd and mdhd decides the size of the time fields.
+  let read_time = |data: &[u8]| -> Option<(u64, u64)> {
+    match data.first()? {
+      0 => Some((read_u32(data, 12)?, read_u32(data, 16)?)),
+      _ => Some((read_u32(data, 20)?, read_u64(data, 24)?)),
+    }
+  };
+  if let Some((mvhd_start, mvhd_end)) = find(&moov, b"mvhd") {
+    if let Some((timescale, duration)) = read_time(&moov[mvhd_start..mvhd_end]) {
+      if timescale > 0 {
+        info.duration = Some(duration as f64 / timescale as f64);
+      }
+    }
+  }
+  for atom in tags::parse_mp4_atoms(&moov)?
+    .into_iter()
+    .filter(|atom| &atom.atom_type == b"trak")
+  {
+    let trak = &moov[atom.body_start..atom.end];
+    let mdia = match find(trak, b"mdia") {
+      Some((start, end)) => &trak[start..end],
+      None => continue,
+    };
+    // The handler type follows 4 bytes of version and flags and 4 bytes of pre-defined.
+    let is_video = find(mdia, b"hdlr").is_some_and(|(start, end)| mdia[start..end].get(8..12) == Some(b"vide"));
+    if !is_video {
+      continue;
+    }
+    if let Some((start, end)) = find(trak, b"tkhd") {
+      let tkhd = &trak[start..end];
+      let offset = if tkhd.first() == Some(&1) { 88 } else { 76 };
+      // The width and the height are 16.16 fixed point numbers.
+      info.width = read_u32(tkhd, offset).map(|width| width >> 16);
+      info.height = read_u32(tkhd, offset + 4).map(|height| height >> 16);
+    }
+    let stbl = find(mdia, b"minf")
+      .map(|(start, end)| &mdia[start..end])
+      .and_then(|minf| find(minf, b"stbl").map(|(start, end)| &minf[start..end]));
+    if let Some(stbl) = stbl {
+      // The first sample entry follows 4 bytes of version and flags and 4 bytes of entry count.
+      if let Some((start, end)) = find(stbl, b"stsd") {
+        let stsd = &stbl[start..end];
+        if let Some(fourcc) = stsd.get(12..16) {
+          info.codec = Some(get_mp4_codec(fourcc));
+        }
+        if info.width.unwrap_or_default() == 0 {
+          info.width = stsd
+            .get(40..42)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as u64);
+          info.height = stsd
+            .get(42..44)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as u64);
+        }
+      }
+      let sample_count = find(stbl, b"stts").map(|(start, end)| {
+        let stts = &stbl[start..end];
+        let entry_count = read_u32(stts, 4).unwrap_or_default() as usize;
+        (0..entry_count)
+          .map_while(|index| read_u32(stts, 8 + index * 8))
+          .sum::<u64>()
+      });
+      let media_time = find(mdia, b"mdhd").and_then(|(start, end)| read_time(&mdia[start..end]));
+      if let (Some(sample_count), Some((timescale, duration))) = (sample_count, media_time) {
+        if duration > 0 {
+          info.fps = Some(sample_count as f64 * timescale as f64 / duration as f64);
+        }
+      }
+    }
+    break;
+  }
+  let ilst = find(&moov, b"udta")
+    .map(|(start, end)| &moov[start..end])
+    .and_then(|udta| find(udta, b"meta").map(|(start, end)| &udta[start..end]))
+    .and_then(|meta| meta.get(4..))
+    .and_then(|meta| find(meta, b"ilst").map(|(start, end)| &meta[start..end]));
+  if let Some(ilst) = ilst {
+    if let Some((start, end)) = find(ilst, b"\xA9nam") {
+      // The data atom starts with 4 bytes of type and 4 bytes of locale.
+      if let Some((data_start, data_end)) = find(&ilst[start..end], b"data") {
+        if let Some(title) = ilst[start..end][data_start..data_end].get(8..) {
+          info.title = Some(String::from_utf8_lossy(title).to_string());
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Reads the duration in seconds, the resolution, the codec and the frame rate of the first video stream
+/// and the title of the container.
+pub fn read_video_metadata(path: &Path) -> Result<BTreeMap<String, String>> {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension.to_lowercase())
+    .unwrap_or_default();
+  let mut reader = BufReader::new(File::open(path).map_err(anyhow::Error::msg)?);
+  let mut info = VideoInfo::default();
+  match extension.as_str() {
+    "avi" => read_avi(&mut reader, &mut info)?,
+    "mkv" | "webm" => read_matroska(&mut reader, &mut info)?,
+    _ => read_mp4(&mut reader, &mut info)?,
+  }
+  Ok(info.into_fields())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  fn new_atom(atom_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(atom_type);
+    data.extend_from_slice(body);
+    data
+  }
+
+  fn new_chunk(chunk_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut data = chunk_type.to_vec();
+    data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    data.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+      data.push(0);
+    }
+    data
+  }
+
+  fn new_list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = list_type.to_vec();
+    chunks.iter().for_each(|chunk| body.extend_from_slice(chunk));
+    new_chunk(b"LIST", &body)
+  }
+
+  /// Creates an EBML element with the ID bytes and an 8 byte size.
+  fn new_element(id: u32, body: &[u8]) -> Vec<u8> {
+    let mut data: Vec<u8> = id.to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+    data.push(0x01);
+    data.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+    data.extend_from_slice(body);
+    data
+  }
+
+  fn new_elements(id: u32, elements: &[Vec<u8>]) -> Vec<u8> {
+    new_element(id, &elements.concat())
+  }
+
+  /// Creates a 640x360 AVI of 1800 frames at 29.97 fps with an audio stream before the video stream.
+  fn new_avi() -> Vec<u8> {
+    let mut avih = vec![0u8; 56];
+    avih[0..4].copy_from_slice(&33367u32.to_le_bytes());
+    avih[16..20].copy_from_slice(&1800u32.to_le_bytes());
+    avih[32..36].copy_from_slice(&640u32.to_le_bytes());
+    avih[36..40].copy_from_slice(&360u32.to_le_bytes());
+    let new_strh = |stream_type: &[u8; 4], scale: u32, rate: u32| -> Vec<u8> {
+      let mut strh = vec![0u8; 56];
+      strh[0..4].copy_from_slice(stream_type);
+      strh[20..24].copy_from_slice(&scale.to_le_bytes());
+      strh[24..28].copy_from_slice(&rate.to_le_bytes());
+      new_chunk(b"strh", &strh)
+    };
+    let mut strf = vec![0u8; 40];
+    strf[16..20].copy_from_slice(b"XVID");
+    let hdrl = new_list(
+      b"hdrl",
+      &[
+        new_chunk(b"avih", &avih),
+        new_list(b"strl", &[new_strh(b"auds", 1, 44100), new_chunk(b"strf", &[0u8; 18])]),
+        new_list(b"strl", &[new_strh(b"vids", 1001, 30000), new_chunk(b"strf", &strf)]),
+      ],
+    );
+    let info = new_list(b"INFO", &[new_chunk(b"INAM", b"Movie\0")]);
+    let movi = new_list(b"movi", &[new_chunk(b"00dc", &[0u8; 16])]);
+    let mut body = b"AVI ".to_vec();
+    body.extend(hdrl);
+    body.extend(info);
+    body.extend(movi);
+    let mut data = b"RIFF".to_vec();
+    data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    data.extend(body);
+    data
+  }
+
+  /// Creates a 1920x1080 Matroska of 125.5 seconds at 23.976 fps with an audio track before the video track.
+  fn new_matroska() -> Vec<u8> {
+    let info = new_elements(
+      EBML_INFO,
+      &[
+        new_element(EBML_TIMECODE_SCALE, &[0x0F, 0x42, 0x40]),
+        new_element(EBML_DURATION, &125500.0f64.to_be_bytes()),
+        new_element(EBML_TITLE, b"Movie"),
+      ],
+    );
+    let audio_track = new_elements(
+      EBML_TRACK_ENTRY,
+      &[new_element(EBML_TRACK_TYPE, &[2]), new_element(EBML_CODEC_ID, b"A_AAC")],
+    );
+    let video_track = new_elements(
+      EBML_TRACK_ENTRY,
+      &[
+        new_element(EBML_TRACK_TYPE, &[1]),
+        new_element(EBML_CODEC_ID, b"V_MPEG4/ISO/AVC"),
+        new_element(EBML_DEFAULT_DURATION, &41708333u32.to_be_bytes()),
+        new_elements(
+          EBML_VIDEO,
+          &[
+            new_element(EBML_PIXEL_WIDTH, &1920u16.to_be_bytes()),
+            new_element(EBML_PIXEL_HEIGHT, &1080u16.to_be_bytes()),
+          ],
+        ),
+      ],
+    );
+    let tracks = new_elements(EBML_TRACKS, &[audio_track, video_track]);
+    let cluster = new_element(EBML_CLUSTER, &[0u8; 16]);
+    let mut data = new_element(EBML_HEADER, &new_element(0x4282, b"matroska"));
+    data.extend(new_elements(EBML_SEGMENT, &[info, tracks, cluster]));
+    data
+  }
+
+  /// Creates an MP4 of 90.5 seconds with 2700 samples in 90 seconds of media time
+  /// and a sound track before the video track.
+  fn new_mp4(tkhd_width: u32, tkhd_height: u32) -> Vec<u8> {
+    let mut mvhd = vec![0u8; 100];
+    mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+    mvhd[16..20].copy_from_slice(&90500u32.to_be_bytes());
+    let new_hdlr = |handler_type: &[u8; 4]| -> Vec<u8> {
+      let mut hdlr = vec![0u8; 24];
+      hdlr[8..12].copy_from_slice(handler_type);
+      new_atom(b"hdlr", &hdlr)
+    };
+    let sound_trak = new_atom(b"trak", &new_atom(b"mdia", &new_hdlr(b"soun")));
+    let mut tkhd = vec![0u8; 84];
+    tkhd[76..80].copy_from_slice(&(tkhd_width << 16).to_be_bytes());
+    tkhd[80..84].copy_from_slice(&(tkhd_height << 16).to_be_bytes());
+    let mut mdhd = vec![0u8; 24];
+    mdhd[12..16].copy_from_slice(&12800u32.to_be_bytes());
+    mdhd[16..20].copy_from_slice(&1152000u32.to_be_bytes());
+    let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 86];
+    stsd.extend_from_slice(b"hvc1");
+    stsd.resize(stsd.len() + 24, 0);
+    stsd.extend_from_slice(&1280u16.to_be_bytes());
+    stsd.extend_from_slice(&720u16.to_be_bytes());
+    stsd.resize(8 + 86, 0);
+    let mut stts = vec![0, 0, 0, 0, 0, 0, 0, 2];
+    for (count, delta) in [(2000u32, 512u32), (700, 183)] {
+      stts.extend_from_slice(&count.to_be_bytes());
+      stts.extend_from_slice(&delta.to_be_bytes());
+    }
+    let mut stbl = new_atom(b"stsd", &stsd);
+    stbl.extend(new_atom(b"stts", &stts));
+    let mut mdia = new_atom(b"mdhd", &mdhd);
+    mdia.extend(new_hdlr(b"vide"));
+    mdia.extend(new_atom(b"minf", &new_atom(b"stbl", &stbl)));
+    let mut video_trak = new_atom(b"tkhd", &tkhd);
+    video_trak.extend(new_atom(b"mdia", &mdia));
+    let mut data = vec![0u8; 8];
+    data.extend_from_slice(b"Movie");
+    let mut meta = vec![0u8; 4];
+    meta.extend(new_atom(b"ilst", &new_atom(b"\xA9nam", &new_atom(b"data", &data))));
+    let mut moov = new_atom(b"mvhd", &mvhd);
+    moov.extend(sound_trak);
+    moov.extend(new_atom(b"trak", &video_trak));
+    moov.extend(new_atom(b"udta", &new_atom(b"meta", &meta)));
+    let mut data = new_atom(b"ftyp", b"isom\0\0\0\0isom");
+    data.extend(new_atom(b"moov", &moov));
+    data.extend(new_atom(b"mdat", &[0u8; 16]));
+    data
+  }
+
+  fn new_fields(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+      .iter()
+      .map(|(name, value)| (name.to_string(), value.to_string()))
+      .collect()
+  }
+
+  fn read_fields<F>(read: F, data: &[u8]) -> Result<BTreeMap<String, String>>
+  where
+    F: Fn(&mut Cursor<Vec<u8>>, &mut VideoInfo) -> Result<()>,
+  {
+    let mut info = VideoInfo::default();
+    read(&mut Cursor::new(data.to_vec()), &mut info)?;
+    Ok(info.into_fields())
+  }
+
+  #[test]
+  fn test_read_avi() {
+    assert_eq!(
+      new_fields(&[
+        (FIELD_CODEC, "mpeg4"),
+        (FIELD_DURATION, "60"),
+        (FIELD_FPS, "29.97"),
+        (FIELD_HEIGHT, "360"),
+        (FIELD_RESOLUTION, "640x360"),
+        (FIELD_TITLE, "Movie"),
+        (FIELD_WIDTH, "640"),
+      ]),
+      read_fields(read_avi, &new_avi()).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_read_matroska() {
+    assert_eq!(
+      new_fields(&[
+        (FIELD_CODEC, "h264"),
+        (FIELD_DURATION, "126"),
+        (FIELD_FPS, "23.976"),
+        (FIELD_HEIGHT, "1080"),
+        (FIELD_RESOLUTION, "1920x1080"),
+        (FIELD_TITLE, "Movie"),
+        (FIELD_WIDTH, "1920"),
+      ]),
+      read_fields(read_matroska, &new_matroska()).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_read_mp4() {
+    let mut fields = new_fields(&[
+      (FIELD_CODEC, "hevc"),
+      (FIELD_DURATION, "91"),
+      (FIELD_FPS, "30"),
+      (FIELD_HEIGHT, "1080"),
+      (FIELD_RESOLUTION, "1920x1080"),
+      (FIELD_TITLE, "Movie"),
+      (FIELD_WIDTH, "1920"),
+    ]);
+    assert_eq!(fields, read_fields(read_mp4, &new_mp4(1920, 1080)).unwrap());
+    // The resolution falls back to the sample entry without the track header.
+    fields.extend(new_fields(&[
+      (FIELD_HEIGHT, "720"),
+      (FIELD_RESOLUTION, "1280x720"),
+      (FIELD_WIDTH, "1280"),
+    ]));
+    assert_eq!(fields, read_fields(read_mp4, &new_mp4(0, 0)).unwrap());
+  }
+
+  #[test]
+  fn test_read_truncated_videos() {
+    let data = new_avi();
+    assert_eq!(
+      "failed to fill whole buffer",
+      read_fields(read_avi, &data[..50]).unwrap_err().to_string()
+    );
+    // The chunks after the main header are cut off, so only the main header is read.
+    assert_eq!(
+      new_fields(&[
+        (FIELD_DURATION, "60"),
+        (FIELD_HEIGHT, "360"),
+        (FIELD_RESOLUTION, "640x360"),
+        (FIELD_WIDTH, "640"),
+      ]),
+      read_fields(read_avi, &data[..100]).unwrap()
+    );
+    let data = new_matroska();
+    assert_eq!(
+      "failed to fill whole buffer",
+      read_fields(read_matroska, &data[..60]).unwrap_err().to_string()
+    );
+    let data = new_mp4(1920, 1080);
+    assert_eq!(
+      "Atom moov is invalid.",
+      read_fields(read_mp4, &data[..200]).unwrap_err().to_string()
+    );
+  }
+}
@@ -22,7 +22,7 @@ import "@fontsource/roboto/700.css";
 
 import { getCurrentWindow, type DragDropEvent } from "@tauri-apps/api/window";
 import { invoke } from "@tauri-apps/api/core";
-import type { Event, UnlistenFn } from "@tauri-apps/api/event";
+import { listen, type Event, type UnlistenFn } from "@tauri-apps/api/event";
 
 import React from "react";
 import { Box, Tab, Tabs } from "@mui/material";
@@ -33,6 +33,9 @@ import {
   ItemType,
   Notification,
   NotificationType,
+  RenameOptions,
+  RenameResult,
+  ScanOptions,
 } from "./lib/Protocol";
 
 import Dashboard from "./Dashboard";
@@ -53,10 +56,15 @@ function App() {
   const [notification, setNotification] = React.useState<Notification | null>(
     null
   );
+  const [pruneDirectories, setPruneDirectories] = React.useState(false);
+  const [roots, setRoots] = React.useState<string[]>([]);
   const [tabIndex, setTabIndex] = React.useState(0);
+  const [trashedPaths, setTrashedPaths] = React.useState<string[]>([]);
+  const [truncate, setTruncate] = React.useState(false);
 
   function clear() {
     setItems([]);
+    setRoots([]);
     setTabIndex(0);
   }
 
@@ -142,10 +150,33 @@ function App() {
   );
 
   const handleRename = React.useCallback(() => {
-    invoke<number>("rename_items", { items })
+    const options: RenameOptions = {
+      normalization: config?.normalization ?? null,
+      profile: null,
+      pruneDirectories,
+      roots,
+      truncate,
+    };
+    invoke<RenameResult>("rename_items", { items, options })
       .then((value) => {
+        setTrashedPaths(value.trashedPaths);
+        if (value.error) {
+          setNotification({
+            message: `${value.error} after renaming ${value.count} item(s) and trashing ${value.trashedPaths.length} item(s)`,
+            type: NotificationType.Error,
+          });
+          return;
+        }
         setNotification({
-          message: `Renamed ${value} item(s) successfully`,
+          message: `Renamed ${value.count} item(s)${
+            value.trashedPaths.length > 0
+              ? `, trashed ${value.trashedPaths.length} item(s)`
+              : ""
+          }${
+            value.removedDirectories.length > 0
+              ? ` and removed ${value.removedDirectories.length} empty directories`
+              : ""
+          } successfully`,
           type: NotificationType.Success,
         });
       })
@@ -155,7 +186,7 @@ function App() {
           type: NotificationType.Error,
         });
       });
-  }, [items, notification]);
+  }, [config, items, notification, pruneDirectories, roots, truncate]);
 
   const onChangeTabIndex = (
     _event: React.SyntheticEvent,
@@ -165,6 +196,7 @@ function App() {
   };
 
   React.useEffect(() => {
+    let cancelExternalPluginsChanged: UnlistenFn | null = null;
     let cancelFileDrop: UnlistenFn | null = null;
     getCurrentWindow()
       .onDragDropEvent((event: Event<DragDropEvent>) => {
@@ -174,14 +206,21 @@ function App() {
             sourcePath: path,
             targetPath: path,
             type: ItemType.Unknown,
+            metadata: {},
+            companions: [],
           }));
           setItems(newItems);
-          invoke<Item[]>("scan_items", {
-            items: newItems,
+          setRoots(paths);
+          const options: ScanOptions = {
             depth: 0,
-            includeDirectory: true,
             extensions: [],
-          })
+            groupCompanions: false,
+            hashes: [],
+            includeDirectory: true,
+            metadata: [],
+            normalization: null,
+          };
+          invoke<Item[]>("scan_items", { items: newItems, options })
             .then((value) => {
               setItems(value);
             })
@@ -196,6 +235,20 @@ function App() {
       .then((value) => {
         cancelFileDrop = value;
       });
+    listen("external-plugins-changed", () => {
+      invoke<Config>("get_config")
+        .then((result) => {
+          setConfig(result);
+        })
+        .catch((error) => {
+          setNotification({
+            message: `${error}`,
+            type: NotificationType.Error,
+          });
+        });
+    }).then((value) => {
+      cancelExternalPluginsChanged = value;
+    });
     if (config === null) {
       invoke<Config>("get_config")
         .then((result) => {
@@ -210,6 +263,9 @@ function App() {
     }
     document.addEventListener("keydown", handleGlobalKeyboardShortcuts);
     return () => {
+      if (cancelExternalPluginsChanged) {
+        cancelExternalPluginsChanged();
+      }
       if (cancelFileDrop) {
         cancelFileDrop();
       }
@@ -222,8 +278,16 @@ function App() {
       <Tools
         clear={clear}
         items={items}
+        normalization={config?.normalization ?? null}
+        pruneDirectories={pruneDirectories}
+        roots={roots}
         setItems={setItems}
         setNotification={setNotification}
+        setPruneDirectories={setPruneDirectories}
+        setTrashedPaths={setTrashedPaths}
+        setTruncate={setTruncate}
+        trashedPaths={trashedPaths}
+        truncate={truncate}
       />
       <Box sx={{ borderBottom: 1, borderColor: "divider", mb: "5px" }}>
         <Tabs
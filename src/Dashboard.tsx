@@ -16,10 +16,12 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type Event } from "@tauri-apps/api/event";
 
 import React from "react";
 
 import {
+  DeleteOutlined as DeleteOutlinedIcon,
   DescriptionOutlined as DescriptionOutlinedIcon,
   DocumentScannerOutlined as DocumentScannerOutlinedIcon,
   HighlightOffOutlined as HighlightOffOutlinedIcon,
@@ -30,9 +32,15 @@ import {
 import {
   Button,
   Checkbox,
+  FormControl,
   FormControlLabel,
   IconButton,
+  InputLabel,
+  LinearProgress,
+  MenuItem,
   Paper,
+  Select,
+  SelectChangeEvent,
   Stack,
   Table,
   TableBody,
@@ -45,7 +53,17 @@ import {
   Typography,
 } from "@mui/material";
 
-import { Config, Item, Notification, NotificationType } from "./lib/Protocol";
+import {
+  Config,
+  HashAlgorithm,
+  HashProgress,
+  Item,
+  MetadataType,
+  Notification,
+  NotificationType,
+  ScanOptions,
+  UnicodeNormalization,
+} from "./lib/Protocol";
 import ItemTypeIcon from "./ItemTypeIcon";
 
 export interface Args {
@@ -58,9 +76,22 @@ export interface Args {
 function Dashboard(args: Args) {
   const [depth, setDepth] = React.useState<number | null>(null);
   const [filterByExtensions, setFilterByExtensions] = React.useState(true);
+  const [groupCompanions, setGroupCompanions] = React.useState<
+    boolean | null
+  >(null);
+  const [hashes, setHashes] = React.useState<HashAlgorithm[] | null>(null);
+  const [hashProgress, setHashProgress] = React.useState<HashProgress | null>(
+    null
+  );
   const [includeDirectories, setIncludeDirectories] = React.useState<
     boolean | null
   >(null);
+  const [metadataTypes, setMetadataTypes] = React.useState<
+    MetadataType[] | null
+  >(null);
+  const [normalization, setNormalization] = React.useState<
+    UnicodeNormalization | "" | null
+  >(null);
 
   const onClickDelete = React.useCallback(
     (index: number) => {
@@ -71,15 +102,37 @@ function Dashboard(args: Args) {
     [args.items]
   );
 
+  const onClickTrash = React.useCallback(
+    (index: number) => {
+      if (index >= 0 && index < args.items.length) {
+        args.setItems(
+          args.items.map((item, i) =>
+            i === index
+              ? {
+                  ...item,
+                  targetPath: item.targetPath === "" ? item.sourcePath : "",
+                }
+              : item
+          )
+        );
+      }
+    },
+    [args.items]
+  );
+
   const onClickScan = React.useCallback(() => {
-    invoke<Item[]>("scan_items", {
-      items: args.items,
+    const options: ScanOptions = {
       depth: depth === null ? -1 : depth,
-      includeDirectory:
-        includeDirectories === null ? false : includeDirectories,
       extensions:
         filterByExtensions && args.config ? args.config.extensions : [],
-    })
+      groupCompanions: groupCompanions === null ? false : groupCompanions,
+      hashes: hashes === null ? [] : hashes,
+      includeDirectory:
+        includeDirectories === null ? false : includeDirectories,
+      metadata: metadataTypes === null ? [] : metadataTypes,
+      normalization: normalization ? normalization : null,
+    };
+    invoke<Item[]>("scan_items", { items: args.items, options })
       .then((value) => {
         args.setItems(value);
       })
@@ -88,8 +141,21 @@ function Dashboard(args: Args) {
           message: `${error}`,
           type: NotificationType.Error,
         });
+      })
+      .finally(() => {
+        setHashProgress(null);
       });
-  }, [args.items, depth, includeDirectories, filterByExtensions, args.config]);
+  }, [
+    args.items,
+    depth,
+    includeDirectories,
+    filterByExtensions,
+    groupCompanions,
+    hashes,
+    metadataTypes,
+    normalization,
+    args.config,
+  ]);
 
   function onChangeDepth(event: React.ChangeEvent<HTMLInputElement>) {
     setDepth(Number(event.target.value));
@@ -101,6 +167,21 @@ function Dashboard(args: Args) {
     setFilterByExtensions(!filterByExtensions);
   }
 
+  function onChangeGroupCompanions(
+    _event: React.ChangeEvent<HTMLInputElement>
+  ) {
+    if (groupCompanions !== null) {
+      setGroupCompanions(!groupCompanions);
+    }
+  }
+
+  function onChangeHashes(event: SelectChangeEvent<HashAlgorithm[]>) {
+    const value = event.target.value;
+    setHashes(
+      typeof value === "string" ? (value.split(",") as HashAlgorithm[]) : value
+    );
+  }
+
   function onChangeIncludeDirectories(
     _event: React.ChangeEvent<HTMLInputElement>
   ) {
@@ -109,14 +190,54 @@ function Dashboard(args: Args) {
     }
   }
 
+  function onChangeMetadataType(metadataType: MetadataType) {
+    if (metadataTypes !== null) {
+      setMetadataTypes(
+        metadataTypes.includes(metadataType)
+          ? metadataTypes.filter((value) => value !== metadataType)
+          : [...metadataTypes, metadataType]
+      );
+    }
+  }
+
+  function onChangeNormalization(
+    event: SelectChangeEvent<UnicodeNormalization | "">
+  ) {
+    setNormalization(event.target.value as UnicodeNormalization | "");
+  }
+
   React.useEffect(() => {
     if (args.config) {
+      if (groupCompanions === null) {
+        setGroupCompanions(args.config.groupCompanions);
+      }
+      if (hashes === null) {
+        setHashes(args.config.hashes);
+      }
       if (includeDirectories === null) {
         setIncludeDirectories(args.config.includeDirectories);
       }
+      if (metadataTypes === null) {
+        setMetadataTypes(args.config.metadata);
+      }
+      if (normalization === null) {
+        setNormalization(args.config.normalization ?? "");
+      }
     }
   }, [args.config]);
 
+  React.useEffect(() => {
+    const unlisten = listen(
+      "hash-progress",
+      (event: Event<HashProgress>) => {
+        setHashProgress(event.payload);
+      }
+    );
+    return () => {
+      unlisten.then((value) => value());
+    };
+  }, []);
+
   if (args.items.length > 0) {
     return (
       <React.Fragment>
@@ -145,6 +266,20 @@ function Dashboard(args: Args) {
               label="Include Directories"
             />
           </Tooltip>
+          <Tooltip
+            arrow
+            title="Attach the subtitles, nfo files and posters sharing the base name to the primary file so that they are renamed together."
+          >
+            <FormControlLabel
+              control={
+                <Checkbox
+                  checked={groupCompanions === null ? false : groupCompanions}
+                  onChange={onChangeGroupCompanions}
+                />
+              }
+              label="Group Companions"
+            />
+          </Tooltip>
           <Tooltip
             arrow
             title="Scan the directories recursively by the given depth. -1 means no limit."
@@ -180,7 +315,129 @@ function Dashboard(args: Args) {
               label="Filter by Extensions"
             />
           </Tooltip>
+          <Tooltip
+            arrow
+            title="Read the artist, album, track, title, year and disc from the audio tags."
+          >
+            <FormControlLabel
+              control={
+                <Checkbox
+                  checked={
+                    metadataTypes !== null &&
+                    metadataTypes.includes(MetadataType.Audio)
+                  }
+                  onChange={() => onChangeMetadataType(MetadataType.Audio)}
+                />
+              }
+              label="Read Audio Tags"
+            />
+          </Tooltip>
+          <Tooltip
+            arrow
+            title="Read the date, time, make, model, lens, latitude and longitude from EXIF and XMP with the modification time as the fallback date."
+          >
+            <FormControlLabel
+              control={
+                <Checkbox
+                  checked={
+                    metadataTypes !== null &&
+                    metadataTypes.includes(MetadataType.Image)
+                  }
+                  onChange={() => onChangeMetadataType(MetadataType.Image)}
+                />
+              }
+              label="Read Photo Metadata"
+            />
+          </Tooltip>
+          <Tooltip
+            arrow
+            title="Parse the mediaTitle, mediaSeason, mediaEpisode, mediaYear, mediaResolution, mediaSource, mediaCodec and mediaGroup from TV episode and movie release names."
+          >
+            <FormControlLabel
+              control={
+                <Checkbox
+                  checked={
+                    metadataTypes !== null &&
+                    metadataTypes.includes(MetadataType.Media)
+                  }
+                  onChange={() => onChangeMetadataType(MetadataType.Media)}
+                />
+              }
+              label="Parse Media Names"
+            />
+          </Tooltip>
+          <Tooltip
+            arrow
+            title="Read the duration, width, height, resolution, codec, fps and title from the video containers."
+          >
+            <FormControlLabel
+              control={
+                <Checkbox
+                  checked={
+                    metadataTypes !== null &&
+                    metadataTypes.includes(MetadataType.Video)
+                  }
+                  onChange={() => onChangeMetadataType(MetadataType.Video)}
+                />
+              }
+              label="Read Video Metadata"
+            />
+          </Tooltip>
+          <Tooltip
+            arrow
+            title="Compute the content hashes into the metadata like {crc32} and {sha256} for template renames."
+          >
+            <FormControl size="small" sx={{ minWidth: "120px" }}>
+              <InputLabel id="hashes-label">Hashes</InputLabel>
+              <Select
+                labelId="hashes-label"
+                label="Hashes"
+                multiple
+                value={hashes === null ? [] : hashes}
+                onChange={onChangeHashes}
+                renderValue={(selected) => selected.join(", ")}
+              >
+                {Object.values(HashAlgorithm).map((value) => (
+                  <MenuItem key={value} value={value}>
+                    <Checkbox
+                      size="small"
+                      checked={hashes !== null && hashes.includes(value)}
+                    />
+                    {value}
+                  </MenuItem>
+                ))}
+              </Select>
+            </FormControl>
+          </Tooltip>
+          <Tooltip
+            arrow
+            title="Normalize the Unicode file names to NFC or NFD so that the names copied from macOS compare equal."
+          >
+            <FormControl size="small" sx={{ minWidth: "140px" }}>
+              <InputLabel id="normalization-label">Normalization</InputLabel>
+              <Select
+                labelId="normalization-label"
+                label="Normalization"
+                value={normalization === null ? "" : normalization}
+                onChange={onChangeNormalization}
+              >
+                <MenuItem value="">None</MenuItem>
+                {Object.values(UnicodeNormalization).map((value) => (
+                  <MenuItem key={value} value={value}>
+                    {value}
+                  </MenuItem>
+                ))}
+              </Select>
+            </FormControl>
+          </Tooltip>
         </Stack>
+        {hashProgress !== null && hashProgress.total > 0 ? (
+          <LinearProgress
+            variant="determinate"
+            value={(hashProgress.completed * 100) / hashProgress.total}
+            sx={{ mb: "5px" }}
+          />
+        ) : null}
         <TableContainer
           component={Paper}
           sx={{ minHeight: "calc(100vh - 220px)" }}
@@ -196,6 +453,9 @@ function Dashboard(args: Args) {
                 </TableCell>
                 <TableCell>Source</TableCell>
                 <TableCell>Target</TableCell>
+                <TableCell align="center" sx={{ width: 24, maxWidth: 24 }}>
+                  <DeleteOutlinedIcon fontSize="small" />
+                </TableCell>
                 <TableCell align="center" sx={{ width: 24, maxWidth: 24 }}>
                   <HighlightOffOutlinedIcon fontSize="small" />
                 </TableCell>
@@ -208,8 +468,46 @@ function Dashboard(args: Args) {
                   <TableCell align="center">
                     <ItemTypeIcon type={item.type} />
                   </TableCell>
-                  <TableCell>{item.sourcePath}</TableCell>
-                  <TableCell>{item.targetPath}</TableCell>
+                  <TableCell>
+                    {item.sourcePath}
+                    {item.companions.length > 0 ? (
+                      <Tooltip arrow title={item.companions.join(", ")}>
+                        <Typography
+                          component="span"
+                          variant="caption"
+                          color="gray"
+                          sx={{ ml: "5px" }}
+                        >
+                          +{item.companions.length}
+                        </Typography>
+                      </Tooltip>
+                    ) : null}
+                  </TableCell>
+                  <TableCell>
+                    {item.targetPath === "" ? (
+                      <Typography variant="body2" color="gray">
+                        (Trash)
+                      </Typography>
+                    ) : (
+                      item.targetPath
+                    )}
+                  </TableCell>
+                  <TableCell align="center">
+                    <Tooltip
+                      arrow
+                      title="Send the item to the trash on rename, or keep it."
+                    >
+                      <IconButton
+                        aria-label="Trash"
+                        color={item.targetPath === "" ? "error" : "primary"}
+                        onClick={() => {
+                          onClickTrash(index);
+                        }}
+                      >
+                        <DeleteOutlinedIcon fontSize="small" />
+                      </IconButton>
+                    </Tooltip>
+                  </TableCell>
                   <TableCell align="center">
                     <IconButton
                       aria-label="Delete"
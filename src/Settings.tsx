@@ -63,6 +63,8 @@ import {
   AddBoxOutlined as AddBoxOutlinedIcon,
   AddchartOutlined as AddchartOutlinedIcon,
   AddCircleOutlineOutlined as AddCircleOutlineOutlinedIcon,
+  ArrowDownwardOutlined as ArrowDownwardOutlinedIcon,
+  ArrowUpwardOutlined as ArrowUpwardOutlinedIcon,
   DisabledByDefaultOutlined as DisabledByDefaultOutlinedIcon,
   DragIndicatorOutlined as DragIndicatorOutlinedIcon,
   EditNoteOutlined as EditNoteOutlinedIcon,
@@ -73,10 +75,13 @@ import {
 
 import {
   Config,
+  ConfigPipeline,
+  ConfigPipelineStep,
   ConfigPlugin,
   ConfigPluginOption,
   ConfigPluginOptionBoolean,
   ConfigPluginOptionDouble,
+  ConfigPluginOptionEnum,
   ConfigPluginOptionInteger,
   ConfigPluginOptionString,
   ConfigPluginOptionType,
@@ -98,7 +103,12 @@ function isConfigPluginOptionEmpty(option: ConfigPluginOption) {
     return false;
   }
   switch (option.type) {
+    case ConfigPluginOptionType.Date:
+    case ConfigPluginOptionType.Enum:
+    case ConfigPluginOptionType.Path:
+    case ConfigPluginOptionType.Regex:
     case ConfigPluginOptionType.String:
+    case ConfigPluginOptionType.Text:
       const defaultValue = (option as ConfigPluginOptionString).defaultValue;
       return (
         defaultValue === null ||
@@ -118,12 +128,23 @@ function Settings(args: Args) {
     React.useState<HTMLElement | null>(null);
   const [depth, setDepth] = React.useState(-1);
   const [dirty, setDirty] = React.useState(false);
+  const [dialogPipelineOpen, setDialogPipelineOpen] = React.useState(false);
   const [dialogPluginOpen, setDialogPluginOpen] = React.useState(false);
   const [extensionText, setExtensionText] = React.useState("");
   const [filterByExtensions, setFilterByExtensions] = React.useState(true);
   const [includeDirectories, setIncludeDirectories] = React.useState(false);
   const [monacoEditor, setMonacoEditor] =
     React.useState<editor.IStandaloneCodeEditor | null>(null);
+  const [pipelines, setPipelines] = React.useState<ConfigPipeline[]>([]);
+  const [pipelineDescription, setPipelineDescription] = React.useState("");
+  const [pipelineDirty, setPipelineDirty] = React.useState(false);
+  const [pipelineIndex, setPipelineIndex] = React.useState(-1);
+  const [pipelineName, setPipelineName] = React.useState("");
+  const [pipelineSteps, setPipelineSteps] = React.useState<
+    ConfigPipelineStep[]
+  >([]);
+  const [pipelineStepsErrorMessage, setPipelineStepsErrorMessage] =
+    React.useState("");
   const [plugins, setPlugins] = React.useState<ConfigPlugin[]>([]);
   const [pluginCode, setPluginCode] = React.useState("");
   const [pluginCodeErrorMessage, setPluginCodeErrorMessage] =
@@ -164,10 +185,18 @@ function Settings(args: Args) {
         extensionText,
         filterByExtensions,
         includeDirectories,
+        pipelines,
         plugins
       );
     },
-    [depth, extensionText, filterByExtensions, includeDirectories, plugins]
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
   );
 
   const onChangeDepth = React.useCallback(
@@ -179,10 +208,18 @@ function Settings(args: Args) {
         extensionText,
         filterByExtensions,
         includeDirectories,
+        pipelines,
         plugins
       );
     },
-    [depth, extensionText, filterByExtensions, includeDirectories, plugins]
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
   );
 
   function onChangeExtensionText(e: React.ChangeEvent<HTMLInputElement>) {
@@ -199,10 +236,18 @@ function Settings(args: Args) {
         extensionText,
         newFilterByExtensions,
         includeDirectories,
+        pipelines,
         plugins
       );
     },
-    [depth, extensionText, filterByExtensions, includeDirectories, plugins]
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
   );
 
   const onChangeIncludeDirectories = React.useCallback(
@@ -214,10 +259,71 @@ function Settings(args: Args) {
         extensionText,
         filterByExtensions,
         newIncludeDirectories,
+        pipelines,
         plugins
       );
     },
-    [depth, extensionText, filterByExtensions, includeDirectories, plugins]
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
+  );
+
+  const onChangePipelineDescription = React.useCallback(
+    (event: React.ChangeEvent<HTMLInputElement | HTMLTextAreaElement>) => {
+      setPipelineDirty(true);
+      setPipelineDescription(event.target.value);
+    },
+    [pipelineDescription]
+  );
+
+  const onChangePipelineName = React.useCallback(
+    (event: React.ChangeEvent<HTMLInputElement | HTMLTextAreaElement>) => {
+      setPipelineDirty(true);
+      setPipelineName(event.target.value);
+    },
+    [pipelineName]
+  );
+
+  const onChangePipelineStepOption = React.useCallback(
+    (index: number, name: string, value: boolean | number | string) => {
+      if (index >= 0 && index < pipelineSteps.length) {
+        const newPipelineStep: ConfigPipelineStep = {
+          ...pipelineSteps[index],
+          options: { ...pipelineSteps[index].options, [name]: value },
+        };
+        setPipelineDirty(true);
+        setPipelineSteps([
+          ...pipelineSteps.slice(0, index),
+          newPipelineStep,
+          ...pipelineSteps.slice(index + 1),
+        ]);
+      }
+    },
+    [pipelineSteps]
+  );
+
+  const onChangePipelineStepPluginId = React.useCallback(
+    (event: SelectChangeEvent, index: number) => {
+      if (index >= 0 && index < pipelineSteps.length) {
+        // The options of the previous plugin do not apply to the new one.
+        const newPipelineStep: ConfigPipelineStep = {
+          options: {},
+          pluginId: event.target.value,
+        };
+        setPipelineDirty(true);
+        setPipelineSteps([
+          ...pipelineSteps.slice(0, index),
+          newPipelineStep,
+          ...pipelineSteps.slice(index + 1),
+        ]);
+      }
+    },
+    [pipelineSteps]
   );
 
   function onChangePluginCode(
@@ -280,7 +386,12 @@ function Settings(args: Args) {
             (newPluginOption as ConfigPluginOptionInteger).defaultValue =
               Math.round(Number(event.target.value));
             break;
+          case ConfigPluginOptionType.Date:
+          case ConfigPluginOptionType.Enum:
+          case ConfigPluginOptionType.Path:
+          case ConfigPluginOptionType.Regex:
           case ConfigPluginOptionType.String:
+          case ConfigPluginOptionType.Text:
             (newPluginOption as ConfigPluginOptionString).defaultValue = String(
               event.target.value
             );
@@ -357,12 +468,28 @@ function Settings(args: Args) {
               ...pluginOptions.slice(index + 1),
             ]);
             break;
+          case ConfigPluginOptionType.Enum:
+            setPluginOptions([
+              ...pluginOptions.slice(0, index),
+              {
+                name: pluginOptions[index].name,
+                type: ConfigPluginOptionType.Enum,
+                defaultValue: "",
+                values: [],
+              } as ConfigPluginOptionEnum,
+              ...pluginOptions.slice(index + 1),
+            ]);
+            break;
+          case ConfigPluginOptionType.Date:
+          case ConfigPluginOptionType.Path:
+          case ConfigPluginOptionType.Regex:
           case ConfigPluginOptionType.String:
+          case ConfigPluginOptionType.Text:
             setPluginOptions([
               ...pluginOptions.slice(0, index),
               {
                 name: pluginOptions[index].name,
-                type: ConfigPluginOptionType.String,
+                type: event.target.value as ConfigPluginOptionType,
                 defaultValue: "",
               },
               ...pluginOptions.slice(index + 1),
@@ -374,12 +501,54 @@ function Settings(args: Args) {
     [pluginOptions]
   );
 
+  const onChangePluginOptionValues = React.useCallback(
+    (
+      event: React.ChangeEvent<HTMLInputElement | HTMLTextAreaElement>,
+      index: number
+    ) => {
+      if (index >= 0 && index < pluginOptions.length) {
+        const values = event.target.value
+          .split(",")
+          .map((value) => value.trim())
+          .filter((value) => value.length > 0);
+        const pluginOption = pluginOptions[index] as ConfigPluginOptionEnum;
+        const newPluginOption: ConfigPluginOptionEnum = {
+          ...pluginOption,
+          defaultValue: values.includes(pluginOption.defaultValue)
+            ? pluginOption.defaultValue
+            : values[0] ?? "",
+          values,
+        };
+        setPluginDirty(true);
+        setPluginOptions([
+          ...pluginOptions.slice(0, index),
+          newPluginOption,
+          ...pluginOptions.slice(index + 1),
+        ]);
+      }
+    },
+    [pluginOptions]
+  );
+
   function onClickButtonAddABuiltInPlugin(
     event: React.MouseEvent<HTMLButtonElement, MouseEvent>
   ) {
     setBuiltInPluginsMenuAnchorEl(event.currentTarget);
   }
 
+  function onClickButtonAddPipelineStep(
+    _event: React.MouseEvent<HTMLButtonElement, MouseEvent>
+  ) {
+    if (plugins.length > 0) {
+      setPipelineDirty(true);
+      setPipelineStepsErrorMessage("");
+      setPipelineSteps([
+        ...pipelineSteps,
+        { options: {}, pluginId: plugins[0].id },
+      ]);
+    }
+  }
+
   function onClickButtonAddPluginOption(
     _event: React.MouseEvent<HTMLButtonElement, MouseEvent>
   ) {
@@ -394,6 +563,18 @@ function Settings(args: Args) {
     ]);
   }
 
+  function onClickButtonCreateANewPipeline(
+    _event: React.MouseEvent<HTMLButtonElement, MouseEvent>
+  ): void {
+    setPipelineDescription("");
+    setPipelineIndex(-1);
+    setPipelineName("");
+    setPipelineSteps([]);
+    setPipelineDirty(false);
+    setDialogPipelineOpen(true);
+    args.setGlobalKeyboardShortcutsEnabled(false);
+  }
+
   function onClickButtonCreateANewPlugin(
     _event: React.MouseEvent<HTMLButtonElement, MouseEvent>
   ): void {
@@ -406,21 +587,72 @@ function Settings(args: Args) {
     args.setGlobalKeyboardShortcutsEnabled(false);
   }
 
+  const onClickButtonDeletePipeline = React.useCallback(
+    (index: number) => {
+      if (index >= 0 && index < pipelines.length) {
+        const newPipelines = pipelines.filter((_, i) => i !== index);
+        setPipelines(newPipelines);
+        setConfig(
+          depth,
+          extensionText,
+          filterByExtensions,
+          includeDirectories,
+          newPipelines,
+          plugins
+        );
+      }
+    },
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
+  );
+
+  const onClickButtonDeletePipelineStep = React.useCallback(
+    (index: number) => {
+      if (index >= 0 && index < pipelineSteps.length) {
+        setPipelineDirty(true);
+        setPipelineSteps(pipelineSteps.filter((_, i) => i !== index));
+      }
+    },
+    [pipelineSteps]
+  );
+
   const onClickButtonDeletePlugin = React.useCallback(
     (index: number) => {
       if (index >= 0 && index < plugins.length) {
         const newPlugins = plugins.filter((_, i) => i !== index);
+        // The steps running the deleted plugin are removed from the pipelines.
+        const newPipelines = pipelines.map((pipeline) => ({
+          ...pipeline,
+          steps: pipeline.steps.filter(
+            (step) => step.pluginId !== plugins[index].id
+          ),
+        }));
+        setPipelines(newPipelines);
         setPlugins(newPlugins);
         setConfig(
           depth,
           extensionText,
           filterByExtensions,
           includeDirectories,
+          newPipelines,
           newPlugins
         );
       }
     },
-    [depth, extensionText, filterByExtensions, includeDirectories, plugins]
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
   );
 
   const onClickButtonDeletePluginOption = React.useCallback(
@@ -433,6 +665,21 @@ function Settings(args: Args) {
     [pluginOptions]
   );
 
+  const onClickButtonEditPipeline = React.useCallback(
+    (index: number) => {
+      if (index >= 0 && index < pipelines.length) {
+        setPipelineDescription(pipelines[index].description);
+        setPipelineIndex(index);
+        setPipelineName(pipelines[index].name);
+        setPipelineSteps(pipelines[index].steps);
+        setPipelineDirty(false);
+        setDialogPipelineOpen(true);
+        args.setGlobalKeyboardShortcutsEnabled(false);
+      }
+    },
+    [pipelines]
+  );
+
   const onClickButtonEditPlugin = React.useCallback(
     (index: number) => {
       if (index >= 0 && index < plugins.length) {
@@ -455,6 +702,24 @@ function Settings(args: Args) {
     }
   }
 
+  const onClickButtonMovePipelineStep = React.useCallback(
+    (index: number, newIndex: number) => {
+      if (
+        index >= 0 &&
+        index < pipelineSteps.length &&
+        newIndex >= 0 &&
+        newIndex < pipelineSteps.length
+      ) {
+        const newPipelineSteps = [...pipelineSteps];
+        newPipelineSteps[index] = pipelineSteps[newIndex];
+        newPipelineSteps[newIndex] = pipelineSteps[index];
+        setPipelineDirty(true);
+        setPipelineSteps(newPipelineSteps);
+      }
+    },
+    [pipelineSteps]
+  );
+
   const onClickButtonSave = React.useCallback(() => {
     invoke<Config>("set_config", {
       config: args.config,
@@ -471,6 +736,28 @@ function Settings(args: Args) {
       });
   }, [args.config]);
 
+  const onClickDialogPipelineButtonCancel = React.useCallback(
+    async (_event: React.MouseEvent<HTMLButtonElement, MouseEvent>) => {
+      let confirmed = true;
+      if (pipelineDirty) {
+        confirmed = await confirm(
+          "Are you sure you want to discard your changes?",
+          {
+            title: "Discard changes?",
+            kind: "warning",
+          }
+        );
+      }
+      if (confirmed) {
+        setPipelineStepsErrorMessage("");
+        setDialogPipelineOpen(false);
+        args.setGlobalKeyboardShortcutsEnabled(true);
+        setPipelineDirty(false);
+      }
+    },
+    [pipelineDirty]
+  );
+
   const onClickDialogPluginButtonCancel = React.useCallback(
     async (_event: React.MouseEvent<HTMLButtonElement, MouseEvent>) => {
       let confirmed = true;
@@ -514,10 +801,18 @@ function Settings(args: Args) {
         extensionText,
         filterByExtensions,
         includeDirectories,
+        pipelines,
         newPlugins
       );
     },
-    [depth, extensionText, filterByExtensions, includeDirectories, plugins]
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      plugins,
+    ]
   );
 
   const onDragPlugin = React.useCallback(
@@ -578,6 +873,7 @@ function Settings(args: Args) {
                   extensionText,
                   filterByExtensions,
                   includeDirectories,
+                  pipelines,
                   newPlugins
                 );
               }
@@ -597,6 +893,7 @@ function Settings(args: Args) {
       extensionText,
       filterByExtensions,
       includeDirectories,
+      pipelines,
       plugins,
       pluginTableRowPosition,
       pluginTableRowHeight,
@@ -610,6 +907,55 @@ function Settings(args: Args) {
     setMonacoEditor(monacoEditor);
   }
 
+  const onSubmitDialogPipeline = React.useCallback(
+    (event: React.FormEvent<HTMLFormElement>) => {
+      event.preventDefault();
+      if (pipelineSteps.length > 0) {
+        const pipeline: ConfigPipeline = {
+          description: pipelineDescription,
+          id: pipelineIndex >= 0 ? pipelines[pipelineIndex].id : uuid.v4(),
+          name: pipelineName,
+          steps: pipelineSteps,
+        };
+        const newPipelines =
+          pipelineIndex >= 0
+            ? [
+                ...pipelines.slice(0, pipelineIndex),
+                pipeline,
+                ...pipelines.slice(pipelineIndex + 1),
+              ]
+            : [...pipelines, pipeline];
+        setPipelines(newPipelines);
+        setPipelineDirty(false);
+        setPipelineStepsErrorMessage("");
+        setDialogPipelineOpen(false);
+        setConfig(
+          depth,
+          extensionText,
+          filterByExtensions,
+          includeDirectories,
+          newPipelines,
+          plugins
+        );
+        args.setGlobalKeyboardShortcutsEnabled(true);
+      } else {
+        setPipelineStepsErrorMessage("Please add a step.");
+      }
+    },
+    [
+      depth,
+      extensionText,
+      filterByExtensions,
+      includeDirectories,
+      pipelines,
+      pipelineDescription,
+      pipelineIndex,
+      pipelineName,
+      pipelineSteps,
+      plugins,
+    ]
+  );
+
   const onSubmitDialogPlugin = React.useCallback(
     (event: React.FormEvent<HTMLFormElement>) => {
       event.preventDefault();
@@ -618,9 +964,13 @@ function Settings(args: Args) {
         const plugin: ConfigPlugin = {
           code,
           description: pluginDescription,
+          external: pluginIndex >= 0 ? plugins[pluginIndex].external : false,
           id: pluginIndex >= 0 ? plugins[pluginIndex].id : uuid.v4(),
           name: pluginName,
-          options: pluginOptions,
+          native: pluginIndex >= 0 ? plugins[pluginIndex].native : false,
+          options: pluginOptions.filter(
+            (option) => !isConfigPluginOptionEmpty(option)
+          ),
         };
         const newPlugins =
           pluginIndex >= 0
@@ -639,6 +989,7 @@ function Settings(args: Args) {
           extensionText,
           filterByExtensions,
           includeDirectories,
+          pipelines,
           newPlugins
         );
         args.setGlobalKeyboardShortcutsEnabled(true);
@@ -652,6 +1003,7 @@ function Settings(args: Args) {
       filterByExtensions,
       includeDirectories,
       monacoEditor,
+      pipelines,
       plugins,
       pluginCode,
       pluginDescription,
@@ -666,6 +1018,7 @@ function Settings(args: Args) {
     extensionText: string,
     filterByExtensions: boolean,
     includeDirectories: boolean,
+    pipelines: ConfigPipeline[],
     plugins: ConfigPlugin[]
   ) {
     const extensions = extensionText
@@ -676,7 +1029,12 @@ function Settings(args: Args) {
       depth,
       extensions,
       filterByExtensions,
+      groupCompanions: args.config?.groupCompanions ?? false,
+      hashes: args.config?.hashes ?? [],
       includeDirectories,
+      metadata: args.config?.metadata ?? [],
+      normalization: args.config?.normalization ?? null,
+      pipelines,
       plugins,
     };
     args.setConfig(config);
@@ -688,6 +1046,7 @@ function Settings(args: Args) {
       setDepth(args.config.depth);
       setExtensionText(args.config.extensions.join(", "));
       setIncludeDirectories(args.config.includeDirectories);
+      setPipelines(args.config.pipelines);
       setPlugins(args.config.plugins);
     }
   }, [args.config]);
@@ -912,6 +1271,94 @@ function Settings(args: Args) {
             </Stack>
           </CardActions>
         </Card>
+        <Card>
+          <CardHeader
+            title="Pipelines"
+            titleTypographyProps={{ variant: "h5" }}
+            sx={{ pt: "10px", pb: "0px" }}
+          />
+          <CardContent>
+            {(() =>
+              pipelines.length > 0 ? (
+                <TableContainer component={Paper}>
+                  <Table size="small">
+                    <TableHead>
+                      <TableRow>
+                        <TableCell>Name</TableCell>
+                        <TableCell>Description</TableCell>
+                        <TableCell>Steps</TableCell>
+                        <TableCell
+                          align="center"
+                          sx={{ width: 80, maxWidth: 80 }}
+                        >
+                          Action
+                        </TableCell>
+                      </TableRow>
+                    </TableHead>
+                    <TableBody>
+                      {pipelines.map((pipeline, index) => (
+                        <TableRow key={pipeline.id}>
+                          <TableCell>{pipeline.name}</TableCell>
+                          <TableCell>{pipeline.description}</TableCell>
+                          <TableCell>
+                            {pipeline.steps
+                              .map(
+                                (step) =>
+                                  plugins.find(
+                                    (plugin) => plugin.id === step.pluginId
+                                  )?.name ?? step.pluginId
+                              )
+                              .join(" → ")}
+                          </TableCell>
+                          <TableCell>
+                            <Stack direction="row" spacing={0}>
+                              <IconButton
+                                aria-label="Edit"
+                                color="primary"
+                                onClick={() => {
+                                  onClickButtonEditPipeline(index);
+                                }}
+                              >
+                                <EditNoteOutlinedIcon fontSize="small" />
+                              </IconButton>
+                              <IconButton
+                                aria-label="Delete"
+                                color="primary"
+                                onClick={() => {
+                                  onClickButtonDeletePipeline(index);
+                                }}
+                              >
+                                <HighlightOffOutlinedIcon fontSize="small" />
+                              </IconButton>
+                            </Stack>
+                          </TableCell>
+                        </TableRow>
+                      ))}
+                    </TableBody>
+                  </Table>
+                </TableContainer>
+              ) : (
+                <></>
+              ))()}
+          </CardContent>
+          <CardActions disableSpacing sx={{ padding: "0px 15px 15px 15px" }}>
+            <Tooltip
+              arrow
+              title="Pipelines run the plugins in order in the target editor with each step working on the output of the previous one."
+            >
+              <Button
+                variant="outlined"
+                startIcon={<AddCircleOutlineOutlinedIcon />}
+                size="small"
+                fullWidth={false}
+                onClick={onClickButtonCreateANewPipeline}
+                sx={{ textTransform: "none" }}
+              >
+                Create a New Pipeline
+              </Button>
+            </Tooltip>
+          </CardActions>
+        </Card>
         <Button
           variant="outlined"
           startIcon={<SaveOutlinedIcon />}
@@ -938,7 +1385,9 @@ function Settings(args: Args) {
       >
         <DialogTitle id="plugin-dialog-title">
           {pluginIndex >= 0
-            ? `Edit Plugin: ${pluginName}`
+            ? `${
+                plugins[pluginIndex].external ? "View External" : "Edit"
+              } Plugin: ${pluginName}`
             : "Create a New Plugin"}
         </DialogTitle>
         <DialogContent>
@@ -1079,21 +1528,46 @@ function Settings(args: Args) {
                                   >
                                     Boolean
                                   </MenuItem>
+                                  <MenuItem
+                                    value={ConfigPluginOptionType.Date}
+                                  >
+                                    Date
+                                  </MenuItem>
                                   <MenuItem
                                     value={ConfigPluginOptionType.Double}
                                   >
                                     Double
                                   </MenuItem>
+                                  <MenuItem
+                                    value={ConfigPluginOptionType.Enum}
+                                  >
+                                    Enum
+                                  </MenuItem>
                                   <MenuItem
                                     value={ConfigPluginOptionType.Integer}
                                   >
                                     Integer
                                   </MenuItem>
+                                  <MenuItem
+                                    value={ConfigPluginOptionType.Path}
+                                  >
+                                    Path
+                                  </MenuItem>
+                                  <MenuItem
+                                    value={ConfigPluginOptionType.Regex}
+                                  >
+                                    Regex
+                                  </MenuItem>
                                   <MenuItem
                                     value={ConfigPluginOptionType.String}
                                   >
                                     String
                                   </MenuItem>
+                                  <MenuItem
+                                    value={ConfigPluginOptionType.Text}
+                                  >
+                                    Text
+                                  </MenuItem>
                                 </Select>
                               </TableCell>
                               <TableCell>
@@ -1144,15 +1618,60 @@ function Settings(args: Args) {
                                       label="false"
                                     />
                                   </RadioGroup>
+                                ) : option.type ===
+                                  ConfigPluginOptionType.Enum ? (
+                                  <Stack direction="row" spacing={1}>
+                                    <TextField
+                                      fullWidth
+                                      size="small"
+                                      defaultValue={(
+                                        option as ConfigPluginOptionEnum
+                                      ).values.join(", ")}
+                                      onBlur={(event) => {
+                                        onChangePluginOptionValues(
+                                          event,
+                                          index
+                                        );
+                                      }}
+                                      placeholder="Values"
+                                    />
+                                    <Select
+                                      fullWidth
+                                      size="small"
+                                      value={option.defaultValue as string}
+                                      onChange={(event) => {
+                                        onChangePluginOptionDefaultValueTextField(
+                                          event as React.ChangeEvent<HTMLInputElement>,
+                                          index
+                                        );
+                                      }}
+                                    >
+                                      {(option as ConfigPluginOptionEnum).values.map(
+                                        (value) => (
+                                          <MenuItem key={value} value={value}>
+                                            {value}
+                                          </MenuItem>
+                                        )
+                                      )}
+                                    </Select>
+                                  </Stack>
                                 ) : (
                                   <TextField
                                     fullWidth
                                     size="small"
+                                    multiline={
+                                      option.type === ConfigPluginOptionType.Text
+                                    }
                                     type={
                                       option.type ===
-                                      ConfigPluginOptionType.String
-                                        ? "text"
-                                        : "number"
+                                        ConfigPluginOptionType.Double ||
+                                      option.type ===
+                                        ConfigPluginOptionType.Integer
+                                        ? "number"
+                                        : option.type ===
+                                          ConfigPluginOptionType.Date
+                                        ? "date"
+                                        : "text"
                                     }
                                     value={option.defaultValue}
                                     onChange={(event) => {
@@ -1167,9 +1686,11 @@ function Settings(args: Args) {
                                         style: {
                                           textAlign:
                                             option.type ===
-                                            ConfigPluginOptionType.String
-                                              ? "left"
-                                              : "right",
+                                              ConfigPluginOptionType.Double ||
+                                            option.type ===
+                                              ConfigPluginOptionType.Integer
+                                              ? "right"
+                                              : "left",
                                         },
                                       },
                                     }}
@@ -1249,6 +1770,7 @@ function Settings(args: Args) {
           <Button
             type="submit"
             variant="outlined"
+            disabled={pluginIndex >= 0 && plugins[pluginIndex].external}
             startIcon={<AddBoxOutlinedIcon />}
             sx={{ textTransform: "none" }}
           >
@@ -1265,6 +1787,304 @@ function Settings(args: Args) {
           </Button>
         </DialogActions>
       </Dialog>
+      <Dialog
+        open={dialogPipelineOpen}
+        aria-labelledby="pipeline-dialog-title"
+        aria-describedby="pipeline-dialog-description"
+        fullWidth={true}
+        maxWidth="lg"
+        PaperProps={{
+          component: "form",
+          onSubmit: onSubmitDialogPipeline,
+        }}
+        sx={{ maxHeight: "calc(100vh - 50px)" }}
+      >
+        <DialogTitle id="pipeline-dialog-title">
+          {pipelineIndex >= 0
+            ? `Edit Pipeline: ${pipelineName}`
+            : "Create a New Pipeline"}
+        </DialogTitle>
+        <DialogContent>
+          <Stack spacing={2} sx={{ mt: "10px" }}>
+            <TextField
+              autoFocus
+              required
+              margin="dense"
+              id="pipeline-name"
+              name="pipeline-name"
+              label="Name"
+              type="text"
+              size="small"
+              fullWidth
+              variant="outlined"
+              value={pipelineName}
+              onChange={onChangePipelineName}
+            />
+            <TextField
+              margin="dense"
+              id="pipeline-description"
+              name="pipeline-description"
+              label="Description"
+              type="text"
+              fullWidth
+              variant="outlined"
+              size="small"
+              multiline
+              minRows={3}
+              maxRows={5}
+              value={pipelineDescription}
+              onChange={onChangePipelineDescription}
+            />
+            <fieldset
+              style={{ borderRadius: "5px", border: "1px solid lightgray" }}
+            >
+              <legend
+                style={{
+                  padding: "0px 5px",
+                  color: pipelineStepsErrorMessage === "" ? "gray" : "red",
+                  fontFamily: "roboto",
+                  fontSize: "12px",
+                }}
+              >
+                {pipelineStepsErrorMessage === ""
+                  ? "Steps *"
+                  : `Steps * (${pipelineStepsErrorMessage})`}
+              </legend>
+              <Stack spacing={2}>
+                <Stack direction="row" spacing={2}>
+                  <Button
+                    variant="outlined"
+                    startIcon={<AddBoxOutlinedIcon />}
+                    disabled={plugins.length === 0}
+                    sx={{ textTransform: "none" }}
+                    onClick={onClickButtonAddPipelineStep}
+                  >
+                    Add a Step
+                  </Button>
+                </Stack>
+                {(() =>
+                  pipelineSteps.length > 0 ? (
+                    <TableContainer component={Paper}>
+                      <Table size="small">
+                        <TableHead>
+                          <TableRow>
+                            <TableCell>Plugin</TableCell>
+                            <TableCell>Options</TableCell>
+                            <TableCell align="center">Action</TableCell>
+                          </TableRow>
+                        </TableHead>
+                        <TableBody>
+                          {pipelineSteps.map((step, index) => (
+                            <TableRow key={index}>
+                              <TableCell sx={{ width: 250 }}>
+                                <Select
+                                  fullWidth
+                                  value={step.pluginId}
+                                  size="small"
+                                  onChange={(event) =>
+                                    onChangePipelineStepPluginId(event, index)
+                                  }
+                                >
+                                  {plugins.map((plugin) => (
+                                    <MenuItem key={plugin.id} value={plugin.id}>
+                                      {plugin.name}
+                                    </MenuItem>
+                                  ))}
+                                </Select>
+                              </TableCell>
+                              <TableCell>
+                                <Stack direction="row" spacing={1}>
+                                  {plugins
+                                    .find(
+                                      (plugin) => plugin.id === step.pluginId
+                                    )
+                                    ?.options.map((option) => {
+                                      const value =
+                                        step.options[option.name] ??
+                                        option.defaultValue;
+                                      switch (option.type) {
+                                        case ConfigPluginOptionType.Boolean:
+                                          return (
+                                            <FormControlLabel
+                                              key={option.name}
+                                              control={
+                                                <Checkbox
+                                                  size="small"
+                                                  checked={value as boolean}
+                                                  onChange={(event) => {
+                                                    onChangePipelineStepOption(
+                                                      index,
+                                                      option.name,
+                                                      event.target.checked
+                                                    );
+                                                  }}
+                                                />
+                                              }
+                                              label={option.name}
+                                            />
+                                          );
+                                        case ConfigPluginOptionType.Enum:
+                                          return (
+                                            <TextField
+                                              key={option.name}
+                                              fullWidth
+                                              select
+                                              size="small"
+                                              label={option.name}
+                                              value={value}
+                                              onChange={(event) => {
+                                                onChangePipelineStepOption(
+                                                  index,
+                                                  option.name,
+                                                  event.target.value
+                                                );
+                                              }}
+                                            >
+                                              {(
+                                                option as ConfigPluginOptionEnum
+                                              ).values.map((enumValue) => (
+                                                <MenuItem
+                                                  key={enumValue}
+                                                  value={enumValue}
+                                                >
+                                                  {enumValue}
+                                                </MenuItem>
+                                              ))}
+                                            </TextField>
+                                          );
+                                        case ConfigPluginOptionType.Double:
+                                        case ConfigPluginOptionType.Integer:
+                                          return (
+                                            <TextField
+                                              key={option.name}
+                                              fullWidth
+                                              size="small"
+                                              type="number"
+                                              label={option.name}
+                                              value={value}
+                                              onChange={(event) => {
+                                                const newValue = Number(
+                                                  event.target.value
+                                                );
+                                                onChangePipelineStepOption(
+                                                  index,
+                                                  option.name,
+                                                  option.type ===
+                                                    ConfigPluginOptionType.Integer
+                                                    ? Math.round(newValue)
+                                                    : newValue
+                                                );
+                                              }}
+                                            />
+                                          );
+                                        default:
+                                          return (
+                                            <TextField
+                                              key={option.name}
+                                              fullWidth
+                                              size="small"
+                                              type={
+                                                option.type ===
+                                                ConfigPluginOptionType.Date
+                                                  ? "date"
+                                                  : "text"
+                                              }
+                                              multiline={
+                                                option.type ===
+                                                ConfigPluginOptionType.Text
+                                              }
+                                              label={option.name}
+                                              value={value}
+                                              onChange={(event) => {
+                                                onChangePipelineStepOption(
+                                                  index,
+                                                  option.name,
+                                                  event.target.value
+                                                );
+                                              }}
+                                            />
+                                          );
+                                      }
+                                    })}
+                                </Stack>
+                              </TableCell>
+                              <TableCell align="center" sx={{ width: 130 }}>
+                                <Stack direction="row" spacing={0}>
+                                  <IconButton
+                                    aria-label="Move Up"
+                                    color="primary"
+                                    disabled={index === 0}
+                                    onClick={() => {
+                                      onClickButtonMovePipelineStep(
+                                        index,
+                                        index - 1
+                                      );
+                                    }}
+                                  >
+                                    <ArrowUpwardOutlinedIcon fontSize="small" />
+                                  </IconButton>
+                                  <IconButton
+                                    aria-label="Move Down"
+                                    color="primary"
+                                    disabled={
+                                      index === pipelineSteps.length - 1
+                                    }
+                                    onClick={() => {
+                                      onClickButtonMovePipelineStep(
+                                        index,
+                                        index + 1
+                                      );
+                                    }}
+                                  >
+                                    <ArrowDownwardOutlinedIcon
+                                      fontSize="small"
+                                    />
+                                  </IconButton>
+                                  <IconButton
+                                    aria-label="Delete"
+                                    color="primary"
+                                    onClick={() => {
+                                      onClickButtonDeletePipelineStep(index);
+                                    }}
+                                  >
+                                    <HighlightOffOutlinedIcon
+                                      fontSize="small"
+                                    />
+                                  </IconButton>
+                                </Stack>
+                              </TableCell>
+                            </TableRow>
+                          ))}
+                        </TableBody>
+                      </Table>
+                    </TableContainer>
+                  ) : (
+                    <></>
+                  ))()}
+              </Stack>
+            </fieldset>
+          </Stack>
+        </DialogContent>
+        <DialogActions>
+          <Button
+            type="submit"
+            variant="outlined"
+            startIcon={<AddBoxOutlinedIcon />}
+            sx={{ textTransform: "none" }}
+          >
+            {pipelineIndex >= 0 ? "Update" : "Create"}
+          </Button>
+          <Button
+            variant="outlined"
+            startIcon={<DisabledByDefaultOutlinedIcon />}
+            onClick={onClickDialogPipelineButtonCancel}
+            color="error"
+            sx={{ textTransform: "none" }}
+          >
+            Cancel
+          </Button>
+        </DialogActions>
+      </Dialog>
     </React.Fragment>
   );
 }
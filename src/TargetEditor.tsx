@@ -15,6 +15,7 @@
  *   limitations under the License.
  */
 
+import { invoke } from "@tauri-apps/api/core";
 import { readText, writeText } from "@tauri-apps/plugin-clipboard-manager";
 
 import React from "react";
@@ -27,14 +28,18 @@ import {
   DialogActions,
   DialogContent,
   DialogTitle,
+  FormControl,
   FormControlLabel,
   Grow,
+  InputLabel,
   MenuItem,
   MenuList,
   Paper,
   Popper,
   Radio,
   RadioGroup,
+  Select,
+  SelectChangeEvent,
   Stack,
   Table,
   TableBody,
@@ -47,6 +52,7 @@ import {
   Typography,
 } from "@mui/material";
 import {
+  AccountTreeOutlined as AccountTreeOutlinedIcon,
   ArrowDropDown as ArrowDropDownIcon,
   ContentCopyOutlined as ContentCopyOutlinedIcon,
   ContentPasteOutlined as ContentPasteOutlinedIcon,
@@ -65,12 +71,22 @@ import { initVimMode } from "monaco-vim";
 import {
   Config,
   ConfigPlugin,
+  ConfigPluginOptionEnum,
   ConfigPluginOptionType,
   Item,
   Notification,
   NotificationType,
+  PipelineStep,
 } from "./lib/Protocol";
-import { runPlugin } from "./lib/PluginRunner";
+import {
+  runNativePlugin,
+  runPipeline,
+  runPlugin,
+  splitTargetPaths,
+  toTargetLine,
+  toTargetPath,
+  TRASH_MARKER,
+} from "./lib/PluginRunner";
 
 export interface Args {
   config: Config | null;
@@ -109,22 +125,29 @@ function CustomOption(args: CustomOptionArgs) {
     (event: React.FormEvent<HTMLFormElement>) => {
       event.preventDefault();
       if (args.monacoEditor && args.plugin) {
-        try {
-          args.monacoEditor.setValue(
-            runPlugin(
-              args.plugin,
-              options,
-              args.items,
-              args.monacoEditor.getValue()
-            )
-          );
-          args.onClickSave();
-        } catch (error) {
-          args.setNotification({
-            message: `${error}`,
-            type: NotificationType.Error,
+        const monacoEditor = args.monacoEditor;
+        const plugin = args.plugin;
+        invoke("validate_plugin_options", { id: plugin.id, options })
+          .then(() =>
+            plugin.native
+              ? runNativePlugin(
+                  plugin,
+                  options,
+                  args.items,
+                  monacoEditor.getValue()
+                )
+              : runPlugin(plugin, options, args.items, monacoEditor.getValue())
+          )
+          .then((value) => {
+            monacoEditor.setValue(value);
+            args.onClickSave();
+          })
+          .catch((error) => {
+            args.setNotification({
+              message: `${error}`,
+              type: NotificationType.Error,
+            });
           });
-        }
       }
       args.setDialogPluginOptionsOpen(false);
     },
@@ -189,7 +212,9 @@ function CustomOption(args: CustomOptionArgs) {
                       {args.plugin.options.map((option, index) => (
                         <TableRow key={option.name}>
                           <TableCell>
-                            <Typography>{option.name}</Typography>
+                            <Tooltip arrow title={option.description ?? ""}>
+                              <Typography>{option.name}</Typography>
+                            </Tooltip>
                           </TableCell>
                           <TableCell>
                             <Typography>{option.type}</Typography>
@@ -256,6 +281,44 @@ function CustomOption(args: CustomOptionArgs) {
                                       placeholder="Default Value"
                                     />
                                   );
+                                case ConfigPluginOptionType.Date:
+                                  return (
+                                    <TextField
+                                      fullWidth
+                                      size="small"
+                                      type="date"
+                                      value={options[option.name]}
+                                      onChange={(event) => {
+                                        onChangePluginOption(
+                                          option.name,
+                                          event.target.value
+                                        );
+                                      }}
+                                    />
+                                  );
+                                case ConfigPluginOptionType.Enum:
+                                  return (
+                                    <TextField
+                                      fullWidth
+                                      select
+                                      size="small"
+                                      value={options[option.name]}
+                                      onChange={(event) => {
+                                        onChangePluginOption(
+                                          option.name,
+                                          event.target.value
+                                        );
+                                      }}
+                                    >
+                                      {(
+                                        option as ConfigPluginOptionEnum
+                                      ).values.map((value) => (
+                                        <MenuItem key={value} value={value}>
+                                          {value}
+                                        </MenuItem>
+                                      ))}
+                                    </TextField>
+                                  );
                                 case ConfigPluginOptionType.Integer:
                                   return (
                                     <TextField
@@ -275,6 +338,10 @@ function CustomOption(args: CustomOptionArgs) {
                                   return (
                                     <TextField
                                       fullWidth
+                                      multiline={
+                                        option.type ===
+                                        ConfigPluginOptionType.Text
+                                      }
                                       size="small"
                                       value={options[option.name]}
                                       onChange={(event) => {
@@ -328,6 +395,7 @@ function TargetEditor(args: Args) {
     React.useState(false);
   const [monacoEditor, setMonacoEditor] =
     React.useState<editor.IStandaloneCodeEditor | null>(null);
+  const [pipelineIndex, setPipelineIndex] = React.useState(0);
   const pluginMenuRef = React.useRef<HTMLDivElement>(null);
   const [pluginIndex, setPluginIndex] = React.useState(0);
   const [pluginMenuOpen, setPluginMenuOpen] = React.useState(false);
@@ -343,17 +411,27 @@ function TargetEditor(args: Args) {
           plugin.options.forEach((option) => {
             options[option.name] = option.defaultValue;
           });
-          try {
-            monacoEditor.setValue(
-              runPlugin(plugin, options, args.items, monacoEditor.getValue())
-            );
-            onClickSave();
-          } catch (error) {
-            args.setNotification({
-              message: `${error}`,
-              type: NotificationType.Error,
+          invoke("validate_plugin_options", { id: plugin.id, options })
+            .then(() =>
+              plugin.native
+                ? runNativePlugin(
+                    plugin,
+                    options,
+                    args.items,
+                    monacoEditor.getValue()
+                  )
+                : runPlugin(plugin, options, args.items, monacoEditor.getValue())
+            )
+            .then((value) => {
+              monacoEditor.setValue(value);
+              onClickSave();
+            })
+            .catch((error) => {
+              args.setNotification({
+                message: `${error}`,
+                type: NotificationType.Error,
+              });
             });
-          }
         }
       }
     },
@@ -414,23 +492,30 @@ function TargetEditor(args: Args) {
 
   const onClickSave = React.useCallback(() => {
     if (monacoEditor) {
-      const lines = monacoEditor
-        .getValue()
-        .split(/[\r\n]+/)
-        .map((line) => line.trim());
+      const lines = splitTargetPaths(monacoEditor.getValue()).map((line) =>
+        line.trim()
+      );
       console.log(args.items);
+      const emptyLineIndex = lines.findIndex((line) => line === "");
       if (lines.length != args.items.length) {
         args.setNotification({
           message: `Line count ${lines.length} mismatches with item count ${args.items.length}.`,
           type: NotificationType.Error,
         });
+      } else if (emptyLineIndex >= 0) {
+        args.setNotification({
+          message: `Line ${emptyLineIndex + 1} is empty. Use ${TRASH_MARKER} to send the item to the trash.`,
+          type: NotificationType.Error,
+        });
       } else {
         args.setItems(
           args.items.map((item, i) => {
             return {
               sourcePath: item.sourcePath,
-              targetPath: lines[i],
+              targetPath: toTargetPath(lines[i]),
               type: item.type,
+              metadata: item.metadata,
+              companions: item.companions,
             };
           })
         );
@@ -438,6 +523,30 @@ function TargetEditor(args: Args) {
     }
   }, [args.items, monacoEditor]);
 
+  const onClickRunPipeline = React.useCallback(() => {
+    const pipeline = args.config?.pipelines[pipelineIndex];
+    if (monacoEditor && pipeline) {
+      invoke<PipelineStep[]>("resolve_pipeline", { id: pipeline.id })
+        .then((steps) =>
+          runPipeline(steps, args.items, monacoEditor.getValue())
+        )
+        .then((value) => {
+          monacoEditor.setValue(value);
+          onClickSave();
+        })
+        .catch((error) => {
+          args.setNotification({
+            message: `${error}`,
+            type: NotificationType.Error,
+          });
+        });
+    }
+  }, [args.config, args.items, monacoEditor, pipelineIndex]);
+
+  function onChangePipelineIndex(event: SelectChangeEvent<number>) {
+    setPipelineIndex(Number(event.target.value));
+  }
+
   function onClickVimMode() {
     if (vim === null) {
       setVim(
@@ -552,13 +661,48 @@ function TargetEditor(args: Args) {
               </Popper>
             </React.Fragment>
           ) : null}
+          {args.config?.pipelines && args.config.pipelines.length > 0 ? (
+            <Stack direction="row" spacing={1}>
+              <FormControl size="small" sx={{ minWidth: "160px" }}>
+                <InputLabel id="pipeline-label">Pipeline</InputLabel>
+                <Select
+                  labelId="pipeline-label"
+                  label="Pipeline"
+                  value={pipelineIndex}
+                  onChange={onChangePipelineIndex}
+                >
+                  {args.config.pipelines.map((pipeline, index) => (
+                    <MenuItem key={pipeline.id} value={index}>
+                      {pipeline.name}
+                    </MenuItem>
+                  ))}
+                </Select>
+              </FormControl>
+              <Tooltip
+                arrow
+                title="Run the plugins of the pipeline in order with each step working on the output of the previous one."
+              >
+                <Button
+                  variant="outlined"
+                  size="small"
+                  startIcon={<AccountTreeOutlinedIcon />}
+                  onClick={onClickRunPipeline}
+                  sx={{ textTransform: "none" }}
+                >
+                  Run Pipeline
+                </Button>
+              </Tooltip>
+            </Stack>
+          ) : null}
         </Stack>
         <Editor
           height="calc(100vh - 200px)"
           language="plaintext"
           onMount={onMountEditor}
           defaultValue=""
-          value={args.items.map((item) => item.targetPath).join("\n")}
+          value={args.items
+            .map((item) => toTargetLine(item.targetPath))
+            .join("\n")}
           theme="light"
           options={{
             fontSize: 16,
@@ -22,16 +22,40 @@ import React from "react";
 import {
   Publish as PublishIcon,
   Recycling as RecyclingIcon,
+  RestoreFromTrash as RestoreFromTrashIcon,
 } from "@mui/icons-material";
-import { Box, Button, Stack, Tooltip } from "@mui/material";
+import {
+  Box,
+  Button,
+  Checkbox,
+  FormControlLabel,
+  Stack,
+  Tooltip,
+} from "@mui/material";
 
-import { Item, Notification, NotificationType } from "./lib/Protocol";
+import {
+  Item,
+  Notification,
+  NotificationType,
+  RenameOptions,
+  RenameResult,
+  RestoreResult,
+  UnicodeNormalization,
+} from "./lib/Protocol";
 
 export interface Args {
   clear: () => void;
   items: Item[];
+  normalization: UnicodeNormalization | null;
+  pruneDirectories: boolean;
+  roots: string[];
   setItems: React.Dispatch<React.SetStateAction<Item[]>>;
   setNotification: React.Dispatch<React.SetStateAction<Notification | null>>;
+  setPruneDirectories: React.Dispatch<React.SetStateAction<boolean>>;
+  setTrashedPaths: React.Dispatch<React.SetStateAction<string[]>>;
+  setTruncate: React.Dispatch<React.SetStateAction<boolean>>;
+  trashedPaths: string[];
+  truncate: boolean;
 }
 
 function Tools(args: Args) {
@@ -40,12 +64,36 @@ function Tools(args: Args) {
   }, [args.items]);
 
   const onClickRename = React.useCallback(() => {
-    invoke<number>("rename_items", {
+    const options: RenameOptions = {
+      normalization: args.normalization,
+      profile: null,
+      pruneDirectories: args.pruneDirectories,
+      roots: args.roots,
+      truncate: args.truncate,
+    };
+    invoke<RenameResult>("rename_items", {
       items: args.items,
+      options,
     })
       .then((value) => {
+        args.setTrashedPaths(value.trashedPaths);
+        if (value.error) {
+          args.setNotification({
+            message: `${value.error} after renaming ${value.count} item(s) and trashing ${value.trashedPaths.length} item(s)`,
+            type: NotificationType.Error,
+          });
+          return;
+        }
         args.setNotification({
-          message: `Renamed ${value} item(s) successfully`,
+          message: `Renamed ${value.count} item(s)${
+            value.trashedPaths.length > 0
+              ? `, trashed ${value.trashedPaths.length} item(s)`
+              : ""
+          }${
+            value.removedDirectories.length > 0
+              ? ` and removed ${value.removedDirectories.length} empty directories`
+              : ""
+          } successfully`,
           type: NotificationType.Success,
         });
       })
@@ -55,7 +103,51 @@ function Tools(args: Args) {
           type: NotificationType.Error,
         });
       });
-  }, [args.items]);
+  }, [
+    args.items,
+    args.normalization,
+    args.pruneDirectories,
+    args.roots,
+    args.truncate,
+  ]);
+
+  const onClickRestore = React.useCallback(() => {
+    invoke<RestoreResult>("restore_from_trash", { paths: args.trashedPaths })
+      .then((value) => {
+        // The items not restored are kept so that they can be restored again.
+        const restoredPathSet = new Set(value.restoredPaths);
+        args.setTrashedPaths(
+          args.trashedPaths.filter((path) => !restoredPathSet.has(path))
+        );
+        if (value.error) {
+          args.setNotification({
+            message: `${value.error} after restoring ${value.restoredPaths.length} item(s) from the trash`,
+            type: NotificationType.Error,
+          });
+          return;
+        }
+        args.setNotification({
+          message: `Restored ${value.restoredPaths.length} item(s) from the trash successfully`,
+          type: NotificationType.Success,
+        });
+      })
+      .catch((error) => {
+        args.setNotification({
+          message: `${error}`,
+          type: NotificationType.Error,
+        });
+      });
+  }, [args.trashedPaths]);
+
+  function onChangePruneDirectories(
+    _event: React.ChangeEvent<HTMLInputElement>
+  ) {
+    args.setPruneDirectories(!args.pruneDirectories);
+  }
+
+  function onChangeTruncate(_event: React.ChangeEvent<HTMLInputElement>) {
+    args.setTruncate(!args.truncate);
+  }
 
   return (
     <Box>
@@ -84,6 +176,46 @@ function Tools(args: Args) {
             Clear
           </Button>
         </Tooltip>
+        <Tooltip
+          arrow
+          title="Restore the items sent to the trash by the last rename."
+        >
+          <Button
+            variant="outlined"
+            startIcon={<RestoreFromTrashIcon />}
+            onClick={onClickRestore}
+            size="small"
+            disabled={args.trashedPaths.length === 0}
+            sx={{ textTransform: "none" }}
+          >
+            Restore
+          </Button>
+        </Tooltip>
+        <Tooltip
+          arrow
+          title="Remove the source directories which become empty after the rename. The dropped directories are never removed."
+        >
+          <FormControlLabel
+            control={
+              <Checkbox
+                checked={args.pruneDirectories}
+                onChange={onChangePruneDirectories}
+              />
+            }
+            label="Prune Empty Directories"
+          />
+        </Tooltip>
+        <Tooltip
+          arrow
+          title="Truncate the names over the length limits of the file system. The extension and the numbering suffix like (2) or _001 are preserved."
+        >
+          <FormControlLabel
+            control={
+              <Checkbox checked={args.truncate} onChange={onChangeTruncate} />
+            }
+            label="Truncate Long Names"
+          />
+        </Tooltip>
       </Stack>
     </Box>
   );
@@ -15,11 +15,12 @@
  *   limitations under the License.
  */
 
+import { invoke } from "@tauri-apps/api/core";
 import {
   delimiter as tauriApiPathDelimiter,
   sep as tauriApiPathSep,
 } from "@tauri-apps/api/path";
-import { ConfigPlugin, Item } from "./Protocol";
+import { ConfigPlugin, Item, PipelineStep } from "./Protocol";
 
 const delimiter = tauriApiPathDelimiter();
 const sep = tauriApiPathSep();
@@ -59,6 +60,36 @@ const path = Object.freeze({
   sep,
 });
 
+/**
+ * The line of an item to send to the trash in the target editor. It is never an absolute path.
+ */
+export const TRASH_MARKER = "<trash>";
+
+/**
+ * Splits the target paths by line. Only the single trailing empty line is dropped.
+ */
+export function splitTargetPaths(targetPathsString: string): string[] {
+  const targetPaths = targetPathsString.split(/\r?\n/g);
+  if (targetPaths.length > 0 && targetPaths[targetPaths.length - 1] === "") {
+    targetPaths.pop();
+  }
+  return targetPaths;
+}
+
+/**
+ * Converts a line to a target path where the trash marker becomes the empty target path.
+ */
+export function toTargetPath(line: string): string {
+  return line.trim() === TRASH_MARKER ? "" : line;
+}
+
+/**
+ * Converts a target path to a line where the empty target path becomes the trash marker.
+ */
+export function toTargetLine(targetPath: string): string {
+  return targetPath === "" ? TRASH_MARKER : targetPath;
+}
+
 export function runPlugin(
   plugin: ConfigPlugin,
   options: Record<string, boolean | number | string>,
@@ -66,20 +97,26 @@ export function runPlugin(
   targetPathsString: string
 ): string {
   console.log(`Running plugin ${plugin.name}.`);
-  const targetPaths = targetPathsString
-    .split(/[\r\n]+/g)
-    .filter((targetPath) => targetPath.length > 0);
+  const targetPaths = splitTargetPaths(targetPathsString);
   if (targetPaths.length != items.length) {
     throw new Error(
       `The number of target paths ${targetPaths.length} does not match the number of items ${items.length}.`
     );
   }
   const $sourceItems = Object.freeze(
-    items.map((item) => Object.freeze({ sourcePath: item.sourcePath }))
+    items.map((item) =>
+      Object.freeze({
+        sourcePath: item.sourcePath,
+        metadata: Object.freeze({ ...item.metadata }),
+      })
+    )
   );
+  // The target items are seeded from the editor text instead of the items,
+  // as in runNativePlugin, so that a pipeline step works on the output of the
+  // previous one and the unsaved edits are kept.
   const $targetItems = Object.freeze(
-    items.map((item) => {
-      return { targetPath: item.targetPath };
+    targetPaths.map((targetPath) => {
+      return { targetPath };
     })
   );
   const $options = Object.freeze(options);
@@ -97,5 +134,56 @@ export function runPlugin(
     $modules,
   });
   fn($args);
-  return $targetItems.map((targetItem) => targetItem.targetPath).join("\n");
+  // The items sent to the trash are kept as they are.
+  return $targetItems
+    .map((targetItem, index) =>
+      toTargetPath(targetPaths[index]) === ""
+        ? TRASH_MARKER
+        : targetItem.targetPath
+    )
+    .join("\n");
+}
+
+export async function runNativePlugin(
+  plugin: ConfigPlugin,
+  options: Record<string, boolean | number | string>,
+  items: Item[],
+  targetPathsString: string
+): Promise<string> {
+  console.log(`Running native plugin ${plugin.name}.`);
+  const targetPaths = splitTargetPaths(targetPathsString);
+  if (targetPaths.length != items.length) {
+    throw new Error(
+      `The number of target paths ${targetPaths.length} does not match the number of items ${items.length}.`
+    );
+  }
+  const newItems = await invoke<Item[]>("run_native_plugin", {
+    id: plugin.id,
+    options,
+    items: items.map((item, index) => ({
+      ...item,
+      targetPath: toTargetPath(targetPaths[index]),
+    })),
+  });
+  return newItems.map((item) => toTargetLine(item.targetPath)).join("\n");
+}
+
+export async function runPipeline(
+  steps: PipelineStep[],
+  items: Item[],
+  targetPathsString: string
+): Promise<string> {
+  console.log(`Running pipeline with ${steps.length} step(s).`);
+  let currentTargetPathsString = targetPathsString;
+  for (const step of steps) {
+    currentTargetPathsString = step.plugin.native
+      ? await runNativePlugin(
+          step.plugin,
+          step.options,
+          items,
+          currentTargetPathsString
+        )
+      : runPlugin(step.plugin, step.options, items, currentTargetPathsString);
+  }
+  return currentTargetPathsString;
 }
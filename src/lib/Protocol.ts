@@ -19,20 +19,40 @@ export interface Config {
   depth: number;
   extensions: string[];
   filterByExtensions: boolean;
+  groupCompanions: boolean;
+  hashes: HashAlgorithm[];
   includeDirectories: boolean;
+  metadata: MetadataType[];
+  normalization: UnicodeNormalization | null;
+  pipelines: ConfigPipeline[];
   plugins: ConfigPlugin[];
 }
 
+export interface ConfigPipeline {
+  description: string;
+  id: string;
+  name: string;
+  steps: ConfigPipelineStep[];
+}
+
+export interface ConfigPipelineStep {
+  options: Record<string, boolean | number | string>;
+  pluginId: string;
+}
+
 export interface ConfigPlugin {
   code: string;
   description: string;
+  external: boolean;
   id: string;
   name: string;
+  native: boolean;
   options: ConfigPluginOption[];
 }
 
 export interface ConfigPluginOption<T = boolean | number | string> {
   defaultValue: T;
+  description?: string;
   name: string;
   type: ConfigPluginOptionType;
 }
@@ -40,26 +60,106 @@ export interface ConfigPluginOption<T = boolean | number | string> {
 export interface ConfigPluginOptionBoolean extends ConfigPluginOption<boolean> {
 }
 
+export interface ConfigPluginOptionDate extends ConfigPluginOption<string> {
+}
+
 export interface ConfigPluginOptionDouble extends ConfigPluginOption<number> {
+  max?: number | null;
+  min?: number | null;
+  step?: number | null;
+}
+
+export interface ConfigPluginOptionEnum extends ConfigPluginOption<string> {
+  values: string[];
 }
 
 export interface ConfigPluginOptionInteger extends ConfigPluginOption<number> {
+  max?: number | null;
+  min?: number | null;
+  step?: number | null;
+}
+
+export interface ConfigPluginOptionPath extends ConfigPluginOption<string> {
+}
+
+export interface ConfigPluginOptionRegex extends ConfigPluginOption<string> {
 }
 
 export interface ConfigPluginOptionString extends ConfigPluginOption<string> {
 }
 
+export interface ConfigPluginOptionText extends ConfigPluginOption<string> {
+}
+
 export enum ConfigPluginOptionType {
   Boolean = "Boolean",
+  Date = "Date",
   Double = "Double",
+  Enum = "Enum",
   Integer = "Integer",
+  Path = "Path",
+  Regex = "Regex",
   String = "String",
+  Text = "Text",
+}
+
+export enum CrcTagAction {
+  Keep = "Keep",
+  Add = "Add",
+  Strip = "Strip",
+}
+
+export enum DuplicateAction {
+  Keep = "Keep",
+  Move = "Move",
+  Suffix = "Suffix",
+}
+
+export interface DuplicateGroup {
+  hash: string;
+  items: Item[];
+  size: number;
+}
+
+export interface DuplicateOptions {
+  action: DuplicateAction;
+  algorithm: HashAlgorithm;
+  folder: string;
+  suffix: string;
+}
+
+export enum FileSystemProfile {
+  Fat = "FAT",
+  Linux = "Linux",
+  MacOs = "macOS",
+  Windows = "Windows",
+}
+
+export interface FlattenOptions {
+  root: string;
+  separator: string;
+}
+
+export enum HashAlgorithm {
+  Blake3 = "Blake3",
+  Crc32 = "Crc32",
+  Md5 = "Md5",
+  Sha1 = "Sha1",
+  Sha256 = "Sha256",
+}
+
+export interface HashProgress {
+  completed: number;
+  sourcePath: string;
+  total: number;
 }
 
 export interface Item {
   sourcePath: string;
   targetPath: string;
   type: ItemType;
+  metadata: Record<string, string>;
+  companions: string[];
 }
 
 export enum ItemType {
@@ -68,6 +168,129 @@ export enum ItemType {
   Unknown = "Unknown",
 }
 
+export enum MetadataType {
+  Audio = "Audio",
+  Image = "Image",
+  Media = "Media",
+  Video = "Video",
+}
+
+export interface NormalizationDuplicateGroup {
+  items: Item[];
+  path: string;
+}
+
+export interface OrganizeOptions {
+  dateFormat: string;
+  field: string;
+  root: string;
+  rule: OrganizeRule;
+}
+
+export enum OrganizeRule {
+  Category = "Category",
+  ExifDate = "ExifDate",
+  FirstLetter = "FirstLetter",
+  Metadata = "Metadata",
+  Modified = "Modified",
+}
+
+export interface PipelineStep {
+  options: Record<string, boolean | number | string>;
+  plugin: ConfigPlugin;
+}
+
+export interface RegexRenameOptions {
+  caseInsensitive: boolean;
+  maxReplacements: number;
+  pattern: string;
+  replacement: string;
+  scope: RegexRenameScope;
+}
+
+export interface RegexRenamePreview {
+  after: string;
+  before: string;
+  matches: TextSpan[];
+  sourcePath: string;
+}
+
+export enum RegexRenameScope {
+  Name = "Name",
+  Extension = "Extension",
+  FileName = "FileName",
+  FullPath = "FullPath",
+}
+
+export interface RemoveDirectoriesResult {
+  errors: string[];
+  removedDirectories: string[];
+}
+
+export interface RenameOptions {
+  normalization: UnicodeNormalization | null;
+  profile: FileSystemProfile | null;
+  pruneDirectories: boolean;
+  roots: string[];
+  truncate: boolean;
+}
+
+export interface RenameResult {
+  count: number;
+  error: string | null;
+  removedDirectories: string[];
+  trashedPaths: string[];
+}
+
+export interface RestoreResult {
+  error: string | null;
+  restoredPaths: string[];
+}
+
+export interface SanitizeOptions {
+  profile: FileSystemProfile;
+  replacement: string;
+  validateOnly: boolean;
+}
+
+export interface SanitizeResult {
+  issues: string[];
+  sourcePath: string;
+  targetPath: string;
+}
+
+export interface ScanOptions {
+  depth: number;
+  extensions: string[];
+  groupCompanions: boolean;
+  hashes: HashAlgorithm[];
+  includeDirectory: boolean;
+  metadata: MetadataType[];
+  normalization: UnicodeNormalization | null;
+}
+
+export enum SubtitleMatchMode {
+  Episode = "Episode",
+  Order = "Order",
+  Similarity = "Similarity",
+}
+
+export interface SubtitleMatchOptions {
+  language: string;
+  mode: SubtitleMatchMode;
+}
+
+export interface TagWriteResult {
+  error: string | null;
+  sourcePath: string;
+  tags: Record<string, string>;
+}
+
+export interface TextSpan {
+  end: number;
+  start: number;
+}
+
 export interface Notification {
   message: string;
   type: NotificationType;
@@ -77,3 +300,45 @@ export enum NotificationType {
   Error = "Error",
   Success = "Success",
 }
+
+export enum UnflattenMode {
+  Count = "Count",
+  Prefix = "Prefix",
+}
+
+export interface UnflattenOptions {
+  count: number;
+  mode: UnflattenMode;
+  separator: string;
+  stripPrefix: boolean;
+}
+
+export enum UnicodeNormalization {
+  Nfc = "NFC",
+  Nfd = "NFD",
+}
+
+export interface VerifyOptions {
+  action: CrcTagAction;
+}
+
+export interface VerifyResult {
+  actual: string | null;
+  error: string | null;
+  expected: string | null;
+  sourcePath: string;
+  status: VerifyStatus;
+  targetPath: string;
+}
+
+export enum VerifyStatus {
+  Match = "Match",
+  Mismatch = "Mismatch",
+  Missing = "Missing",
+  Error = "Error",
+}
+
+export interface WriteTagsOptions {
+  dryRun: boolean;
+  pattern: string;
+}